@@ -23,7 +23,7 @@ fn main() -> io::Result<()> {
     extreme::run(async {
         // kernel 5.5 and later support TCP accept
         loop {
-            let stream = ring.accept(&acceptor).await?;
+            let (stream, _) = ring.accept(&acceptor).await?;
             match proxy(&ring, &stream, &stream).await {
                 Ok(()) => eprintln!("client disconnected"),
                 Err(e) => eprintln!("client failure: {}", e),
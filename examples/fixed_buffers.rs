@@ -0,0 +1,75 @@
+use std::{
+    fs::OpenOptions,
+    io::{IoSliceMut, Result},
+    os::unix::fs::OpenOptionsExt,
+};
+
+const CHUNK_SIZE: u64 = 4096;
+const CHUNKS: u64 = 64 * 1024;
+
+// `O_DIRECT` requires all reads and writes
+// to be aligned to the block device's block
+// size. 4096 might not be the best, or even
+// a valid one, for yours!
+#[repr(align(4096))]
+struct Aligned([u8; CHUNK_SIZE as usize]);
+
+fn main() -> Result<()> {
+    // start the ring
+    let ring = rio::new().expect("create uring");
+
+    // open input file, with `O_DIRECT` set
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .custom_flags(libc::O_DIRECT)
+        .open("file")
+        .expect("open file");
+    file.set_len(CHUNK_SIZE * CHUNKS)?;
+
+    // registered buffers must never move,
+    // so we leak this one.
+    let buf: &'static mut Aligned = Box::leak(Box::new(
+        Aligned([0; CHUNK_SIZE as usize]),
+    ));
+
+    // read every chunk into an unregistered
+    // buffer, which the kernel has to map
+    // for each read.
+    let pre = std::time::Instant::now();
+    for i in 0..CHUNKS {
+        ring.read_at(&file, &buf.0, i * CHUNK_SIZE)
+            .wait()?;
+    }
+    let unregistered = pre.elapsed();
+
+    // now register the buffer so that the
+    // kernel maps it only once.
+    let bufs = [IoSliceMut::new(&mut buf.0)];
+    ring.register_buffers(&bufs)?;
+
+    let pre = std::time::Instant::now();
+    for i in 0..CHUNKS {
+        // the buffer is only looked at once the
+        // read is done.
+        unsafe {
+            ring.read_fixed(
+                &file,
+                0,
+                0,
+                CHUNK_SIZE as usize,
+                i * CHUNK_SIZE,
+            )
+        }
+        .wait()?;
+    }
+    let registered = pre.elapsed();
+
+    ring.unregister_buffers()?;
+
+    dbg!(unregistered, registered);
+
+    Ok(())
+}
@@ -21,7 +21,7 @@ fn counter() -> io::Result<()> {
 fn serve(ring: rio::Rio, acceptor: TcpListener) -> io::Result<()> {
     extreme::run(async move {
         loop {
-            let stream = ring.accept(&acceptor).wait()?;
+            let (stream, _) = ring.accept(&acceptor).wait()?;
             let mut buf = RESP;
             while !buf.is_empty() {
                 let written_bytes =
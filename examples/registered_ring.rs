@@ -0,0 +1,37 @@
+/*
+ * This example submits and waits for NOPs one at a
+ * time, which enters the kernel for every one of
+ * them, before and after registering the ring's
+ * fd with the submitting thread.
+ */
+
+use std::io::Result;
+
+const NOPS: usize = 1_000_000;
+
+fn main() -> Result<()> {
+    // start the ring
+    let ring = rio::new().expect("create uring");
+
+    let pre = std::time::Instant::now();
+    for _ in 0..NOPS {
+        ring.nop().wait()?;
+    }
+    let unregistered = pre.elapsed();
+
+    // from now on, this thread enters the ring
+    // by its registered index.
+    ring.register_ring_fd()?;
+
+    let pre = std::time::Instant::now();
+    for _ in 0..NOPS {
+        ring.nop().wait()?;
+    }
+    let registered = pre.elapsed();
+
+    ring.unregister_ring_fd()?;
+
+    dbg!(unregistered, registered);
+
+    Ok(())
+}
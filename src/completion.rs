@@ -1,20 +1,27 @@
 use std::{
+    collections::VecDeque,
     future::Future,
     io,
     marker::PhantomData,
+    os::unix::io::RawFd,
     pin::Pin,
     sync::{Arc, Condvar, Mutex},
     task::{Context, Poll, Waker},
+    time::{Duration, Instant},
 };
 
 use super::{
-    io_uring::io_uring_cqe, FromCqe, Measure, Uring, M,
+    io_uring::{
+        __kernel_timespec, io_uring_cqe, IoPriority,
+        PipeLease, IOSQE_ASYNC,
+    },
+    FromCqe, Measure, Uring, M,
 };
 
 #[derive(Debug)]
-struct CompletionState {
+pub(crate) struct CompletionState {
     done: bool,
-    item: Option<io::Result<io_uring_cqe>>,
+    items: VecDeque<io::Result<io_uring_cqe>>,
     waker: Option<Waker>,
 }
 
@@ -22,14 +29,75 @@ impl Default for CompletionState {
     fn default() -> CompletionState {
         CompletionState {
             done: false,
-            item: None,
+            items: VecDeque::new(),
             waker: None,
         }
     }
 }
 
+/// Memory that the kernel writes results into,
+/// owned by a `Completion` so that it stays
+/// allocated at a stable address until the
+/// operation is done.
+#[derive(Debug)]
+pub(crate) enum Pinned {
+    None,
+    Statx(Box<libc::statx>),
+    SockAddr(
+        Box<(libc::sockaddr_storage, libc::socklen_t)>,
+    ),
+    Timespec(Box<__kernel_timespec>),
+    /// Only held so that the pipe is recycled once
+    /// the operation is done with it.
+    Pipe(#[allow(dead_code)] PipeLease),
+    /// The iovec array that a vectored operation
+    /// reads, built from slices that are borrowed for
+    /// the lifetime of the `Completion`.
+    Iovecs(Vec<io::IoSlice<'static>>),
+    /// The registered file table of the ring, whose
+    /// slot an operation changes, updated once it
+    /// succeeds, along with that slot, if it is known
+    /// before the operation completes.
+    FileTable(Arc<Mutex<Vec<RawFd>>>, Option<u32>),
+    /// The registered file table of the ring, and
+    /// the fds that an update puts into its slots
+    /// from the given one on, which the kernel reads
+    /// from here, applied to the table once the
+    /// update succeeds.
+    FilesUpdate(Arc<Mutex<Vec<RawFd>>>, u32, Vec<RawFd>),
+    /// The states of operations linked ahead of this
+    /// one, whose results are done by the time this
+    /// one completes.
+    Outcomes(Vec<Arc<Mutex<CompletionState>>>),
+}
+
+impl Pinned {
+    /// How many of the operations in `Outcomes`
+    /// succeeded.
+    pub(crate) fn succeeded(&self) -> usize {
+        if let Pinned::Outcomes(states) = self {
+            states
+                .iter()
+                .filter(|state| {
+                    matches!(
+                        state.lock().unwrap().items.front(),
+                        Some(Ok(_))
+                    )
+                })
+                .count()
+        } else {
+            0
+        }
+    }
+}
+
 /// A Future value which may or may not be filled
 ///
+/// It may be waited on with `wait`, or awaited
+/// under any executor, in which case the reaper
+/// thread wakes the awaiting task as soon as it
+/// reaps the operation's completion.
+///
 /// # Safety
 ///
 /// To prevent undefined behavior in the form of
@@ -38,12 +106,69 @@ impl Default for CompletionState {
 /// happen with `std::mem::forget`, cycles in
 /// `Arc` or `Rc`, and in other ways.
 #[derive(Debug)]
-pub struct Completion<'a, C: FromCqe> {
+pub struct Completion<'a, C> {
     lifetime: PhantomData<&'a C>,
     mu: Arc<Mutex<CompletionState>>,
     cv: Arc<Condvar>,
     uring: &'a Uring,
+    pub(crate) pinned: Pinned,
+    decode: fn(io_uring_cqe, &mut Pinned) -> C,
     pub(crate) sqe_id: u64,
+    pub(crate) user_data: u64,
+    /// A timeout bounding this operation, followed
+    /// by the cancellation it triggers when it could
+    /// not be linked to this operation directly.
+    pub(crate) timer: Vec<Completion<'a, ()>>,
+    /// The operations that were linked ahead of
+    /// this one, which complete before it does.
+    pub(crate) linked: Vec<Completion<'a, ()>>,
+    cancel_on_drop: bool,
+}
+
+/// A stream of results from a single multishot
+/// operation, which the kernel keeps producing
+/// until it decides to stop, or until the
+/// operation is canceled.
+///
+/// It can be consumed as a blocking `Iterator`,
+/// or polled from async code with `poll_next`,
+/// which has the signature of `Stream::poll_next`.
+///
+/// Once the kernel stops the operation, which it
+/// signals with a final result, the stream ends
+/// after yielding that result. Dropping the
+/// stream before then cancels the operation, and
+/// waits for the kernel to stop it.
+#[derive(Debug)]
+pub struct CompletionStream<'a, C> {
+    lifetime: PhantomData<&'a C>,
+    mu: Arc<Mutex<CompletionState>>,
+    cv: Arc<Condvar>,
+    uring: &'a Uring,
+    pub(crate) sqe_id: u64,
+    pub(crate) user_data: u64,
+}
+
+/// The result of an operation together with the
+/// `IORING_CQE_F_*` flags that the kernel completed
+/// it with, as returned by `Completion::with_flags`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Flagged<C> {
+    /// The result that the `Completion` itself
+    /// resolves to.
+    pub result: C,
+    /// The raw flags of the completion, such as
+    /// `rio::cqe_flags::IORING_CQE_F_SOCK_NONEMPTY`.
+    pub flags: u32,
+}
+
+/// A `Completion` that also hands back the flags
+/// that its operation completed with. It may be
+/// waited on with `wait`, or awaited, just like the
+/// `Completion` it was created from.
+#[derive(Debug)]
+pub struct WithFlags<'a, C> {
+    completion: Completion<'a, C>,
 }
 
 /// The completer side of the Future
@@ -54,9 +179,14 @@ pub struct Filler {
 }
 
 /// Create a new `Filler` and the `Completion`
-/// that will be filled by its completion.
-pub fn pair<'a, C: FromCqe>(
+/// that will be filled by its completion. The
+/// result is produced by `decode`, which may
+/// also read from the memory in `pinned` that
+/// the kernel wrote into.
+pub(crate) fn pair<'a, C>(
     uring: &'a Uring,
+    pinned: Pinned,
+    decode: fn(io_uring_cqe, &mut Pinned) -> C,
 ) -> (Completion<'a, C>, Filler) {
     let mu =
         Arc::new(Mutex::new(CompletionState::default()));
@@ -66,36 +196,214 @@ pub fn pair<'a, C: FromCqe>(
         mu: mu.clone(),
         cv: cv.clone(),
         sqe_id: 0,
+        user_data: 0,
         uring,
+        pinned,
+        decode,
+        timer: Vec::new(),
+        linked: Vec::new(),
+        cancel_on_drop: false,
     };
     let filler = Filler { mu, cv };
 
     (future, filler)
 }
 
-impl<'a, C: FromCqe> Completion<'a, C> {
+/// Create a `Completion` that already failed with
+/// `err`, for an operation whose arguments are
+/// rejected before anything is queued for it.
+pub(crate) fn failed<'a, C>(
+    uring: &'a Uring,
+    err: io::Error,
+) -> Completion<'a, C> {
+    let (future, filler) =
+        pair(uring, Pinned::None, |_, _| unreachable!());
+    filler.fill(Err(err));
+
+    future
+}
+
+/// Create a new `Filler` and the `CompletionStream`
+/// that will be filled by each of its completions.
+pub(crate) fn stream_pair<'a, C>(
+    uring: &'a Uring,
+) -> (CompletionStream<'a, C>, Filler) {
+    let mu =
+        Arc::new(Mutex::new(CompletionState::default()));
+    let cv = Arc::new(Condvar::new());
+    let stream = CompletionStream {
+        lifetime: PhantomData,
+        mu: mu.clone(),
+        cv: cv.clone(),
+        sqe_id: 0,
+        user_data: 0,
+        uring,
+    };
+    let filler = Filler { mu, cv };
+
+    (stream, filler)
+}
+
+impl<'a, C> Completion<'a, C> {
+    /// Asks the kernel to cancel the operation
+    /// behind this `Completion`, which then fails
+    /// with `ECANCELED`.
+    ///
+    /// The returned `Completion` fails with `ENOENT`
+    /// if the operation already completed, or with
+    /// `EALREADY` if it was already running and may
+    /// or may not still be interrupted.
+    ///
+    /// # Warning
+    ///
+    /// This only becomes usable on linux kernels
+    /// 5.5 and up.
+    pub fn cancel(&self) -> Completion<'a, ()> {
+        // if this fails, the operation stays queued
+        // ahead of the cancellation, which then finds
+        // it once both are submitted.
+        let _ = self.uring.ensure_submitted(self.sqe_id);
+
+        self.uring.cancel(self.user_data)
+    }
+
+    /// The state that the operation behind this
+    /// `Completion` is filled into, for recording in
+    /// `Pinned::Outcomes`.
+    pub(crate) fn state(
+        &self,
+    ) -> Arc<Mutex<CompletionState>> {
+        self.mu.clone()
+    }
+
+    /// The `user_data` that the operation behind this
+    /// `Completion` was submitted with, which can be
+    /// handed to `Uring::cancel`. It identifies this
+    /// operation until it completes, after which it
+    /// no longer matches anything in flight.
+    pub const fn token(&self) -> u64 {
+        self.user_data
+    }
+
     /// Block on the `Completion`'s completion
     /// or dropping of the `Filler`
-    pub fn wait(self) -> io::Result<C>
-    where
-        C: FromCqe,
-    {
-        self.wait_inner().unwrap()
-    }
-
-    fn wait_inner(&self) -> Option<io::Result<C>>
-    where
-        C: FromCqe,
-    {
-        debug_assert_ne!(
-            self.sqe_id,
-            0,
+    ///
+    /// If the operation can't be submitted, this
+    /// returns that error instead, such as
+    /// `RioError::SubmissionQueueFull`. The operation
+    /// then stays queued until a later submission
+    /// hands it to the kernel, which dropping the
+    /// `Completion` waits for.
+    pub fn wait(mut self) -> io::Result<C> {
+        self.wait_inner().unwrap().map(|f| f.result)
+    }
+
+    /// Makes dropping this `Completion` before it
+    /// completes cancel its operation, instead of
+    /// waiting for the operation to complete on its
+    /// own, which may take forever for something like
+    /// a read from a quiet socket. Either way, the
+    /// drop returns only once the kernel is done with
+    /// the operation's buffers, so this suits futures
+    /// that are dropped early by `?` or a `select`.
+    ///
+    /// # Warning
+    ///
+    /// Cancelling only becomes usable on linux
+    /// kernels 5.5 and up, on older ones the drop
+    /// keeps waiting for the operation.
+    pub const fn cancel_on_drop(
+        mut self,
+    ) -> Completion<'a, C> {
+        self.cancel_on_drop = true;
+        self
+    }
+
+    /// Turn this into a `WithFlags`, which resolves
+    /// to the result together with the flags of the
+    /// completion, for the features that report
+    /// through them, like `IORING_CQE_F_SOCK_NONEMPTY`
+    /// after a receive. An operation that fails only
+    /// returns its error.
+    pub const fn with_flags(self) -> WithFlags<'a, C> {
+        WithFlags { completion: self }
+    }
+
+    /// Block on the `Completion`'s completion for at
+    /// most `dur`, returning `Ok(None)` if it did not
+    /// complete in time.
+    ///
+    /// Since the kernel could otherwise keep writing
+    /// into the operation's buffers after they are
+    /// gone, an operation that times out is
+    /// cancelled, and this then waits until the
+    /// kernel is done with it, so there is nothing
+    /// left to clean up afterwards. An operation that
+    /// is already running when it is cancelled, like
+    /// a read from a regular file, is waited for,
+    /// and its result is returned if it still
+    /// succeeded.
+    ///
+    /// # Warning
+    ///
+    /// Cancelling only becomes usable on linux
+    /// kernels 5.5 and up, on older ones this keeps
+    /// waiting for the operation.
+    pub fn wait_timeout(
+        mut self,
+        dur: Duration,
+    ) -> io::Result<Option<C>> {
+        self.uring.ensure_submitted(self.sqe_id)?;
+
+        // a deadline too far out to represent is
+        // never reached.
+        let deadline = Instant::now().checked_add(dur);
+        let mut inner = self.mu.lock().unwrap();
+        while !inner.done {
+            let now = Instant::now();
+            inner = match deadline {
+                Some(at) if now >= at => break,
+                Some(at) => {
+                    self.cv
+                        .wait_timeout(inner, at - now)
+                        .unwrap()
+                        .0
+                }
+                None => self.cv.wait(inner).unwrap(),
+            };
+        }
+        let timed_out = !inner.done;
+        drop(inner);
+
+        if timed_out {
+            drop(self.uring.cancel(self.user_data));
+        }
+
+        match self.wait_inner().unwrap() {
+            Err(ref e)
+                if timed_out
+                    && e.raw_os_error()
+                        == Some(libc::ECANCELED) =>
+            {
+                Ok(None)
+            }
+            other => other.map(|f| Some(f.result)),
+        }
+    }
+
+    fn wait_inner(
+        &mut self,
+    ) -> Option<io::Result<Flagged<C>>> {
+        debug_assert!(
+            self.sqe_id != 0 || self.mu.lock().unwrap().done,
             "sqe_id was never filled-in for this Completion",
         );
 
-        self.uring
-            .ensure_submitted(self.sqe_id)
-            .expect("failed to submit SQE from wait_inner");
+        if let Err(e) =
+            self.uring.ensure_submitted(self.sqe_id)
+        {
+            return Some(Err(e));
+        }
 
         let _ = Measure::new(&M.wait);
 
@@ -105,58 +413,386 @@ impl<'a, C: FromCqe> Completion<'a, C> {
             inner = self.cv.wait(inner).unwrap();
         }
 
-        inner.item.take().map(|io_result| {
-            io_result.map(FromCqe::from_cqe)
+        let item = inner.items.pop_front();
+        drop(inner);
+
+        item.map(|io_result| self.finish(io_result))
+    }
+
+    fn poll_inner(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<Flagged<C>>> {
+        if let Err(e) =
+            self.uring.ensure_submitted(self.sqe_id)
+        {
+            return Poll::Ready(Err(e));
+        }
+
+        let mut state = self.mu.lock().unwrap();
+        if state.done && !state.items.is_empty() {
+            let item = state.items.pop_front().unwrap();
+            drop(state);
+
+            Poll::Ready(self.finish(item))
+        } else {
+            if !state.done {
+                state.waker = Some(cx.waker().clone());
+            }
+            Poll::Pending
+        }
+    }
+
+    /// Decodes the completion of the operation, once
+    /// the timeout set by `with_timeout` is done.
+    fn finish(
+        &mut self,
+        io_result: io::Result<io_uring_cqe>,
+    ) -> io::Result<Flagged<C>> {
+        let expired = self.finish_timer();
+        timed_out(io_result, expired).map(|cqe| Flagged {
+            result: (self.decode)(cqe, &mut self.pinned),
+            flags: cqe.flags,
         })
     }
+
+    /// Bound this operation by a timeout, after which
+    /// the kernel cancels it, and it fails with
+    /// `ETIMEDOUT`.
+    ///
+    /// This must be called before the operation is
+    /// waited on. If it is the last operation queued,
+    /// an `IORING_OP_LINK_TIMEOUT` is placed right
+    /// behind it, which passes on a `Link` ordering
+    /// of its own to the operation queued next. If it
+    /// was already submitted, or other operations
+    /// were queued after it, it is canceled by a
+    /// separate timeout instead, so queued operations
+    /// are never reordered.
+    ///
+    /// # Warning
+    ///
+    /// This only becomes usable on linux kernels
+    /// 5.5 and up.
+    pub fn with_timeout(
+        mut self,
+        dur: Duration,
+    ) -> Completion<'a, C> {
+        let uring = self.uring;
+        uring.link_timeout(&mut self, dur);
+        self
+    }
+
+    /// Bound this operation by an absolute deadline,
+    /// measured against the same monotonic clock as
+    /// `Instant`, after which the kernel cancels it,
+    /// and it fails with `ETIMEDOUT`. Unlike
+    /// `with_timeout`, this doesn't move the
+    /// deadline back by however long it takes to
+    /// submit the operation.
+    ///
+    /// The same rules as for `with_timeout` apply.
+    ///
+    /// # Warning
+    ///
+    /// This only becomes usable on linux kernels
+    /// 5.5 and up.
+    pub fn with_deadline(
+        mut self,
+        deadline: Instant,
+    ) -> Completion<'a, C> {
+        let uring = self.uring;
+        uring.link_deadline(&mut self, deadline);
+        self
+    }
+
+    /// Makes the kernel hand this operation to one of
+    /// its async worker threads right away, with
+    /// `IOSQE_ASYNC`, instead of first trying to
+    /// complete it inline while it is submitted. This
+    /// keeps an operation that is known to block, like
+    /// a read from a slow network filesystem, from
+    /// holding up the thread that submits it, at the
+    /// cost of a context switch for operations that
+    /// would have completed inline.
+    ///
+    /// This must be called before the operation is
+    /// waited on, and has no effect on operations that
+    /// were already submitted, which only happens
+    /// early when the submission queue was full.
+    ///
+    /// # Warning
+    ///
+    /// This only becomes usable on linux kernels
+    /// 5.6 and up.
+    pub fn async_hint(self) -> Completion<'a, C> {
+        self.uring
+            .add_sqe_flags(self.user_data, IOSQE_ASYNC);
+        self
+    }
+
+    /// Gives the read or write behind this
+    /// `Completion` the I/O priority `prio`, so that
+    /// the block layer can serve latency-critical
+    /// reads ahead of background scans on the same
+    /// device. How much this matters depends on the
+    /// device's I/O scheduler, and other operations
+    /// are left alone.
+    ///
+    /// Like `async_hint`, this must be called before
+    /// the operation is waited on. The operation
+    /// fails with `EINVAL` for a priority that the
+    /// kernel rejects, and with `EPERM` for the
+    /// `RealTime` class without the privileges for
+    /// it.
+    pub fn with_ioprio(
+        self,
+        prio: IoPriority,
+    ) -> Completion<'a, C> {
+        self.uring.set_sqe_ioprio(
+            self.user_data,
+            prio.to_ioprio(),
+        );
+        self
+    }
+
+    /// Waits for the timeout set by `with_timeout`,
+    /// which is quick once the operation is done,
+    /// and returns whether it expired.
+    fn finish_timer(&mut self) -> bool {
+        let mut timer = std::mem::take(&mut self.timer);
+        if timer.is_empty() {
+            return false;
+        }
+        let timeout = timer.remove(0);
+        if !timer.is_empty() {
+            // not linked, so it must be removed
+            drop(self.uring.timeout_remove(&timeout));
+        }
+        match timeout.wait() {
+            Ok(()) => true,
+            Err(e) => e.raw_os_error() == Some(libc::ETIME),
+        }
+    }
+}
+
+fn timed_out(
+    io_result: io::Result<io_uring_cqe>,
+    expired: bool,
+) -> io::Result<io_uring_cqe> {
+    match io_result {
+        Err(ref e)
+            if expired
+                && e.raw_os_error()
+                    == Some(libc::ECANCELED) =>
+        {
+            Err(io::Error::from_raw_os_error(
+                libc::ETIMEDOUT,
+            ))
+        }
+        other => other,
+    }
 }
 
-impl<'a, C: FromCqe> Drop for Completion<'a, C> {
+impl<'a, C> Drop for Completion<'a, C> {
     fn drop(&mut self) {
+        if self.cancel_on_drop
+            && !self.mu.lock().unwrap().done
+        {
+            drop(self.cancel());
+        }
+        wait_until_done(
+            self.uring,
+            self.sqe_id,
+            &self.mu,
+            &self.cv,
+        );
         self.wait_inner();
     }
 }
 
-impl<'a, C: FromCqe> Future for Completion<'a, C> {
+impl<'a, C> Future for Completion<'a, C> {
     type Output = io::Result<C>;
 
     fn poll(
         self: Pin<&mut Self>,
         cx: &mut Context<'_>,
     ) -> Poll<Self::Output> {
-        self.uring
-            .ensure_submitted(self.sqe_id)
-            .expect("failed to submit SQE from wait_inner");
+        self.get_mut()
+            .poll_inner(cx)
+            .map(|res| res.map(|f| f.result))
+    }
+}
+
+impl<'a, C> WithFlags<'a, C> {
+    /// Block on the completion, like
+    /// `Completion::wait`.
+    pub fn wait(mut self) -> io::Result<Flagged<C>> {
+        self.completion.wait_inner().unwrap()
+    }
+}
+
+impl<'a, C> Future for WithFlags<'a, C> {
+    type Output = io::Result<Flagged<C>>;
+
+    fn poll(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Self::Output> {
+        self.get_mut().completion.poll_inner(cx)
+    }
+}
+
+impl<'a, C: FromCqe> CompletionStream<'a, C> {
+    /// Returns `true` while the kernel keeps the
+    /// operation armed. Once it returns `false`, the
+    /// kernel has stopped the operation, and only
+    /// the results that are already queued will be
+    /// yielded before the stream ends, so it needs
+    /// to be submitted again to receive more.
+    pub fn is_armed(&self) -> bool {
+        !self.mu.lock().unwrap().done
+    }
+
+    /// Attempt to pull out the next result of
+    /// this stream, registering the current task
+    /// for wakeup if it is not available yet, and
+    /// returning `None` once the stream has ended.
+    pub fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<io::Result<C>>> {
+        let this = self.get_mut();
+
+        if let Err(e) =
+            this.uring.ensure_submitted(this.sqe_id)
+        {
+            return Poll::Ready(Some(Err(e)));
+        }
+
+        let mut state = this.mu.lock().unwrap();
+        if let Some(item) = state.items.pop_front() {
+            Poll::Ready(Some(item.map(C::from_cqe)))
+        } else if state.done {
+            Poll::Ready(None)
+        } else {
+            state.waker = Some(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+}
+
+impl<'a, C: FromCqe> Iterator for CompletionStream<'a, C> {
+    type Item = io::Result<C>;
+
+    /// Block until the next result of this stream
+    /// arrives, returning `None` once the stream
+    /// has ended.
+    fn next(&mut self) -> Option<io::Result<C>> {
+        if let Err(e) =
+            self.uring.ensure_submitted(self.sqe_id)
+        {
+            return Some(Err(e));
+        }
+
+        let _ = Measure::new(&M.wait);
 
         let mut state = self.mu.lock().unwrap();
-        if state.item.is_some() {
-            Poll::Ready(
-                state
-                    .item
-                    .take()
+
+        while state.items.is_empty() && !state.done {
+            state = self.cv.wait(state).unwrap();
+        }
+
+        state
+            .items
+            .pop_front()
+            .map(|io_result| io_result.map(C::from_cqe))
+    }
+}
+
+impl<'a, C> Drop for CompletionStream<'a, C> {
+    fn drop(&mut self) {
+        if !self.mu.lock().unwrap().done {
+            // the kernel would otherwise keep the
+            // operation armed indefinitely.
+            drop(self.uring.cancel(self.user_data));
+        }
+
+        wait_until_done(
+            self.uring,
+            self.sqe_id,
+            &self.mu,
+            &self.cv,
+        );
+    }
+}
+
+/// Blocks until the kernel is done with the
+/// operation `sqe_id`, which it may still use the
+/// memory of even after submitting it failed, since
+/// it then stays queued for a later submission.
+/// Submitting is retried until it succeeds, in case
+/// nothing else submits in the mean time.
+fn wait_until_done(
+    uring: &Uring,
+    sqe_id: u64,
+    mu: &Mutex<CompletionState>,
+    cv: &Condvar,
+) {
+    let mut submitted = false;
+    let mut state = mu.lock().unwrap();
+    while !state.done {
+        if !submitted {
+            drop(state);
+            submitted =
+                uring.ensure_submitted(sqe_id).is_ok();
+            state = mu.lock().unwrap();
+            if !submitted {
+                state = cv
+                    .wait_timeout(
+                        state,
+                        Duration::from_millis(1),
+                    )
                     .unwrap()
-                    .map(FromCqe::from_cqe),
-            )
-        } else {
-            if !state.done {
-                state.waker = Some(cx.waker().clone());
+                    .0;
             }
-            Poll::Pending
+            continue;
         }
+        state = cv.wait(state).unwrap();
     }
 }
 
 impl Filler {
     /// Complete the `Completion`
     pub fn fill(self, inner: io::Result<io_uring_cqe>) {
+        self.push(inner, true);
+    }
+
+    /// Hand an intermediate result of a multishot
+    /// operation to its `CompletionStream`, or the
+    /// first result of an operation that will
+    /// complete a second time to its `Completion`,
+    /// keeping this `Filler` around for the rest.
+    pub(crate) fn fill_more(
+        &self,
+        inner: io::Result<io_uring_cqe>,
+    ) {
+        self.push(inner, false);
+    }
+
+    fn push(
+        &self,
+        inner: io::Result<io_uring_cqe>,
+        done: bool,
+    ) {
         let mut state = self.mu.lock().unwrap();
 
         if let Some(waker) = state.waker.take() {
             waker.wake();
         }
 
-        state.item = Some(inner);
-        state.done = true;
+        state.items.push_back(inner);
+        state.done = done;
 
         self.cv.notify_all();
     }
@@ -0,0 +1,195 @@
+use std::sync::atomic::AtomicU16;
+
+use super::*;
+
+/// The running kernel needs to be at least this
+/// new to register a buffer ring.
+const MIN_KERNEL: (u32, u32) = (5, 19);
+
+/// The most entries that a buffer ring may have.
+const MAX_ENTRIES: u16 = 1 << 15;
+
+/// A ring of buffers that is shared with the
+/// kernel, from which operations that select a
+/// buffer from its group, like `recv_multi`,
+/// `recv_buf_select` and `read_at_buf_select`,
+/// take one without a syscall or an sqe for
+/// handing each buffer to the kernel, as
+/// `provide_buffers` needs.
+///
+/// Buffers are handed to the kernel with `provide`,
+/// and once an operation has selected one and its
+/// contents have been used, it is handed back the
+/// same way. The ring is unregistered when this is
+/// dropped.
+///
+/// # Examples
+///
+/// ```no_run
+/// let ring = rio::new().unwrap();
+/// let bufs = ring.register_buf_ring(7, 4).unwrap();
+///
+/// let mut storage =
+///     vec![vec![0_u8; 4096].into_boxed_slice(); 4];
+/// for (bid, buf) in storage.iter_mut().enumerate() {
+///     unsafe {
+///         bufs.provide(bid as u16, buf);
+///     }
+/// }
+///
+/// let listener =
+///     std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+/// let (socket, _) = listener.accept().unwrap();
+/// for arrival in ring.recv_multi(&socket, 7) {
+///     let (bid, len) = arrival.unwrap();
+///     let buf = &mut storage[usize::from(bid)];
+///     println!("got {:?}", &buf[..len]);
+///     unsafe {
+///         bufs.provide(bid, buf);
+///     }
+/// }
+/// ```
+#[derive(Debug)]
+pub struct BufRing {
+    ring: Rio,
+    base: *mut io_uring_buf,
+    mapping_len: usize,
+    entries: u16,
+    group_id: u16,
+    /// The tail that the next `provide` publishes,
+    /// which only this side of the ring advances.
+    tail: Mutex<u16>,
+}
+
+#[allow(unsafe_code)]
+unsafe impl Send for BufRing {}
+
+#[allow(unsafe_code)]
+unsafe impl Sync for BufRing {}
+
+impl BufRing {
+    /// Maps an empty ring of `entries` buffers, which
+    /// must be a power of two no larger than 32768,
+    /// and registers it with `ring` as the group
+    /// `group_id`.
+    pub(crate) fn register(
+        ring: &Rio,
+        group_id: u16,
+        entries: u16,
+    ) -> io::Result<BufRing> {
+        if !entries.is_power_of_two()
+            || entries > MAX_ENTRIES
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "a BufRing needs a power of two \
+                     between 1 and {} entries.",
+                    MAX_ENTRIES,
+                ),
+            ));
+        }
+
+        let mapping_len = usize::from(entries)
+            * size_of::<io_uring_buf>();
+        let base = anonymous_mmap(mapping_len, 0)?;
+
+        let reg = io_uring_buf_reg {
+            ring_addr: base as u64,
+            ring_entries: u32::from(entries),
+            bgid: group_id,
+            ..io_uring_buf_reg::default()
+        };
+
+        if let Err(e) = ring.register_pbuf_ring(&reg) {
+            #[allow(unsafe_code)]
+            unsafe {
+                libc::munmap(base.cast(), mapping_len);
+            }
+            let (running, release) = kernel_version()?;
+            if e.raw_os_error() == Some(libc::EINVAL)
+                && running < MIN_KERNEL
+            {
+                return Err(io::Error::new(
+                    io::ErrorKind::Other,
+                    format!(
+                        "registering a buffer ring needs \
+                         linux {}.{} or newer, but the \
+                         running kernel is {}.",
+                        MIN_KERNEL.0, MIN_KERNEL.1, release,
+                    ),
+                ));
+            }
+            return Err(e);
+        }
+
+        Ok(BufRing {
+            ring: ring.clone(),
+            base: base.cast(),
+            mapping_len,
+            entries,
+            group_id,
+            tail: Mutex::new(0),
+        })
+    }
+
+    /// Hands `buf` to the kernel under the id `bid`,
+    /// which the operation that selects it reports
+    /// along with the number of bytes it filled in.
+    ///
+    /// # Safety
+    ///
+    /// The kernel may write into `buf` until an
+    /// operation that selected it completes, so the
+    /// caller must keep it alive and must not access
+    /// it in the meantime, and must not unregister
+    /// the ring before then. No more than `entries`
+    /// buffers may be handed to the kernel without
+    /// being selected, or the oldest ones are
+    /// overwritten.
+    #[allow(unsafe_code)]
+    pub unsafe fn provide(&self, bid: u16, buf: &mut [u8]) {
+        let mut tail = self.tail.lock().unwrap();
+
+        let index = usize::from(*tail & (self.entries - 1));
+        let entry = self.base.add(index);
+        (*entry).addr = buf.as_mut_ptr() as u64;
+        (*entry).len = u32::try_from(buf.len()).unwrap();
+        (*entry).bid = bid;
+
+        // the tail doubles as the `resv` field of the
+        // first entry, which the kernel reads to learn
+        // which entries hold buffers.
+        *tail = tail.wrapping_add(1);
+        let shared_tail =
+            &*std::ptr::addr_of_mut!((*self.base).resv)
+                .cast::<AtomicU16>();
+        shared_tail.store(*tail, Release);
+    }
+
+    /// The buffer group id that operations pass to
+    /// select a buffer from this ring.
+    pub const fn group_id(&self) -> u16 {
+        self.group_id
+    }
+
+    /// How many buffers the ring can hold.
+    pub const fn entries(&self) -> u16 {
+        self.entries
+    }
+}
+
+impl Drop for BufRing {
+    fn drop(&mut self) {
+        let _ =
+            self.ring.unregister_pbuf_ring(self.group_id);
+
+        #[allow(unsafe_code)]
+        unsafe {
+            libc::munmap(
+                self.base.cast(),
+                self.mapping_len,
+            );
+        }
+    }
+}
@@ -0,0 +1,327 @@
+use std::ops::{Deref, DerefMut};
+
+use super::*;
+
+/// The size of the huge pages that a `BufferPool`
+/// asks for, which is the default one on x86-64
+/// and aarch64.
+const HUGE_PAGE_SIZE: usize = 2 * 1024 * 1024;
+
+/// The most buffers that the kernel lets a ring
+/// register at once.
+const MAX_BUFFERS: usize = 1 << 14;
+
+/// A set of equally sized buffers in one mapping,
+/// which are registered with a ring so that
+/// `PooledBuffer::read_fixed` and
+/// `PooledBuffer::write_fixed` can use them,
+/// and which are handed out and recycled one at a
+/// time by `take`.
+///
+/// The buffers are unregistered again when the pool
+/// is dropped, which must not happen while an
+/// operation on one of them is still in flight.
+///
+/// # Examples
+///
+/// ```no_run
+/// let ring = rio::new().unwrap();
+/// let pool =
+///     rio::BufferPool::with_hugepages(&ring, 64, 1 << 16)
+///         .unwrap();
+///
+/// let file = std::fs::File::open("file").unwrap();
+/// let mut buf =
+///     pool.take().expect("all buffers in use");
+/// let len = buf.len();
+/// let read =
+///     buf.read_fixed(&file, 0, len, 0).wait().unwrap();
+/// let data: &[u8] = &buf[..read];
+/// ```
+#[derive(Debug)]
+pub struct BufferPool {
+    ring: Rio,
+    base: *mut u8,
+    mapping_len: usize,
+    buffer_len: usize,
+    huge_pages: bool,
+    free: Mutex<Vec<u16>>,
+}
+
+#[allow(unsafe_code)]
+unsafe impl Send for BufferPool {}
+
+#[allow(unsafe_code)]
+unsafe impl Sync for BufferPool {}
+
+/// One buffer of a `BufferPool`, which goes back
+/// into the pool when this is dropped.
+#[derive(Debug)]
+pub struct PooledBuffer<'a> {
+    pool: &'a BufferPool,
+    index: u16,
+}
+
+impl BufferPool {
+    /// Maps `count` buffers of `size` bytes each,
+    /// backed by huge pages where the system has some
+    /// reserved, and registers them with `ring`,
+    /// which must not have other buffers registered.
+    ///
+    /// `size` is rounded up to a multiple of the page
+    /// size, so that every buffer is aligned for
+    /// `O_DIRECT`. Without huge pages, the buffers are
+    /// backed by regular pages instead, which
+    /// `huge_pages` tells.
+    pub fn with_hugepages(
+        ring: &Rio,
+        count: usize,
+        size: usize,
+    ) -> io::Result<BufferPool> {
+        if count == 0 || count > MAX_BUFFERS || size == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "a BufferPool needs between 1 and {} \
+                     buffers of at least 1 byte.",
+                    MAX_BUFFERS,
+                ),
+            ));
+        }
+
+        let page_size = page_size();
+        let buffer_len =
+            size.div_ceil(page_size) * page_size;
+        let len = buffer_len
+            .checked_mul(count)
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "the BufferPool is too large.",
+                )
+            })?;
+
+        let huge_len =
+            len.div_ceil(HUGE_PAGE_SIZE) * HUGE_PAGE_SIZE;
+        let (base, mapping_len, huge_pages) =
+            match anonymous_mmap(
+                huge_len,
+                libc::MAP_HUGETLB,
+            ) {
+                Ok(base) => (base, huge_len, true),
+                Err(_) => {
+                    (anonymous_mmap(len, 0)?, len, false)
+                }
+            };
+
+        let bufs: Vec<IoSliceMut<'static>> = (0..count)
+            .map(|index| {
+                #[allow(unsafe_code)]
+                IoSliceMut::new(unsafe {
+                    std::slice::from_raw_parts_mut(
+                        base.add(index * buffer_len),
+                        buffer_len,
+                    )
+                })
+            })
+            .collect();
+
+        if let Err(e) = ring.register_buffers(&bufs) {
+            #[allow(unsafe_code)]
+            unsafe {
+                libc::munmap(base.cast(), mapping_len);
+            }
+            return Err(e);
+        }
+
+        Ok(BufferPool {
+            ring: ring.clone(),
+            base,
+            mapping_len,
+            buffer_len,
+            huge_pages,
+            free: Mutex::new(
+                (0..count)
+                    .rev()
+                    .map(|index| {
+                        u16::try_from(index).unwrap()
+                    })
+                    .collect(),
+            ),
+        })
+    }
+
+    /// Hands out a buffer that is not in use, or
+    /// `None` if they all are.
+    pub fn take(&self) -> Option<PooledBuffer<'_>> {
+        let index = self.free.lock().unwrap().pop()?;
+        Some(PooledBuffer { pool: self, index })
+    }
+
+    /// Whether the buffers are backed by huge pages,
+    /// rather than regular ones because the system
+    /// had no huge pages to spare.
+    pub const fn huge_pages(&self) -> bool {
+        self.huge_pages
+    }
+
+    /// The size of each buffer, after rounding it up
+    /// to a multiple of the page size.
+    pub const fn buffer_len(&self) -> usize {
+        self.buffer_len
+    }
+}
+
+impl Drop for BufferPool {
+    fn drop(&mut self) {
+        let _ = self.ring.unregister_buffers();
+
+        #[allow(unsafe_code)]
+        unsafe {
+            libc::munmap(
+                self.base.cast(),
+                self.mapping_len,
+            );
+        }
+    }
+}
+
+impl<'a> PooledBuffer<'a> {
+    /// The index of this buffer among the registered
+    /// ones, to pass as `buf_index` to
+    /// `Uring::read_fixed` and `Uring::write_fixed`.
+    pub const fn index(&self) -> u16 {
+        self.index
+    }
+
+    /// Writes `len` bytes, starting `offset` bytes
+    /// into this buffer, to the file at `at`, like
+    /// `Uring::write_fixed`, but safely, since the
+    /// buffer stays borrowed until the returned
+    /// `Completion` is done.
+    ///
+    /// Returns the number of bytes written.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range does not fit into the
+    /// buffer.
+    pub fn write_fixed<'b, F>(
+        &'b self,
+        file: &'b F,
+        offset: usize,
+        len: usize,
+        at: u64,
+    ) -> Completion<'b, usize>
+    where
+        F: AsRingFd,
+    {
+        // the shared borrow keeps the buffer from
+        // being written to while the kernel reads it.
+        #[allow(unsafe_code)]
+        unsafe {
+            self.pool.ring.write_fixed(
+                file, self.index, offset, len, at,
+            )
+        }
+    }
+
+    /// Reads `len` bytes from the file at `at` into
+    /// this buffer, starting `offset` bytes into it,
+    /// like `Uring::read_fixed`, but safely, since
+    /// the buffer stays borrowed until the returned
+    /// `Completion` is done.
+    ///
+    /// Returns the number of bytes read.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range does not fit into the
+    /// buffer.
+    pub fn read_fixed<'b, F>(
+        &'b mut self,
+        file: &'b F,
+        offset: usize,
+        len: usize,
+        at: u64,
+    ) -> Completion<'b, usize>
+    where
+        F: AsRingFd,
+    {
+        // the exclusive borrow keeps the buffer from
+        // being accessed while the kernel writes it.
+        #[allow(unsafe_code)]
+        unsafe {
+            self.pool.ring.read_fixed(
+                file, self.index, offset, len, at,
+            )
+        }
+    }
+}
+
+impl<'a> Deref for PooledBuffer<'a> {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        let offset =
+            usize::from(self.index) * self.pool.buffer_len;
+        #[allow(unsafe_code)]
+        unsafe {
+            std::slice::from_raw_parts(
+                self.pool.base.add(offset),
+                self.pool.buffer_len,
+            )
+        }
+    }
+}
+
+impl<'a> DerefMut for PooledBuffer<'a> {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        let offset =
+            usize::from(self.index) * self.pool.buffer_len;
+        // only this `PooledBuffer` hands out its index.
+        #[allow(unsafe_code)]
+        unsafe {
+            std::slice::from_raw_parts_mut(
+                self.pool.base.add(offset),
+                self.pool.buffer_len,
+            )
+        }
+    }
+}
+
+impl<'a> Drop for PooledBuffer<'a> {
+    fn drop(&mut self) {
+        self.pool.free.lock().unwrap().push(self.index);
+    }
+}
+
+fn page_size() -> usize {
+    #[allow(unsafe_code)]
+    let page_size =
+        unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+    usize::try_from(page_size).unwrap()
+}
+
+/// Maps `len` bytes of zeroed memory that is not
+/// backed by any file.
+pub(crate) fn anonymous_mmap(
+    len: usize,
+    flags: i32,
+) -> io::Result<*mut u8> {
+    #[allow(unsafe_code)]
+    let ptr = unsafe {
+        libc::mmap(
+            std::ptr::null_mut(),
+            len,
+            libc::PROT_READ | libc::PROT_WRITE,
+            libc::MAP_PRIVATE | libc::MAP_ANONYMOUS | flags,
+            -1,
+            0,
+        )
+    };
+    if ptr == libc::MAP_FAILED {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(ptr.cast())
+}
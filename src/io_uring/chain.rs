@@ -0,0 +1,241 @@
+use super::*;
+
+/// A builder for a chain of operations that run
+/// one after the other, as returned by
+/// `Uring::chain`.
+///
+/// Each step is linked to the next one with
+/// `Ordering::Link`, except for the last, and the
+/// whole chain is queued as one contiguous run by
+/// `submit`, which returns the `Completion` of
+/// every step, nested in the order they were added.
+/// If a step fails or comes up short, the steps
+/// after it fail with `ECANCELED`.
+///
+/// Nothing is queued before `submit`, so the
+/// borrows of every step last until the chain is
+/// submitted.
+///
+/// # Examples
+///
+/// ```no_run
+/// let ring = rio::new().unwrap();
+/// let from = std::fs::File::open("from").unwrap();
+/// let to = std::fs::File::create("to").unwrap();
+/// let buf = vec![0_u8; 4096];
+///
+/// // a short read cancels the write and the fsync.
+/// let (((_, read), write), fsync) = ring
+///     .chain()
+///     .read_at(&from, &buf, 0)
+///     .write_at(&to, &buf, 0)
+///     .fsync(&to)
+///     .submit()
+///     .unwrap();
+///
+/// read.wait().unwrap();
+/// write.wait().unwrap();
+/// fsync.wait().unwrap();
+/// ```
+#[must_use = "a Chain queues nothing until it is submitted"]
+pub struct Chain<'a, T> {
+    uring: &'a Uring,
+    /// How many steps were added, for which `submit`
+    /// reserves slots in the completion queue.
+    steps: usize,
+    /// Queues the steps so far, linking the last of
+    /// them to what follows with the given ordering.
+    queue: Box<dyn FnOnce(Ordering) -> T + 'a>,
+}
+
+impl<'a, T> std::fmt::Debug for Chain<'a, T> {
+    fn fmt(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+    ) -> std::fmt::Result {
+        write!(f, "Chain {{ .. }}")
+    }
+}
+
+/// Starts a `Chain` without any steps.
+pub(crate) fn empty_chain(uring: &Uring) -> Chain<'_, ()> {
+    Chain {
+        uring,
+        steps: 0,
+        queue: Box::new(|_| ()),
+    }
+}
+
+impl<'a, T: 'a> Chain<'a, T> {
+    /// Adds a step that queues an operation with
+    /// one of the `_ordered` methods of `Uring`,
+    /// passing on the `Ordering` it is called with,
+    /// for operations that have no method of their
+    /// own on `Chain`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// let ring = rio::new().unwrap();
+    /// let file = std::fs::File::open("file").unwrap();
+    ///
+    /// let (_, sync) = ring
+    ///     .chain()
+    ///     .then(|ring, ordering| {
+    ///         ring.fdatasync_ordered(&file, ordering)
+    ///     })
+    ///     .submit()
+    ///     .unwrap();
+    /// sync.wait().unwrap();
+    /// ```
+    pub fn then<C, F>(
+        self,
+        step: F,
+    ) -> Chain<'a, (T, Completion<'a, C>)>
+    where
+        F: 'a
+            + FnOnce(&'a Uring, Ordering) -> Completion<'a, C>,
+    {
+        let Chain {
+            uring,
+            steps,
+            queue,
+        } = self;
+        Chain {
+            uring,
+            steps: steps + 1,
+            queue: Box::new(move |ordering| {
+                let earlier = queue(Ordering::Link);
+                (earlier, step(uring, ordering))
+            }),
+        }
+    }
+
+    /// Adds a step that does nothing, like
+    /// `Uring::nop`.
+    pub fn nop(self) -> Chain<'a, (T, Completion<'a, ()>)> {
+        self.then(Uring::nop_ordered)
+    }
+
+    /// Adds a step that opens `path`, like
+    /// `Uring::openat`. Its descriptor is only known
+    /// once it completes, so later steps of the same
+    /// chain can't use it.
+    pub fn openat<F>(
+        self,
+        dirfd: &'a F,
+        path: &'a CStr,
+        flags: i32,
+        mode: u32,
+    ) -> Chain<'a, (T, Completion<'a, RawFd>)>
+    where
+        F: AsRawFd,
+    {
+        self.then(move |uring, ordering| {
+            uring.openat_ordered(
+                dirfd, path, flags, mode, ordering,
+            )
+        })
+    }
+
+    /// Adds a step that reads into `iov` at `at`,
+    /// like `Uring::read_at`.
+    pub fn read_at<F, B>(
+        self,
+        file: &'a F,
+        iov: &'a B,
+        at: u64,
+    ) -> Chain<'a, (T, Completion<'a, usize>)>
+    where
+        F: AsRingFd,
+        B: AsIoVec + AsIoVecMut,
+    {
+        self.then(move |uring, ordering| {
+            uring.read_at_ordered(file, iov, at, ordering)
+        })
+    }
+
+    /// Adds a step that writes `iov` at `at`, like
+    /// `Uring::write_at`.
+    pub fn write_at<F, B>(
+        self,
+        file: &'a F,
+        iov: &'a B,
+        at: u64,
+    ) -> Chain<'a, (T, Completion<'a, usize>)>
+    where
+        F: AsRingFd,
+        B: 'a + AsIoVec,
+    {
+        self.then(move |uring, ordering| {
+            uring.write_at_ordered(file, iov, at, ordering)
+        })
+    }
+
+    /// Adds a step that sends `iov`, like
+    /// `Uring::send`.
+    pub fn send<F, B>(
+        self,
+        stream: &'a F,
+        iov: &'a B,
+    ) -> Chain<'a, (T, Completion<'a, usize>)>
+    where
+        F: AsRingFd,
+        B: 'a + AsIoVec,
+    {
+        self.then(move |uring, ordering| {
+            uring.send_ordered(stream, iov, ordering)
+        })
+    }
+
+    /// Adds a step that receives into `iov`, like
+    /// `Uring::recv`.
+    pub fn recv<F, B>(
+        self,
+        stream: &'a F,
+        iov: &'a B,
+    ) -> Chain<'a, (T, Completion<'a, usize>)>
+    where
+        F: AsRingFd,
+        B: AsIoVec + AsIoVecMut,
+    {
+        self.then(move |uring, ordering| {
+            uring.recv_ordered(stream, iov, ordering)
+        })
+    }
+
+    /// Adds a step that flushes `file`, like
+    /// `Uring::fsync`.
+    pub fn fsync(
+        self,
+        file: &'a File,
+    ) -> Chain<'a, (T, Completion<'a, ()>)> {
+        self.then(move |uring, ordering| {
+            uring.fsync_ordered(file, ordering)
+        })
+    }
+
+    /// Adds a step that closes `fd`, like
+    /// `Uring::close`.
+    pub fn close(
+        self,
+        fd: RawFd,
+    ) -> Chain<'a, (T, Completion<'a, ()>)> {
+        self.then(move |uring, ordering| {
+            uring.close_ordered(fd, ordering)
+        })
+    }
+
+    /// Queues every step as one contiguous run with
+    /// `Uring::batch_n`, and submits them, returning
+    /// their `Completion`s, or the error of that
+    /// submission.
+    pub fn submit(self) -> io::Result<T> {
+        let Chain {
+            uring,
+            steps,
+            queue,
+        } = self;
+        uring.batch_n(steps, |_| queue(Ordering::None))
+    }
+}
@@ -1,16 +1,26 @@
-use std::sync::Arc;
+use std::{os::unix::thread::JoinHandleExt, sync::Arc};
 
 use super::*;
 
 /// Configuration for the underlying `io_uring` system.
 #[derive(Clone, Debug, Copy)]
 pub struct Config {
-    /// The number of entries in the submission queue.
+    /// The number of entries in the submission queue,
+    /// between 1 and 32768, or 2^31 with `clamp`,
+    /// which `start` rounds up to the next power of
+    /// two, as the kernel only supports those.
     /// The completion queue size may be specified by
-    /// using `raw_params` instead. By default, the
-    /// kernel will choose a completion queue that is 2x
-    /// the submission queue's size.
+    /// using `cq_depth`. By default, the kernel will
+    /// choose a completion queue that is 2x the
+    /// submission queue's size.
     pub depth: usize,
+    /// The number of entries in the completion queue,
+    /// which must be a power of two that is at least
+    /// `depth`, after rounding it, and at most 65536,
+    /// or 2^31 with `clamp`.
+    /// A deeper completion queue can absorb bursts
+    /// of completions between reaps.
+    pub cq_depth: Option<usize>,
     /// Enable `SQPOLL` mode, which spawns a kernel
     /// thread that polls for submissions without
     /// needing to block as often to submit.
@@ -20,17 +30,133 @@ pub struct Config {
     /// by a non-privileged user.
     pub sq_poll: bool,
     /// Specify a particular CPU to pin the
-    /// `SQPOLL` thread onto.
-    pub sq_poll_affinity: u32,
-    /// Specify that the user will directly
-    /// poll the hardware for operation completion
-    /// rather than using the completion queue.
+    /// `SQPOLL` thread onto, which `start` fails for
+    /// if it is not online. When this is `None`, the
+    /// thread may run on any CPU.
     ///
-    /// CURRENTLY UNSUPPORTED
+    /// This only takes effect with `sq_poll` enabled.
+    pub sq_poll_affinity: Option<u32>,
+    /// How many milliseconds the `SQPOLL` thread
+    /// keeps polling without any new submissions
+    /// before it goes to sleep. When this is `None`,
+    /// the kernel picks its default of one second.
+    ///
+    /// This only takes effect with `sq_poll` enabled.
+    pub sq_poll_idle_ms: Option<u32>,
+    /// Enable `IOPOLL` mode, where completions are
+    /// found by actively polling the hardware
+    /// rather than waiting for an interrupt, which
+    /// can lower latency on fast `NVMe` devices at the
+    /// cost of a busy reaper thread.
+    ///
+    /// Only reads and writes on files opened with
+    /// `O_DIRECT` on block devices that support
+    /// polling work in this mode, others fail.
     pub io_poll: bool,
+    /// Enable `COOP_TASKRUN` mode, where the kernel
+    /// no longer interrupts a running thread to
+    /// finish its operations, and instead waits for
+    /// it to enter the kernel or go to sleep. This
+    /// saves inter-processor interrupts, at the cost
+    /// of delaying completions of operations that
+    /// were submitted by a thread that is busy in
+    /// userspace.
+    ///
+    /// This requires linux 5.19 or newer, and
+    /// `start` fails on older kernels.
+    pub coop_taskrun: bool,
+    /// Enable `SINGLE_ISSUER` mode, which lets the
+    /// kernel skip synchronization by promising that
+    /// only the thread calling `start` ever submits
+    /// to, or registers anything with, the ring.
+    /// Submissions from any other thread fail with
+    /// `EEXIST`, and since `rio` submits whenever a
+    /// `Completion` is waited on, waiting on one
+    /// from another thread fails with that error.
+    ///
+    /// This is usually combined with `coop_taskrun`.
+    /// The `DEFER_TASKRUN` mode that is often paired
+    /// with it can't be offered, because it only lets
+    /// the submitting thread reap completions, and
+    /// `rio` reaps them on a thread of its own.
+    ///
+    /// This requires linux 6.0 or newer, and `start`
+    /// fails on older kernels.
+    pub single_issuer: bool,
+    /// Pin the thread that reaps completions onto a
+    /// particular CPU, which `start` fails for if it
+    /// is not one that this process may run on.
+    pub reaper_affinity: Option<usize>,
+    /// Hold the thread that reaps completions until
+    /// at least `min_complete` of them are ready, or
+    /// `max_wait` has passed, instead of waking it
+    /// up for every single one, which spreads the
+    /// cost of a wakeup over a batch.
+    ///
+    /// Any completion may then be reaped up to
+    /// `max_wait` late, so this only pays off when
+    /// many operations are kept in flight. This
+    /// requires linux 5.11 or newer, and `start`
+    /// fails on older kernels.
+    pub reaper_batch: Option<(u32, Duration)>,
+    /// Cap the async worker threads of the ring to
+    /// `(bounded, unbounded)` on start, see
+    /// `Uring::set_iowq_max_workers`, so that many
+    /// rings on one machine, or a burst of blocking
+    /// operations, don't spawn a thread for every
+    /// one of them. This requires linux 5.15 or
+    /// newer, and `start` fails on older kernels.
+    pub iowq_max_workers: Option<(u32, u32)>,
+    /// Share the async backend of the existing ring
+    /// with this descriptor, as `Uring` returns from
+    /// `as_raw_fd`, instead of giving the new ring
+    /// one of its own. The typical pattern is to
+    /// start the first ring normally and attach all
+    /// later ones to it, including the shards of a
+    /// `ShardedRio`, so that their `sq_poll` threads
+    /// and, on kernels before 5.12, their worker
+    /// threads are shared. Since 5.12,
+    /// rings started by the same thread share their
+    /// worker threads anyway.
+    ///
+    /// The attached ring must stay open until this
+    /// one starts. This requires linux 5.6 or newer,
+    /// and `start` fails on older kernels, or if the
+    /// descriptor is not a ring.
+    pub attach_wq_fd: Option<RawFd>,
+    /// Let the kernel clamp `depth` and `cq_depth`
+    /// to the largest queues it allows, instead of
+    /// failing `start` for larger ones, so that the
+    /// same configuration also starts on hosts with
+    /// lower limits. `Uring::sq_entries` and
+    /// `Uring::cq_entries` report the sizes that the
+    /// kernel picked.
+    ///
+    /// This requires linux 5.6 or newer, and `start`
+    /// fails on older kernels.
+    pub clamp: bool,
     /// Print a profile table on drop, showing where
     /// time was spent.
     pub print_profile_on_drop: bool,
+    /// Make dropping the `Uring` `fsync` every file
+    /// that was written to through it, so that a
+    /// durable log is on disk once its ring is gone.
+    ///
+    /// Every write has completed by then, since each
+    /// `Completion` borrows the ring and waits for
+    /// its operation when dropped. Files are tracked
+    /// by their descriptor, or their slot among the
+    /// registered files, on the first write, and
+    /// kept open by a duplicate of the descriptor
+    /// until the drop, so closing them first is
+    /// fine. A file whose descriptor number was used
+    /// before by another written file is missed.
+    ///
+    /// The drop blocks until every `fsync` is done,
+    /// and can't report their errors, so critical
+    /// paths should still `fsync` explicitly and
+    /// check the result.
+    pub flush_on_drop: bool,
     /// setting `raw_params` overrides everything else
     pub raw_params: Option<io_uring_params>,
 }
@@ -39,40 +165,205 @@ impl Default for Config {
     fn default() -> Config {
         Config {
             depth: 256,
+            cq_depth: None,
             sq_poll: false,
             io_poll: false,
-            sq_poll_affinity: 0,
+            coop_taskrun: false,
+            single_issuer: false,
+            sq_poll_affinity: None,
+            sq_poll_idle_ms: None,
+            reaper_affinity: None,
+            reaper_batch: None,
+            iowq_max_workers: None,
+            attach_wq_fd: None,
+            clamp: false,
             raw_params: None,
             print_profile_on_drop: false,
+            flush_on_drop: false,
         }
     }
 }
 
 impl Config {
+    /// Returns a `ConfigBuilder` that starts out with
+    /// the default configuration.
+    pub fn builder() -> ConfigBuilder {
+        ConfigBuilder::default()
+    }
+
+    /// The oldest linux version, as `(major, minor)`,
+    /// that can start a ring with this configuration,
+    /// so that it can be checked before calling
+    /// `start`. Operations may need newer kernels,
+    /// as their docs note and `Probe` reports.
+    ///
+    /// `sq_poll` counts as needing linux 5.11, which
+    /// is the first to allow it without privileges,
+    /// though privileged users can use it on 5.1.
+    /// With `raw_params`, only the minimum for
+    /// `io_uring` itself is known.
+    pub const fn min_kernel(&self) -> (u32, u32) {
+        if self.raw_params.is_some() {
+            MIN_KERNEL
+        } else if self.single_issuer {
+            (6, 0)
+        } else if self.coop_taskrun {
+            (5, 19)
+        } else if self.sq_poll
+            || self.reaper_batch.is_some()
+        {
+            (5, 11)
+        } else if self.attach_wq_fd.is_some() || self.clamp
+        {
+            (5, 6)
+        } else if self.cq_depth.is_some() {
+            (5, 5)
+        } else {
+            MIN_KERNEL
+        }
+    }
+
     /// Start the `Rio` system.
     pub fn start(mut self) -> io::Result<Rio> {
-        let mut params =
-            if let Some(params) = self.raw_params.take() {
-                params
-            } else {
-                let mut params = io_uring_params::default();
-
-                if self.sq_poll {
-                    // set SQPOLL mode to avoid needing wakeup
-                    params.flags = IORING_SETUP_SQPOLL;
-                    params.sq_thread_cpu =
-                        self.sq_poll_affinity;
+        let (running, release) = kernel_version()?;
+        if running < MIN_KERNEL {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                format!(
+                    "io_uring needs linux {}.{} or newer, \
+                     but the running kernel is {}.",
+                    MIN_KERNEL.0, MIN_KERNEL.1, release,
+                ),
+            ));
+        }
+
+        let (max_depth, max_cq_depth) = if self.clamp {
+            (MAX_CLAMPED_DEPTH, MAX_CLAMPED_DEPTH)
+        } else {
+            (MAX_DEPTH, MAX_CQ_DEPTH)
+        };
+        if self.depth == 0 || self.depth > max_depth {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "depth {} must be between 1 and {}.",
+                    self.depth, max_depth,
+                ),
+            ));
+        }
+        self.depth = self.depth.next_power_of_two();
+
+        if let Some((min_complete, _)) = self.reaper_batch {
+            if min_complete == 0 {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "reaper_batch needs to wait for at \
+                     least 1 completion.",
+                ));
+            }
+            if running < (5, 11) {
+                return Err(io::Error::new(
+                    io::ErrorKind::Other,
+                    format!(
+                        "reaper_batch needs linux 5.11 or \
+                         newer, but the running kernel is \
+                         {}.",
+                        release,
+                    ),
+                ));
+            }
+        }
+
+        if let Some(cq_depth) = self.cq_depth {
+            if !cq_depth.is_power_of_two()
+                || cq_depth < self.depth
+                || cq_depth > max_cq_depth
+            {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!(
+                        "cq_depth {} must be a power of two \
+                         between depth {} and {}.",
+                        cq_depth, self.depth, max_cq_depth,
+                    ),
+                ));
+            }
+        }
+
+        let mut params = if let Some(params) =
+            self.raw_params.take()
+        {
+            params
+        } else {
+            let mut params = io_uring_params::default();
+
+            if self.sq_poll {
+                // set SQPOLL mode to avoid needing wakeup
+                params.flags |= IORING_SETUP_SQPOLL;
+                if let Some(cpu) = self.sq_poll_affinity {
+                    params.flags |= IORING_SETUP_SQ_AFF;
+                    params.sq_thread_cpu = cpu;
                 }
+                params.sq_thread_idle =
+                    self.sq_poll_idle_ms.unwrap_or(0);
+            }
+
+            if self.io_poll {
+                params.flags |= IORING_SETUP_IOPOLL;
+            }
+
+            if self.coop_taskrun {
+                params.flags |= IORING_SETUP_COOP_TASKRUN;
+            }
+
+            if self.single_issuer {
+                params.flags |= IORING_SETUP_SINGLE_ISSUER;
+            }
+
+            if let Some(wq_fd) = self.attach_wq_fd {
+                params.flags |= IORING_SETUP_ATTACH_WQ;
+                params.wq_fd =
+                    u32::try_from(wq_fd).unwrap();
+            }
+
+            if self.clamp {
+                params.flags |= IORING_SETUP_CLAMP;
+            }
+
+            if let Some(cq_depth) = self.cq_depth {
+                params.flags |= IORING_SETUP_CQSIZE;
+                params.cq_entries =
+                    u32::try_from(cq_depth).unwrap();
+            }
 
-                params
-            };
+            params
+        };
 
         let params_ptr: *mut io_uring_params = &mut params;
 
-        let ring_fd = setup(
+        let ring_fd = match setup(
             u32::try_from(self.depth).unwrap(),
             params_ptr,
-        )?;
+        ) {
+            Err(ref e)
+                if e.raw_os_error()
+                    == Some(libc::EINVAL)
+                    && running < self.min_kernel() =>
+            {
+                let (major, minor) = self.min_kernel();
+                return Err(io::Error::new(
+                    io::ErrorKind::Other,
+                    format!(
+                        "io_uring_setup failed with EINVAL: \
+                         this configuration needs linux \
+                         {}.{} or newer, but the running \
+                         kernel is {}.",
+                        major, minor, release,
+                    ),
+                ));
+            }
+            res => res?,
+        };
 
         if ring_fd < 0 {
             let mut err = io::Error::last_os_error();
@@ -95,26 +386,323 @@ impl Config {
             params.cq_entries as usize,
         ));
 
+        let inbox = Arc::new(Inbox::default());
+
+        let probe = Probe::new(ring_fd)
+            .unwrap_or_else(|_| Probe::fallback());
+
         let sq = Sq::new(&params, ring_fd)?;
         let cq = Cq::new(
             &params,
             ring_fd,
             in_flight.clone(),
             ticket_queue.clone(),
+            inbox.clone(),
+            probe,
+            self.reaper_batch.map(
+                |(min_complete, max_wait)| {
+                    (min_complete, dur2timespec(max_wait))
+                },
+            ),
         )?;
 
-        std::thread::spawn(move || {
+        let reaper = std::thread::spawn(move || {
             let mut cq = cq;
             cq.reaper(ring_fd)
         });
 
-        Ok(Rio(Arc::new(Uring::new(
+        let pinned = match self.reaper_affinity {
+            Some(cpu) => pin_thread(&reaper, cpu),
+            None => Ok(()),
+        };
+
+        let rio = Rio(Arc::new(Uring::new(
             self,
-            params.flags,
+            &params,
             ring_fd,
             sq,
             in_flight,
             ticket_queue,
-        ))))
+            inbox,
+            reaper,
+            probe,
+            running,
+        )));
+
+        // dropping the ring on failure shuts it down.
+        pinned?;
+        if let Some((bounded, unbounded)) =
+            self.iowq_max_workers
+        {
+            rio.set_iowq_max_workers(bounded, unbounded)?;
+        }
+
+        Ok(rio)
+    }
+}
+
+/// The first linux version that has `io_uring`.
+const MIN_KERNEL: (u32, u32) = (5, 1);
+
+/// The deepest submission queue that the kernel
+/// sets up without `IORING_SETUP_CLAMP`.
+const MAX_DEPTH: usize = 1 << 15;
+
+/// The deepest completion queue that the kernel
+/// sets up without `IORING_SETUP_CLAMP`.
+const MAX_CQ_DEPTH: usize = 2 * MAX_DEPTH;
+
+/// The deepest queues that `start` asks for with
+/// `IORING_SETUP_CLAMP`, which is the largest power
+/// of two that `io_uring_setup` takes.
+const MAX_CLAMPED_DEPTH: usize = 1 << 31;
+
+/// The version of the running kernel as
+/// `(major, minor)`, along with the full release
+/// string that `uname` reports, like
+/// `5.10.0-8-amd64`.
+pub(crate) fn kernel_version(
+) -> io::Result<((u32, u32), String)> {
+    #[allow(unsafe_code)]
+    let mut uts: libc::utsname =
+        unsafe { MaybeUninit::zeroed().assume_init() };
+
+    #[allow(unsafe_code)]
+    let ret = unsafe { libc::uname(&mut uts) };
+    if ret < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    #[allow(unsafe_code)]
+    let release =
+        unsafe { CStr::from_ptr(uts.release.as_ptr()) }
+            .to_string_lossy()
+            .into_owned();
+
+    let mut numbers = release
+        .split(|c: char| !c.is_ascii_digit())
+        .map(|number| number.parse().unwrap_or(0));
+    let major = numbers.next().unwrap_or(0);
+    let minor = numbers.next().unwrap_or(0);
+
+    Ok(((major, minor), release))
+}
+
+/// Pins the given thread onto a single CPU.
+fn pin_thread(
+    thread: &JoinHandle<()>,
+    cpu: usize,
+) -> io::Result<()> {
+    if cpu >= usize::try_from(libc::CPU_SETSIZE).unwrap() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("there is no CPU {} to pin onto.", cpu),
+        ));
+    }
+
+    #[allow(unsafe_code)]
+    let ret = unsafe {
+        let mut set: libc::cpu_set_t =
+            MaybeUninit::zeroed().assume_init();
+        libc::CPU_SET(cpu, &mut set);
+        libc::pthread_setaffinity_np(
+            thread.as_pthread_t(),
+            size_of::<libc::cpu_set_t>(),
+            &set,
+        )
+    };
+    if ret != 0 {
+        return Err(io::Error::from_raw_os_error(ret));
+    }
+    Ok(())
+}
+
+/// A chainable way of building a `Config`, which
+/// rejects contradictory settings when it is built.
+///
+/// # Examples
+///
+/// ```no_run
+/// let ring = rio::Config::builder()
+///     .depth(1024)
+///     .print_profile_on_drop(true)
+///     .start()
+///     .expect("create uring");
+/// ```
+#[derive(Clone, Debug, Copy, Default)]
+pub struct ConfigBuilder {
+    config: Config,
+}
+
+impl ConfigBuilder {
+    /// Sets `Config::depth`.
+    pub const fn depth(mut self, depth: usize) -> Self {
+        self.config.depth = depth;
+        self
+    }
+
+    /// Sets `Config::cq_depth`.
+    pub const fn cq_depth(
+        mut self,
+        cq_depth: usize,
+    ) -> Self {
+        self.config.cq_depth = Some(cq_depth);
+        self
+    }
+
+    /// Sets `Config::sq_poll`.
+    pub const fn sq_poll(mut self, sq_poll: bool) -> Self {
+        self.config.sq_poll = sq_poll;
+        self
+    }
+
+    /// Sets `Config::sq_poll_affinity`, which
+    /// requires `sq_poll` to be enabled as well.
+    pub const fn sq_poll_affinity(
+        mut self,
+        cpu: u32,
+    ) -> Self {
+        self.config.sq_poll_affinity = Some(cpu);
+        self
+    }
+
+    /// Sets `Config::sq_poll_idle_ms`, which requires
+    /// `sq_poll` to be enabled as well.
+    pub const fn sq_poll_idle_ms(
+        mut self,
+        sq_poll_idle_ms: u32,
+    ) -> Self {
+        self.config.sq_poll_idle_ms = Some(sq_poll_idle_ms);
+        self
+    }
+
+    /// Sets `Config::io_poll`.
+    pub const fn io_poll(mut self, io_poll: bool) -> Self {
+        self.config.io_poll = io_poll;
+        self
+    }
+
+    /// Sets `Config::coop_taskrun`.
+    pub const fn coop_taskrun(
+        mut self,
+        coop_taskrun: bool,
+    ) -> Self {
+        self.config.coop_taskrun = coop_taskrun;
+        self
+    }
+
+    /// Sets `Config::single_issuer`.
+    pub const fn single_issuer(
+        mut self,
+        single_issuer: bool,
+    ) -> Self {
+        self.config.single_issuer = single_issuer;
+        self
+    }
+
+    /// Sets `Config::reaper_affinity`.
+    pub const fn reaper_affinity(
+        mut self,
+        cpu: usize,
+    ) -> Self {
+        self.config.reaper_affinity = Some(cpu);
+        self
+    }
+
+    /// Sets `Config::reaper_batch`.
+    pub const fn reaper_batch(
+        mut self,
+        min_complete: u32,
+        max_wait: Duration,
+    ) -> Self {
+        self.config.reaper_batch =
+            Some((min_complete, max_wait));
+        self
+    }
+
+    /// Sets `Config::iowq_max_workers`.
+    pub const fn iowq_max_workers(
+        mut self,
+        bounded: u32,
+        unbounded: u32,
+    ) -> Self {
+        self.config.iowq_max_workers =
+            Some((bounded, unbounded));
+        self
+    }
+
+    /// Sets `Config::attach_wq_fd`.
+    pub const fn attach_wq_fd(
+        mut self,
+        wq_fd: RawFd,
+    ) -> Self {
+        self.config.attach_wq_fd = Some(wq_fd);
+        self
+    }
+
+    /// Sets `Config::clamp`.
+    pub const fn clamp(mut self, clamp: bool) -> Self {
+        self.config.clamp = clamp;
+        self
+    }
+
+    /// Sets `Config::print_profile_on_drop`.
+    pub const fn print_profile_on_drop(
+        mut self,
+        print_profile_on_drop: bool,
+    ) -> Self {
+        self.config.print_profile_on_drop =
+            print_profile_on_drop;
+        self
+    }
+
+    /// Sets `Config::flush_on_drop`.
+    pub const fn flush_on_drop(
+        mut self,
+        flush_on_drop: bool,
+    ) -> Self {
+        self.config.flush_on_drop = flush_on_drop;
+        self
+    }
+
+    /// Checks the settings for contradictions, and
+    /// returns the resulting `Config`.
+    pub fn build(self) -> io::Result<Config> {
+        let config = self.config;
+
+        if config.depth == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "depth must be at least 1.",
+            ));
+        }
+
+        if config.sq_poll_affinity.is_some()
+            && !config.sq_poll
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "sq_poll_affinity only takes effect \
+                 with sq_poll enabled.",
+            ));
+        }
+
+        if config.sq_poll_idle_ms.is_some()
+            && !config.sq_poll
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "sq_poll_idle_ms only takes effect \
+                 with sq_poll enabled.",
+            ));
+        }
+
+        Ok(config)
+    }
+
+    /// Builds the `Config` and starts the `Rio`
+    /// system with it.
+    pub fn start(self) -> io::Result<Rio> {
+        self.build()?.start()
     }
 }
@@ -1,4 +1,4 @@
-#![allow(unused)]
+#![allow(unused, missing_docs)]
 pub const IORING_OP_NOP: u8 = 0;
 pub const IORING_OP_READV: u8 = 1;
 pub const IORING_OP_WRITEV: u8 = 2;
@@ -28,26 +28,64 @@ pub const IORING_OP_MADVISE: u8 = 25;
 pub const IORING_OP_SEND: u8 = 26;
 pub const IORING_OP_RECV: u8 = 27;
 pub const IORING_OP_OPENAT2: u8 = 28;
-pub const IORING_OP_LAST: u8 = 29;
+pub const IORING_OP_EPOLL_CTL: u8 = 29;
+pub const IORING_OP_SPLICE: u8 = 30;
+pub const IORING_OP_PROVIDE_BUFFERS: u8 = 31;
+pub const IORING_OP_REMOVE_BUFFERS: u8 = 32;
+pub const IORING_OP_TEE: u8 = 33;
+pub const IORING_OP_SHUTDOWN: u8 = 34;
+pub const IORING_OP_RENAMEAT: u8 = 35;
+pub const IORING_OP_UNLINKAT: u8 = 36;
+pub const IORING_OP_MKDIRAT: u8 = 37;
+pub const IORING_OP_SYMLINKAT: u8 = 38;
+pub const IORING_OP_LINKAT: u8 = 39;
+pub const IORING_OP_MSG_RING: u8 = 40;
+pub const IORING_OP_FSETXATTR: u8 = 41;
+pub const IORING_OP_SETXATTR: u8 = 42;
+pub const IORING_OP_FGETXATTR: u8 = 43;
+pub const IORING_OP_GETXATTR: u8 = 44;
+pub const IORING_OP_SOCKET: u8 = 45;
+pub const IORING_OP_URING_CMD: u8 = 46;
+pub const IORING_OP_SEND_ZC: u8 = 47;
+pub const IORING_OP_LAST: u8 = 48;
+pub const IORING_OP_FTRUNCATE: u8 = 55;
 pub const IOSQE_FIXED_FILE: u8 = 1;
 pub const IOSQE_IO_DRAIN: u8 = 2;
 pub const IOSQE_IO_LINK: u8 = 4;
 pub const IOSQE_IO_HARDLINK: u8 = 8;
 pub const IOSQE_ASYNC: u8 = 16;
-pub const IOSQE_PERSONALITY: u8 = 32;
+pub const IOSQE_BUFFER_SELECT: u8 = 32;
 pub const IORING_SETUP_IOPOLL: u32 = 1;
 pub const IORING_SETUP_SQPOLL: u32 = 2;
 pub const IORING_SETUP_SQ_AFF: u32 = 4;
 pub const IORING_SETUP_CQSIZE: u32 = 8;
 pub const IORING_SETUP_CLAMP: u32 = 16;
-pub const IORING_FSYNC_DATASYNC: u8 = 1;
+pub const IORING_SETUP_ATTACH_WQ: u32 = 32;
+pub const IORING_SETUP_COOP_TASKRUN: u32 = 1 << 8;
+pub const IORING_SETUP_SINGLE_ISSUER: u32 = 1 << 12;
+pub const IORING_FSYNC_DATASYNC: u32 = 1;
+pub const IORING_ACCEPT_MULTISHOT: u16 = 1;
+pub const IORING_FILE_INDEX_ALLOC: u32 = !0;
+pub const SPLICE_F_FD_IN_FIXED: u32 = 1 << 31;
+pub const IORING_RECV_MULTISHOT: u16 = 1 << 1;
+pub const IORING_POLL_ADD_MULTI: u32 = 1;
+pub const IORING_CQE_F_BUFFER: u32 = 1;
+pub const IORING_CQE_F_MORE: u32 = 1 << 1;
+pub const IORING_CQE_F_SOCK_NONEMPTY: u32 = 1 << 2;
+pub const IORING_CQE_F_NOTIF: u32 = 1 << 3;
+pub const IORING_CQE_BUFFER_SHIFT: u32 = 16;
 pub const IORING_TIMEOUT_ABS: u32 = 1;
+pub const IORING_ASYNC_CANCEL_ALL: u32 = 1;
+pub const IORING_ASYNC_CANCEL_FD: u32 = 1 << 1;
+pub const IORING_ASYNC_CANCEL_ANY: u32 = 1 << 2;
 pub const IORING_OFF_SQ_RING: i64 = 0;
 pub const IORING_OFF_CQ_RING: i64 = 0x0800_0000;
 pub const IORING_OFF_SQES: i64 = 0x1000_0000;
 pub const IORING_SQ_NEED_WAKEUP: u32 = 1;
 pub const IORING_ENTER_GETEVENTS: u32 = 1;
 pub const IORING_ENTER_SQ_WAKEUP: u32 = 2;
+pub const IORING_ENTER_EXT_ARG: u32 = 8;
+pub const IORING_ENTER_REGISTERED_RING: u32 = 1 << 4;
 pub const IORING_FEAT_SINGLE_MMAP: u32 = 1;
 pub const IORING_FEAT_NODROP: u32 = 2;
 pub const IORING_FEAT_SUBMIT_STABLE: u32 = 4;
@@ -60,3 +98,77 @@ pub const IORING_REGISTER_EVENTFD: u32 = 4;
 pub const IORING_UNREGISTER_EVENTFD: u32 = 5;
 pub const IORING_REGISTER_FILES_UPDATE: u32 = 6;
 pub const IORING_REGISTER_EVENTFD_ASYNC: u32 = 7;
+pub const IORING_REGISTER_PROBE: u32 = 8;
+pub const IORING_REGISTER_FILES2: u32 = 13;
+pub const IORING_REGISTER_IOWQ_MAX_WORKERS: u32 = 19;
+pub const IORING_REGISTER_RING_FDS: u32 = 20;
+pub const IORING_UNREGISTER_RING_FDS: u32 = 21;
+pub const IORING_REGISTER_PBUF_RING: u32 = 22;
+pub const IORING_UNREGISTER_PBUF_RING: u32 = 23;
+pub const IO_URING_OP_SUPPORTED: u16 = 1;
+pub const IORING_RSRC_REGISTER_SPARSE: u32 = 1;
+
+/// The name of the constant for an `IORING_OP_*`
+/// opcode, for use in error messages.
+pub const fn op_name(opcode: u8) -> &'static str {
+    match opcode {
+        IORING_OP_NOP => "IORING_OP_NOP",
+        IORING_OP_READV => "IORING_OP_READV",
+        IORING_OP_WRITEV => "IORING_OP_WRITEV",
+        IORING_OP_FSYNC => "IORING_OP_FSYNC",
+        IORING_OP_READ_FIXED => "IORING_OP_READ_FIXED",
+        IORING_OP_WRITE_FIXED => "IORING_OP_WRITE_FIXED",
+        IORING_OP_POLL_ADD => "IORING_OP_POLL_ADD",
+        IORING_OP_POLL_REMOVE => "IORING_OP_POLL_REMOVE",
+        IORING_OP_SYNC_FILE_RANGE => {
+            "IORING_OP_SYNC_FILE_RANGE"
+        }
+        IORING_OP_SENDMSG => "IORING_OP_SENDMSG",
+        IORING_OP_RECVMSG => "IORING_OP_RECVMSG",
+        IORING_OP_TIMEOUT => "IORING_OP_TIMEOUT",
+        IORING_OP_TIMEOUT_REMOVE => {
+            "IORING_OP_TIMEOUT_REMOVE"
+        }
+        IORING_OP_ACCEPT => "IORING_OP_ACCEPT",
+        IORING_OP_ASYNC_CANCEL => "IORING_OP_ASYNC_CANCEL",
+        IORING_OP_LINK_TIMEOUT => "IORING_OP_LINK_TIMEOUT",
+        IORING_OP_CONNECT => "IORING_OP_CONNECT",
+        IORING_OP_FALLOCATE => "IORING_OP_FALLOCATE",
+        IORING_OP_OPENAT => "IORING_OP_OPENAT",
+        IORING_OP_CLOSE => "IORING_OP_CLOSE",
+        IORING_OP_FILES_UPDATE => "IORING_OP_FILES_UPDATE",
+        IORING_OP_STATX => "IORING_OP_STATX",
+        IORING_OP_READ => "IORING_OP_READ",
+        IORING_OP_WRITE => "IORING_OP_WRITE",
+        IORING_OP_FADVISE => "IORING_OP_FADVISE",
+        IORING_OP_MADVISE => "IORING_OP_MADVISE",
+        IORING_OP_SEND => "IORING_OP_SEND",
+        IORING_OP_RECV => "IORING_OP_RECV",
+        IORING_OP_OPENAT2 => "IORING_OP_OPENAT2",
+        IORING_OP_EPOLL_CTL => "IORING_OP_EPOLL_CTL",
+        IORING_OP_SPLICE => "IORING_OP_SPLICE",
+        IORING_OP_PROVIDE_BUFFERS => {
+            "IORING_OP_PROVIDE_BUFFERS"
+        }
+        IORING_OP_REMOVE_BUFFERS => {
+            "IORING_OP_REMOVE_BUFFERS"
+        }
+        IORING_OP_TEE => "IORING_OP_TEE",
+        IORING_OP_SHUTDOWN => "IORING_OP_SHUTDOWN",
+        IORING_OP_RENAMEAT => "IORING_OP_RENAMEAT",
+        IORING_OP_UNLINKAT => "IORING_OP_UNLINKAT",
+        IORING_OP_MKDIRAT => "IORING_OP_MKDIRAT",
+        IORING_OP_SYMLINKAT => "IORING_OP_SYMLINKAT",
+        IORING_OP_LINKAT => "IORING_OP_LINKAT",
+        IORING_OP_MSG_RING => "IORING_OP_MSG_RING",
+        IORING_OP_FSETXATTR => "IORING_OP_FSETXATTR",
+        IORING_OP_SETXATTR => "IORING_OP_SETXATTR",
+        IORING_OP_FGETXATTR => "IORING_OP_FGETXATTR",
+        IORING_OP_GETXATTR => "IORING_OP_GETXATTR",
+        IORING_OP_SOCKET => "IORING_OP_SOCKET",
+        IORING_OP_URING_CMD => "IORING_OP_URING_CMD",
+        IORING_OP_SEND_ZC => "IORING_OP_SEND_ZC",
+        IORING_OP_FTRUNCATE => "IORING_OP_FTRUNCATE",
+        _ => "an unknown io_uring operation",
+    }
+}
@@ -5,6 +5,25 @@ use std::slice::from_raw_parts_mut;
 use super::*;
 
 /// Consumes uring completions.
+///
+/// A `Cq` is owned by the one reaper thread of its
+/// ring, which reaps completions without taking any
+/// lock. It is deliberately the only consumer:
+/// multishot operations and zero-copy sends post
+/// several completions under one ticket, which have
+/// to be filled in the order the kernel posted
+/// them, with the ticket only freed after the last
+/// one, so reapers claiming disjoint ranges of the
+/// queue would have to serialize on the tickets
+/// they share anyway. To spread completions over
+/// several threads, spread the operations over
+/// several rings with `ShardedRio`.
+///
+/// Since the reaper thread never runs anything
+/// else, a thread that has to wake up periodically,
+/// say for a timer wheel, waits on its operations
+/// with `Completion::wait_timeout` instead, or
+/// races them against a `Uring::timeout`.
 #[derive(Debug)]
 pub struct Cq {
     khead: *mut AtomicU32,
@@ -14,6 +33,17 @@ pub struct Cq {
     cqes: *mut [io_uring_cqe],
     ticket_queue: Arc<TicketQueue>,
     in_flight: Arc<InFlight>,
+    inbox: Arc<Inbox>,
+    probe: Probe,
+    iopoll: bool,
+    /// Whether the kernel holds on to completions
+    /// that don't fit into the ring, instead of
+    /// dropping them, as of linux 5.5.
+    nodrop: bool,
+    /// How many completions the reaper waits for at
+    /// least, and for how long at most, from
+    /// `Config::reaper_batch`.
+    batch: Option<(u32, __kernel_timespec)>,
     ring_ptr: *const libc::c_void,
     ring_mmap_sz: usize,
 }
@@ -39,6 +69,9 @@ impl Cq {
         ring_fd: i32,
         in_flight: Arc<InFlight>,
         ticket_queue: Arc<TicketQueue>,
+        inbox: Arc<Inbox>,
+        probe: Probe,
+        batch: Option<(u32, __kernel_timespec)>,
     ) -> io::Result<Cq> {
         // TODO IORING_FEAT_SINGLE_MMAP for cq
         let cq_ring_mmap_sz = params.cq_off.cqes as usize
@@ -76,41 +109,109 @@ impl Cq {
                 ),
                 in_flight: in_flight.clone(),
                 ticket_queue: ticket_queue.clone(),
+                inbox,
+                probe,
+                iopoll: params.flags & IORING_SETUP_IOPOLL
+                    != 0,
+                nodrop: params.features
+                    & IORING_FEAT_NODROP
+                    != 0,
+                batch,
             }
         })
     }
 
     pub(crate) fn reaper(&mut self, ring_fd: i32) {
-        fn block_for_cqe(ring_fd: i32) -> io::Result<()> {
+        fn block_for_cqe(
+            ring_fd: i32,
+            batch: &Option<(u32, __kernel_timespec)>,
+        ) -> io::Result<()> {
             let flags = IORING_ENTER_GETEVENTS;
             let submit = 0;
             let wait = 1;
             let sigset = std::ptr::null_mut();
 
             let _ = Measure::new(&M.enter_cqe);
-            enter(ring_fd, submit, wait, flags, sigset)?;
+            if let Some((min_complete, max_wait)) = batch {
+                enter_timeout(
+                    ring_fd,
+                    *min_complete,
+                    flags,
+                    max_wait,
+                )?;
+            } else {
+                enter(
+                    ring_fd, submit, wait, flags, sigset,
+                )?;
+            }
 
             Ok(())
         }
 
         loop {
-            if let Err(e) = block_for_cqe(ring_fd) {
+            if let Err(e) =
+                block_for_cqe(ring_fd, &self.batch)
+            {
                 panic!("error in cqe reaper: {:?}", e);
             } else {
-                assert_eq!(
-                    unsafe {
-                        (*self.koverflow).load(Relaxed)
-                    },
-                    0
-                );
-                if self.reap_ready_cqes().is_none() {
+                self.check_overflow();
+                match self.reap_ready_cqes() {
                     // poison pill detected, time to shut down
-                    return;
+                    None => return,
+                    // in IOPOLL mode, entering returns right
+                    // away when nothing is being polled for,
+                    // instead of sleeping until a completion
+                    // arrives.
+                    Some(0) if self.iopoll => {
+                        std::thread::yield_now()
+                    }
+                    Some(_) => {}
                 }
             }
         }
     }
 
+    /// Panics if the kernel dropped completions,
+    /// which leaves their `Completion`s waiting
+    /// forever.
+    ///
+    /// Every operation holds one of as many tickets
+    /// as the ring has room for completions, so this
+    /// takes more than one completion per ticket from
+    /// multishot operations or zero-copy sends. On
+    /// kernels with `IORING_FEAT_NODROP`, which are
+    /// the only ones that have those, completions
+    /// that don't fit are held back until the next
+    /// `io_uring_enter` with `IORING_ENTER_GETEVENTS`,
+    /// which the reaper does after every batch, or
+    /// the multishot operation is stopped. They are
+    /// only dropped when the kernel runs out of
+    /// memory for holding them.
+    fn check_overflow(&self) {
+        let dropped =
+            unsafe { (*self.koverflow).load(Relaxed) };
+        if dropped == 0 {
+            return;
+        }
+        if self.nodrop {
+            panic!(
+                "the kernel ran out of memory for holding \
+                 back completions that did not fit into \
+                 the completion queue, and dropped {} of \
+                 them. Raise cq_depth to make room.",
+                dropped
+            );
+        } else {
+            panic!(
+                "the completion queue overflowed, and the \
+                 kernel dropped {} completions. It is too \
+                 old to hold them back, which linux 5.5 \
+                 and up do.",
+                dropped
+            );
+        }
+    }
+
     fn reap_ready_cqes(&mut self) -> Option<usize> {
         let _ = Measure::new(&M.reap_ready);
         let mut head =
@@ -132,29 +233,87 @@ impl Cq {
 
             // we detect a poison pill by seeing if
             // the user_data is really big, which it
-            // will tend not to be. if it's not a
-            // poison pill, it will be up to as large
-            // as the completion queue length.
-            let (ticket, poisoned) =
+            // will never be otherwise. if it's not a
+            // poison pill, its low 32 bits are the
+            // ticket, and the bits above that are a
+            // generation that never sets the top two
+            // bits.
+            let (user_data, poisoned) =
                 if cqe.user_data > u64::max_value() / 2 {
                     (cqe.user_data ^ u64::max_value(), true)
                 } else {
                     (cqe.user_data, false)
                 };
 
+            // messages from other rings hold no ticket.
+            if !poisoned
+                && user_data & MSG_RING_USER_DATA != 0
+            {
+                #[allow(clippy::cast_sign_loss)]
+                let len = cqe.res as u32;
+                cq.inbox.push(
+                    user_data ^ MSG_RING_USER_DATA,
+                    len,
+                );
+                unsafe { &*cq.khead }.fetch_add(1, Release);
+                cq_opt = Some(cq);
+                head += 1;
+                continue;
+            }
+            let ticket = user_data & u64::from(u32::MAX);
+
             let res = cqe.res;
 
-            let completion_filler =
-                cq.in_flight.take_filler(ticket as usize);
-            to_push.push(ticket as usize);
+            // multishot operations, and those that
+            // complete a second time to signal that
+            // their buffers are released, keep their
+            // ticket until a cqe arrives without
+            // IORING_CQE_F_MORE set.
+            let more = cqe.flags & IORING_CQE_F_MORE != 0;
 
-            let result = if res < 0 {
+            let slot = usize::try_from(ticket).unwrap();
+            let opcode = cq.in_flight.opcode(slot);
+
+            let result = if matches!(
+                res.neg(),
+                libc::EINVAL
+                    | libc::EOPNOTSUPP
+                    | libc::ENOSYS
+            ) && !cq
+                .probe
+                .is_supported(opcode)
+            {
+                Err(unsupported(opcode))
+            } else if res == -libc::EINVAL
+                && opcode == IORING_OP_RENAMEAT
+            {
+                Err(invalid_rename())
+            } else if res == -libc::EINVAL
+                && opcode == IORING_OP_OPENAT2
+            {
+                Err(invalid_open_how())
+            } else if res == -libc::EOPNOTSUPP && cq.iopoll
+            {
+                Err(not_pollable(opcode))
+            } else if res == -libc::ETIME
+                && opcode == IORING_OP_TIMEOUT
+            {
+                // an expired timeout is its success
+                Ok(*cqe)
+            } else if res < 0 {
                 Err(io::Error::from_raw_os_error(res.neg()))
             } else {
                 Ok(*cqe)
             };
 
-            completion_filler.fill(result);
+            if more {
+                cq.in_flight.fill_more(slot, result);
+            } else {
+                let completion_filler =
+                    cq.in_flight.take_filler(slot);
+                to_push.push(slot);
+                completion_filler.fill(result);
+            }
 
             unsafe { &*cq.khead }.fetch_add(1, Release);
             cq_opt = Some(cq);
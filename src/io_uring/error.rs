@@ -0,0 +1,138 @@
+use std::{error::Error, fmt};
+
+use super::*;
+
+/// What went wrong with an operation, telling
+/// failures to hand it to the kernel apart from the
+/// kernel completing it with an error.
+///
+/// `rio` keeps returning `io::Error`, so that the
+/// errors of the kernel can still be inspected with
+/// `raw_os_error`, and this converts from and into
+/// it. `Completion` turns back into the error of the
+/// kernel that it carries, while the others are
+/// wrapped so that they survive the round trip.
+/// `Syscall` keeps the kind of the error it carries,
+/// whose errno `RioError::from` gets back to.
+///
+/// # Examples
+///
+/// ```no_run
+/// let ring = rio::new().unwrap();
+/// let file = std::fs::File::open("file").unwrap();
+/// let buf = &mut [0_u8; 4096];
+///
+/// match ring.read_at(&file, buf, 0).wait() {
+///     Ok(_) => {}
+///     Err(e) => match rio::RioError::from(e) {
+///         rio::RioError::SubmissionQueueFull => {
+///             // back off and retry
+///         }
+///         other => panic!("read failed: {}", other),
+///     },
+/// }
+/// ```
+#[derive(Debug)]
+pub enum RioError {
+    /// The kernel refused to take more submissions
+    /// with `EBUSY` until completions are reaped.
+    SubmissionQueueFull,
+    /// A syscall for submitting to or setting up the
+    /// ring failed.
+    Syscall(io::Error),
+    /// The kernel completed the operation with an
+    /// error.
+    Completion(io::Error),
+    /// The kernel does not support the operation with
+    /// this opcode, one of the constants in
+    /// `rio::opcode`.
+    Unsupported(u8),
+}
+
+impl RioError {
+    /// Tags an error of the `io_uring_enter` syscall
+    /// that submits to the ring.
+    pub(crate) fn submission(err: io::Error) -> RioError {
+        if err.raw_os_error() == Some(libc::EBUSY) {
+            RioError::SubmissionQueueFull
+        } else {
+            RioError::Syscall(err)
+        }
+    }
+}
+
+impl fmt::Display for RioError {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result {
+        match self {
+            RioError::SubmissionQueueFull => write!(
+                f,
+                "the kernel can't take more submissions \
+                 until completions are reaped."
+            ),
+            RioError::Syscall(e) => {
+                write!(f, "io_uring syscall failed: {}", e)
+            }
+            RioError::Completion(e) => {
+                write!(f, "operation failed: {}", e)
+            }
+            RioError::Unsupported(opcode) => write!(
+                f,
+                "{} (opcode {}) is not supported by the \
+                 running kernel, which is probably too old.",
+                op_name(*opcode),
+                opcode,
+            ),
+        }
+    }
+}
+
+impl Error for RioError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            RioError::Syscall(e)
+            | RioError::Completion(e) => Some(e),
+            RioError::SubmissionQueueFull
+            | RioError::Unsupported(_) => None,
+        }
+    }
+}
+
+impl From<RioError> for io::Error {
+    fn from(err: RioError) -> io::Error {
+        let kind = match err {
+            RioError::SubmissionQueueFull => {
+                io::ErrorKind::WouldBlock
+            }
+            RioError::Syscall(ref e) => e.kind(),
+            RioError::Completion(e) => return e,
+            RioError::Unsupported(_) => {
+                io::ErrorKind::Other
+            }
+        };
+        io::Error::new(kind, err)
+    }
+}
+
+impl From<io::Error> for RioError {
+    fn from(err: io::Error) -> RioError {
+        if err.get_ref().is_none() {
+            return RioError::Completion(err);
+        }
+
+        let kind = err.kind();
+        match err.into_inner() {
+            Some(inner) => {
+                match inner.downcast::<RioError>() {
+                    Ok(rio_error) => *rio_error,
+                    Err(other) => RioError::Completion(
+                        io::Error::new(kind, other),
+                    ),
+                }
+            }
+            None => RioError::Completion(kind.into()),
+        }
+    }
+}
@@ -0,0 +1,45 @@
+use super::*;
+
+/// An index into the table of files registered with
+/// `Uring::register_files`. Operations that are
+/// handed one of these look the file up in that
+/// table instead of taking a reference on a raw
+/// file descriptor, which saves the kernel some
+/// work on every submission.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FixedFile(pub u32);
+
+/// The slot of the registered file table that an
+/// operation like `Uring::accept_direct` installs its
+/// new file into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FileSlot {
+    /// The slot at this index, replacing any file
+    /// that is already in it.
+    Index(u32),
+    /// Any free slot, which the kernel picks.
+    Alloc,
+}
+
+/// Anything that an operation can target: either
+/// a raw file descriptor, for sockets, files, etc...
+/// or a `FixedFile` index into the registered file
+/// table.
+pub trait AsRingFd {
+    /// Returns the value that goes into the fd slot
+    /// of the submission, and whether it is an index
+    /// into the registered file table.
+    fn as_ring_fd(&self) -> (RawFd, bool);
+}
+
+impl<T: AsRawFd> AsRingFd for T {
+    fn as_ring_fd(&self) -> (RawFd, bool) {
+        (self.as_raw_fd(), false)
+    }
+}
+
+impl AsRingFd for FixedFile {
+    fn as_ring_fd(&self) -> (RawFd, bool) {
+        (RawFd::try_from(self.0).unwrap(), true)
+    }
+}
@@ -6,6 +6,13 @@ pub(crate) struct InFlight {
     iovecs: UnsafeCell<Vec<libc::iovec>>,
     msghdrs: UnsafeCell<Vec<libc::msghdr>>,
     fillers: UnsafeCell<Vec<Option<Filler>>>,
+    opcodes: UnsafeCell<Vec<u8>>,
+    /// The descriptor that the operation of each
+    /// ticket was last submitted against, or -1 for
+    /// none or a registered file.
+    fds: UnsafeCell<Vec<RawFd>>,
+    generations: UnsafeCell<Vec<u32>>,
+    completed: AtomicU64,
 }
 
 impl std::fmt::Debug for InFlight {
@@ -37,10 +44,17 @@ impl InFlight {
             filler_vec.push(None);
         }
         let fillers = UnsafeCell::new(filler_vec);
+        let opcodes = UnsafeCell::new(vec![0; size]);
+        let fds = UnsafeCell::new(vec![-1; size]);
+        let generations = UnsafeCell::new(vec![0; size]);
         InFlight {
             iovecs,
             msghdrs,
             fillers,
+            opcodes,
+            fds,
+            generations,
+            completed: 0.into(),
         }
     }
 
@@ -81,13 +95,109 @@ impl InFlight {
         }
     }
 
+    /// Returns a fresh `user_data` for an operation
+    /// that is about to be submitted with `ticket`,
+    /// which is never equal to that of an earlier
+    /// operation that used the same ticket, until
+    /// the generation wraps around after 2^30 uses.
+    /// The two top bits stay clear, for the poison
+    /// pill and for messages from other rings.
+    pub(crate) fn next_user_data(
+        &self,
+        ticket: usize,
+    ) -> u64 {
+        #[allow(unsafe_code)]
+        let generation = unsafe {
+            let generations = &mut *self.generations.get();
+            generations[ticket] = generations[ticket]
+                .wrapping_add(1)
+                & 0x3FFF_FFFF;
+            generations[ticket]
+        };
+        (u64::from(generation) << 32)
+            | u64::try_from(ticket).unwrap()
+    }
+
+    pub(crate) fn set_opcode(
+        &self,
+        ticket: usize,
+        opcode: u8,
+    ) {
+        #[allow(unsafe_code)]
+        unsafe {
+            (&mut *self.opcodes.get())[ticket] = opcode;
+        }
+    }
+
+    pub(crate) fn set_fd(&self, ticket: usize, fd: RawFd) {
+        #[allow(unsafe_code)]
+        unsafe {
+            (&mut *self.fds.get())[ticket] = fd;
+        }
+    }
+
+    /// The `user_data` of the operation that each
+    /// ticket was last submitted with, among those
+    /// that were submitted against `fd`, which may
+    /// have completed already. Like the tickets'
+    /// fds and generations, this must only be used
+    /// with the submission queue locked.
+    pub(crate) fn user_data_for_fd(
+        &self,
+        fd: RawFd,
+    ) -> Vec<u64> {
+        #[allow(unsafe_code)]
+        let (fds, generations) = unsafe {
+            (&*self.fds.get(), &*self.generations.get())
+        };
+        fds.iter()
+            .zip(generations)
+            .enumerate()
+            .filter(|(_, (ticket_fd, generation))| {
+                **ticket_fd == fd && **generation != 0
+            })
+            .map(|(ticket, (_, generation))| {
+                (u64::from(*generation) << 32)
+                    | u64::try_from(ticket).unwrap()
+            })
+            .collect()
+    }
+
+    pub(crate) fn opcode(&self, ticket: usize) -> u8 {
+        #[allow(unsafe_code)]
+        unsafe {
+            (&*self.opcodes.get())[ticket]
+        }
+    }
+
+    pub(crate) fn fill_more(
+        &self,
+        ticket: usize,
+        result: io::Result<io_uring_cqe>,
+    ) {
+        #[allow(unsafe_code)]
+        unsafe {
+            (&*self.fillers.get())[ticket]
+                .as_ref()
+                .unwrap()
+                .fill_more(result);
+        }
+    }
+
     pub(crate) fn take_filler(
         &self,
         ticket: usize,
     ) -> Filler {
+        self.completed.fetch_add(1, Release);
         #[allow(unsafe_code)]
         unsafe {
             (*self.fillers.get())[ticket].take().unwrap()
         }
     }
+
+    /// How many operations received their final
+    /// completion so far.
+    pub(crate) fn completed(&self) -> u64 {
+        self.completed.load(Acquire)
+    }
 }
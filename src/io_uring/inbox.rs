@@ -0,0 +1,60 @@
+use std::collections::VecDeque;
+
+use super::*;
+
+/// Marks the `user_data` of a completion that
+/// another ring posted with `Uring::msg_ring`, which
+/// operations never set, as their generation only
+/// reaches up to bit 61.
+pub(crate) const MSG_RING_USER_DATA: u64 = 1 << 62;
+
+/// The messages that other rings posted to this one
+/// with `Uring::msg_ring`, in the order the reaper
+/// found them.
+#[derive(Debug, Default)]
+pub(crate) struct Inbox {
+    mu: Mutex<Messages>,
+    cv: Condvar,
+}
+
+#[derive(Debug, Default)]
+struct Messages {
+    queue: VecDeque<(u64, u32)>,
+    /// Tasks waiting in `poll_pop`.
+    wakers: Vec<Waker>,
+}
+
+impl Inbox {
+    pub(crate) fn push(&self, data: u64, len: u32) {
+        let mut messages = self.mu.lock().unwrap();
+        messages.queue.push_back((data, len));
+        self.cv.notify_one();
+        for waker in messages.wakers.drain(..) {
+            waker.wake();
+        }
+    }
+
+    pub(crate) fn pop(&self) -> (u64, u32) {
+        let mut messages = self.mu.lock().unwrap();
+        loop {
+            if let Some(message) =
+                messages.queue.pop_front()
+            {
+                return message;
+            }
+            messages = self.cv.wait(messages).unwrap();
+        }
+    }
+
+    pub(crate) fn poll_pop(
+        &self,
+        cx: &mut Context<'_>,
+    ) -> Poll<(u64, u32)> {
+        let mut messages = self.mu.lock().unwrap();
+        if let Some(message) = messages.queue.pop_front() {
+            return Poll::Ready(message);
+        }
+        messages.wakers.push(cx.waker().clone());
+        Poll::Pending
+    }
+}
@@ -5,6 +5,11 @@ use super::*;
 /// Generated by bindgen, then cleaned up
 /// Target: linux 5.3.9_p3-debian-sources
 ///         `include/uapi/linux/io_uring.h`
+///
+/// The tail of `io_uring_sqe` has since been
+/// extended by hand to follow newer kernels,
+/// and `off` and `addr` double as `addr2` and
+/// `splice_off_in`.
 use std::fmt;
 
 #[repr(C)]
@@ -23,7 +28,9 @@ pub struct io_uring_params {
     pub flags: u32,
     pub sq_thread_cpu: u32,
     pub sq_thread_idle: u32,
-    pub resv: [u32; 5_usize],
+    pub features: u32,
+    pub wq_fd: u32,
+    pub resv: [u32; 3_usize],
     pub sq_off: io_sqring_offsets,
     pub cq_off: io_cqring_offsets,
 }
@@ -65,17 +72,63 @@ impl io_uring_sqe {
         };
 
         self.__bindgen_anon_1.rw_flags = 0;
-        self.__bindgen_anon_2.__pad2 = [0; 3];
+        self.__bindgen_anon_2 =
+            io_uring_sqe__bindgen_ty_2::default();
 
         self.apply_order(ordering);
     }
 
+    /// Like `prep_rw`, but targets either a raw fd
+    /// or an index into the registered file table.
+    pub(crate) fn prep_rw_file<F: AsRingFd>(
+        &mut self,
+        opcode: u8,
+        file: &F,
+        len: usize,
+        off: u64,
+        ordering: Ordering,
+    ) {
+        let (file_descriptor, fixed) = file.as_ring_fd();
+        self.prep_rw(
+            opcode,
+            file_descriptor,
+            len,
+            off,
+            ordering,
+        );
+        if fixed {
+            self.flags |= IOSQE_FIXED_FILE;
+        }
+    }
+
+    /// Sets the file that a splice or tee reads
+    /// from, which is flagged with
+    /// `SPLICE_F_FD_IN_FIXED` when it is registered,
+    /// along with the `SPLICE_F_*` `flags`.
+    pub(crate) fn set_splice_fd_in<F: AsRingFd>(
+        &mut self,
+        file: &F,
+        flags: u32,
+    ) {
+        let (file_descriptor, fixed) = file.as_ring_fd();
+        self.__bindgen_anon_2.splice_fd_in =
+            file_descriptor;
+        self.__bindgen_anon_1.splice_flags = if fixed {
+            flags | SPLICE_F_FD_IN_FIXED
+        } else {
+            flags
+        };
+    }
+
     fn apply_order(&mut self, ordering: Ordering) {
         match ordering {
             Ordering::None => {}
             Ordering::Link => {
                 self.flags |= IOSQE_IO_LINK
             }
+            Ordering::HardLink => {
+                self.flags |= IOSQE_IO_HARDLINK
+            }
             Ordering::Drain => {
                 self.flags |= IOSQE_IO_DRAIN
             }
@@ -83,14 +136,35 @@ impl io_uring_sqe {
     }
 }
 
+#[repr(C)]
+#[derive(Default, Debug, Copy, Clone)]
+pub struct __kernel_timespec {
+    pub tv_sec: i64,
+    pub tv_nsec: i64,
+}
+
 #[repr(C)]
 #[derive(Copy, Clone)]
 pub union io_uring_sqe__bindgen_ty_1 {
     pub rw_flags: __kernel_rwf_t,
     pub fsync_flags: u32,
     pub poll_events: u16,
+    /// Read as the combined `poll_events` and the
+    /// next 16 bits on kernels that set
+    /// `IORING_FEAT_POLL_32BITS`.
+    pub poll32_events: u32,
     pub sync_range_flags: u32,
     pub msg_flags: u32,
+    pub accept_flags: u32,
+    pub open_flags: u32,
+    pub statx_flags: u32,
+    pub fadvise_advice: u32,
+    pub splice_flags: u32,
+    pub timeout_flags: u32,
+    pub cancel_flags: u32,
+    pub rename_flags: u32,
+    pub unlink_flags: u32,
+    pub hardlink_flags: u32,
     _bindgen_union_align: u32,
 }
 
@@ -113,29 +187,15 @@ impl fmt::Debug for io_uring_sqe__bindgen_ty_1 {
 }
 
 #[repr(C)]
-#[derive(Copy, Clone)]
-pub union io_uring_sqe__bindgen_ty_2 {
+#[derive(Default, Debug, Copy, Clone)]
+pub struct io_uring_sqe__bindgen_ty_2 {
+    /// Also used as `buf_group`.
     pub buf_index: u16,
-    pub __pad2: [u64; 3_usize],
-    _bindgen_union_align: [u64; 3_usize],
-}
-
-impl fmt::Debug for io_uring_sqe__bindgen_ty_2 {
-    fn fmt(
-        &self,
-        f: &mut fmt::Formatter<'_>,
-    ) -> fmt::Result {
-        write!(f, "io_uring_sqe__bindgen_ty_2")
-    }
-}
-
-impl Default for io_uring_sqe__bindgen_ty_2 {
-    fn default() -> io_uring_sqe__bindgen_ty_2 {
-        #[allow(unsafe_code)]
-        unsafe {
-            std::mem::zeroed()
-        }
-    }
+    pub personality: u16,
+    /// Also used as `file_index`.
+    pub splice_fd_in: i32,
+    pub addr3: u64,
+    pub __pad2: [u64; 1_usize],
 }
 
 #[repr(C)]
@@ -163,3 +223,81 @@ pub struct io_cqring_offsets {
     pub cqes: u32,
     pub resv: [u64; 2_usize],
 }
+
+#[repr(C)]
+#[derive(Default, Debug, Copy, Clone)]
+pub struct io_uring_getevents_arg {
+    pub sigmask: u64,
+    pub sigmask_sz: u32,
+    pub pad: u32,
+    pub ts: u64,
+}
+
+#[repr(C)]
+#[derive(Default, Debug, Copy, Clone)]
+pub struct io_uring_rsrc_register {
+    pub nr: u32,
+    pub flags: u32,
+    pub resv2: u64,
+    pub data: u64,
+    pub tags: u64,
+}
+
+#[repr(C)]
+#[derive(Default, Debug, Copy, Clone)]
+pub struct io_uring_rsrc_update {
+    pub offset: u32,
+    pub resv: u32,
+    pub data: u64,
+}
+
+#[repr(C)]
+#[derive(Default, Debug, Copy, Clone)]
+pub struct io_uring_buf {
+    pub addr: u64,
+    pub len: u32,
+    pub bid: u16,
+    /// The tail of the ring, in its first entry.
+    pub resv: u16,
+}
+
+#[repr(C)]
+#[derive(Default, Debug, Copy, Clone)]
+pub struct io_uring_buf_reg {
+    pub ring_addr: u64,
+    pub ring_entries: u32,
+    pub bgid: u16,
+    pub flags: u16,
+    pub resv: [u64; 3_usize],
+}
+
+#[repr(C)]
+#[derive(Default, Debug, Copy, Clone)]
+pub struct io_uring_probe_op {
+    pub op: u8,
+    pub resv: u8,
+    pub flags: u16,
+    pub resv2: u32,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct io_uring_probe {
+    pub last_op: u8,
+    pub ops_len: u8,
+    pub resv: u16,
+    pub resv2: [u32; 3_usize],
+    pub ops: [io_uring_probe_op; 256_usize],
+}
+
+impl Default for io_uring_probe {
+    fn default() -> io_uring_probe {
+        io_uring_probe {
+            last_op: 0,
+            ops_len: 0,
+            resv: 0,
+            resv2: [0; 3],
+            ops: [io_uring_probe_op::default(); 256],
+        }
+    }
+}
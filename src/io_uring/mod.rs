@@ -1,53 +1,158 @@
 use std::{
-    cell::UnsafeCell,
+    cell::{Cell, RefCell, UnsafeCell},
     convert::TryFrom,
+    ffi::CStr,
     fs::File,
-    io,
-    net::{TcpListener, TcpStream},
+    io::{self, IoSlice, IoSliceMut},
+    net::{
+        Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4,
+        SocketAddrV6, TcpListener, TcpStream,
+    },
     ops::Neg,
-    os::unix::io::{AsRawFd, FromRawFd},
+    os::unix::io::{AsRawFd, FromRawFd, RawFd},
     sync::{
         atomic::{
-            AtomicU32, AtomicU64,
-            Ordering::{Acquire, Relaxed, Release},
+            fence, AtomicU32, AtomicU64,
+            Ordering::{Acquire, Relaxed, Release, SeqCst},
         },
-        Arc, Condvar, Mutex,
+        Arc, Condvar, Mutex, MutexGuard, Weak,
     },
+    task::{Context, Poll, Waker},
+    thread::JoinHandle,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
     mem::MaybeUninit
 };
 
 use super::{
-    pair, AsIoVec, AsIoVecMut, Completion, Filler, FromCqe,
-    Measure, M,
+    failed, pair, stream_pair, AsIoVec, AsIoVecMut,
+    Completion, CompletionStream, Filler, FromCqe,
+    Measure, ProfileSnapshot, M,
 };
 
+mod buf_ring;
+mod buffer_pool;
+mod chain;
 mod config;
 mod constants;
 mod cq;
+mod error;
+mod fixed_file;
 mod in_flight;
+mod inbox;
 mod kernel_types;
+mod msghdr;
+mod probe;
+mod sharded;
+mod splice_pipe;
 mod sq;
+mod stats;
 mod syscall;
 mod ticket_queue;
 mod uring;
 
 pub(crate) use {
+    buffer_pool::anonymous_mmap,
+    chain::empty_chain,
+    config::kernel_version,
     constants::*,
     cq::Cq,
     in_flight::InFlight,
+    inbox::{Inbox, MSG_RING_USER_DATA},
     kernel_types::{
-        io_uring_cqe, io_uring_params, io_uring_sqe,
+        __kernel_timespec, io_uring_buf, io_uring_buf_reg,
+        io_uring_cqe, io_uring_getevents_arg,
+        io_uring_params, io_uring_probe,
+        io_uring_rsrc_register, io_uring_rsrc_update,
+        io_uring_sqe,
+    },
+    probe::{
+        invalid_open_how, invalid_rename, not_pollable,
+        unsupported,
     },
+    splice_pipe::{PipeLease, SplicePipe},
     sq::Sq,
-    syscall::{enter, setup},
+    syscall::{enter, enter_timeout, register, setup},
     ticket_queue::TicketQueue,
+    uring::{dur2timespec, enter_target},
 };
 
 pub use {
-    config::Config,
+    buf_ring::BufRing,
+    buffer_pool::{BufferPool, PooledBuffer},
+    chain::Chain,
+    config::{Config, ConfigBuilder},
+    error::RioError,
+    fixed_file::{AsRingFd, FileSlot, FixedFile},
+    msghdr::{Msghdr, MsghdrMut},
+    probe::Probe,
+    sharded::ShardedRio,
+    stats::RioStats,
     uring::{Rio, Uring},
 };
 
+pub(crate) use super::completion::Pinned;
+
+/// The `IORING_OP_*` opcodes that identify each kind
+/// of operation, as passed to `Probe::is_supported`.
+pub mod opcode {
+    pub use super::constants::{
+        IORING_OP_ACCEPT, IORING_OP_ASYNC_CANCEL,
+        IORING_OP_CLOSE, IORING_OP_CONNECT,
+        IORING_OP_EPOLL_CTL, IORING_OP_FADVISE,
+        IORING_OP_FALLOCATE, IORING_OP_FGETXATTR,
+        IORING_OP_FILES_UPDATE, IORING_OP_FSETXATTR,
+        IORING_OP_FSYNC, IORING_OP_FTRUNCATE,
+        IORING_OP_GETXATTR, IORING_OP_LINKAT,
+        IORING_OP_LINK_TIMEOUT, IORING_OP_MADVISE,
+        IORING_OP_MKDIRAT, IORING_OP_MSG_RING,
+        IORING_OP_NOP, IORING_OP_OPENAT, IORING_OP_OPENAT2,
+        IORING_OP_POLL_ADD, IORING_OP_POLL_REMOVE,
+        IORING_OP_PROVIDE_BUFFERS, IORING_OP_READ,
+        IORING_OP_READV, IORING_OP_READ_FIXED,
+        IORING_OP_RECV, IORING_OP_RECVMSG,
+        IORING_OP_REMOVE_BUFFERS, IORING_OP_RENAMEAT,
+        IORING_OP_SEND, IORING_OP_SENDMSG,
+        IORING_OP_SEND_ZC, IORING_OP_SETXATTR,
+        IORING_OP_SHUTDOWN, IORING_OP_SOCKET,
+        IORING_OP_SPLICE, IORING_OP_STATX,
+        IORING_OP_SYMLINKAT, IORING_OP_SYNC_FILE_RANGE,
+        IORING_OP_TEE, IORING_OP_TIMEOUT,
+        IORING_OP_TIMEOUT_REMOVE, IORING_OP_UNLINKAT,
+        IORING_OP_URING_CMD, IORING_OP_WRITE,
+        IORING_OP_WRITEV, IORING_OP_WRITE_FIXED,
+    };
+}
+
+/// The offset to pass to `read_at`, `write_at` and
+/// the other positioned operations to make them use,
+/// and advance, the file's own position instead of
+/// an explicit one, like `read` and `write` do. This
+/// is the only offset besides 0 that pipes and
+/// sockets accept, and with `O_APPEND` it lets the
+/// kernel append at the end of the file.
+///
+/// # Warning
+///
+/// This only becomes usable on linux kernels 5.6 and up.
+pub const CURRENT_POSITION: u64 = u64::MAX;
+
+/// Stands in for the fd of a slot in the table
+/// returned by `Uring::registered_files` that holds
+/// a file without a descriptor in this process, such
+/// as a socket accepted by `Uring::accept_direct`.
+pub const DIRECT_DESCRIPTOR: RawFd = -2;
+
+/// The `IORING_CQE_F_*` flags that the kernel
+/// completes operations with, as handed back by
+/// `Completion::with_flags`.
+pub mod cqe_flags {
+    pub use super::constants::{
+        IORING_CQE_BUFFER_SHIFT, IORING_CQE_F_BUFFER,
+        IORING_CQE_F_MORE, IORING_CQE_F_NOTIF,
+        IORING_CQE_F_SOCK_NONEMPTY,
+    };
+}
+
 /// Specify whether `io_uring` should
 /// run operations in a specific order.
 /// By default, it will run independent
@@ -68,12 +173,230 @@ pub enum Ordering {
     /// things like file copy, fsync-after-write,
     /// or proxies.
     Link,
+    /// `Ordering::HardLink` also causes the next
+    /// submitted operation to wait until this one
+    /// finishes, but unlike `Ordering::Link`, it runs
+    /// the next one even if this one fails or comes
+    /// up short, like a short read.
+    ///
+    /// This only becomes usable on linux kernels
+    /// 5.5 and up.
+    HardLink,
     /// `Ordering::Drain` causes all previously
     /// submitted operations to complete before
     /// this one begins.
     Drain,
 }
 
+/// Conversions from the raw timestamps of a
+/// `libc::statx`, as returned by `Uring::statx`,
+/// into `SystemTime`s.
+pub trait StatxExt {
+    /// The last access time, if `STATX_ATIME`
+    /// was requested.
+    fn accessed(&self) -> SystemTime;
+    /// The last modification time, if
+    /// `STATX_MTIME` was requested.
+    fn modified(&self) -> SystemTime;
+    /// The last status change time, if
+    /// `STATX_CTIME` was requested.
+    fn status_changed(&self) -> SystemTime;
+    /// The creation time, if `STATX_BTIME` was
+    /// requested and the filesystem supports it.
+    fn created(&self) -> SystemTime;
+}
+
+impl StatxExt for libc::statx {
+    fn accessed(&self) -> SystemTime {
+        timestamp2system_time(self.stx_atime)
+    }
+
+    fn modified(&self) -> SystemTime {
+        timestamp2system_time(self.stx_mtime)
+    }
+
+    fn status_changed(&self) -> SystemTime {
+        timestamp2system_time(self.stx_ctime)
+    }
+
+    fn created(&self) -> SystemTime {
+        timestamp2system_time(self.stx_btime)
+    }
+}
+
+/// The metadata of a file as returned by
+/// `Uring::stat`, decoded from a `libc::statx`
+/// along the lines of `std::fs::Metadata`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileStat {
+    len: u64,
+    mode: u32,
+    accessed: SystemTime,
+    modified: SystemTime,
+    status_changed: SystemTime,
+    created: Option<SystemTime>,
+}
+
+impl FileStat {
+    /// The size of the file in bytes.
+    pub const fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Whether the file is empty.
+    pub const fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The file type and permission bits, as in
+    /// `st_mode`.
+    pub const fn mode(&self) -> u32 {
+        self.mode
+    }
+
+    /// Whether this is a directory.
+    pub const fn is_dir(&self) -> bool {
+        self.mode & libc::S_IFMT == libc::S_IFDIR
+    }
+
+    /// Whether this is a regular file.
+    pub const fn is_file(&self) -> bool {
+        self.mode & libc::S_IFMT == libc::S_IFREG
+    }
+
+    /// Whether this is a symbolic link, which can
+    /// only be the case when it was not followed.
+    pub const fn is_symlink(&self) -> bool {
+        self.mode & libc::S_IFMT == libc::S_IFLNK
+    }
+
+    /// The permissions of the file.
+    pub fn permissions(&self) -> std::fs::Permissions {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::Permissions::from_mode(self.mode & 0o7777)
+    }
+
+    /// The last access time.
+    pub const fn accessed(&self) -> SystemTime {
+        self.accessed
+    }
+
+    /// The last modification time.
+    pub const fn modified(&self) -> SystemTime {
+        self.modified
+    }
+
+    /// The last status change time.
+    pub const fn status_changed(&self) -> SystemTime {
+        self.status_changed
+    }
+
+    /// The creation time, if the filesystem records
+    /// it.
+    pub const fn created(&self) -> Option<SystemTime> {
+        self.created
+    }
+}
+
+impl From<libc::statx> for FileStat {
+    fn from(statx: libc::statx) -> FileStat {
+        FileStat {
+            len: statx.stx_size,
+            mode: u32::from(statx.stx_mode),
+            accessed: statx.accessed(),
+            modified: statx.modified(),
+            status_changed: statx.status_changed(),
+            created: if statx.stx_mask & libc::STATX_BTIME
+                == 0
+            {
+                None
+            } else {
+                Some(statx.created())
+            },
+        }
+    }
+}
+
+/// How `Uring::openat2` opens a file, laid out like
+/// the `open_how` struct of `openat2(2)`.
+///
+/// `flags` and `mode` are the same as for `openat`,
+/// and `resolve` takes the `libc::RESOLVE_*` flags
+/// that restrict how the path is resolved, like
+/// `RESOLVE_BENEATH` to keep it from escaping the
+/// directory, or `RESOLVE_NO_SYMLINKS` to refuse
+/// following any symlink on the way.
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct OpenHow {
+    /// The `O_*` flags to open the file with.
+    pub flags: u64,
+    /// The permissions of a file that `O_CREAT` or
+    /// `O_TMPFILE` creates, which must be 0
+    /// otherwise.
+    pub mode: u64,
+    /// The `RESOLVE_*` flags.
+    pub resolve: u64,
+}
+
+/// The scheduling classes of `ioprio_set(2)`, which
+/// decide how the block layer orders an operation
+/// against others on the same device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IoPriorityClass {
+    /// Derive the priority from the CPU niceness of
+    /// the submitting thread.
+    None,
+    /// Served before all other classes, which
+    /// requires `CAP_SYS_ADMIN` or `CAP_SYS_NICE`.
+    RealTime,
+    /// The default class for processes.
+    BestEffort,
+    /// Only served when nothing else needs the
+    /// device.
+    Idle,
+}
+
+/// The I/O priority that `Completion::with_ioprio`
+/// gives a read or write, as a class and a level
+/// within it. Levels go from 0, the highest, to 7,
+/// must be 0 for the `None` class, and are ignored
+/// for the `Idle` class.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IoPriority {
+    /// The scheduling class.
+    pub class: IoPriorityClass,
+    /// The level within the class.
+    pub level: u16,
+}
+
+impl IoPriority {
+    /// Packs the class and level into the `ioprio`
+    /// value that the kernel takes, with the class
+    /// in the top 3 bits.
+    pub const fn to_ioprio(self) -> u16 {
+        let class = match self.class {
+            IoPriorityClass::None => 0,
+            IoPriorityClass::RealTime => 1,
+            IoPriorityClass::BestEffort => 2,
+            IoPriorityClass::Idle => 3,
+        };
+        (class << 13) | self.level
+    }
+}
+
+fn timestamp2system_time(
+    ts: libc::statx_timestamp,
+) -> SystemTime {
+    let nanos = Duration::from_nanos(u64::from(ts.tv_nsec));
+    if let Ok(secs) = u64::try_from(ts.tv_sec) {
+        UNIX_EPOCH + Duration::from_secs(secs) + nanos
+    } else {
+        let secs = ts.tv_sec.unsigned_abs();
+        UNIX_EPOCH - Duration::from_secs(secs) + nanos
+    }
+}
+
 fn uring_mmap(
     size: usize,
     ring_fd: i32,
@@ -107,6 +430,28 @@ fn uring_mmap(
     Ok(ptr)
 }
 
+/// The id of the buffer that the kernel selected
+/// from a group registered with `provide_buffers`,
+/// and the number of bytes it filled in it.
+///
+/// The kernel doesn't pick a buffer for a zero
+/// length result, like the end of a stream, in
+/// which case the id is 0 and holds no data.
+impl FromCqe for (u16, usize) {
+    fn from_cqe(cqe: io_uring_cqe) -> (u16, usize) {
+        if cqe.flags & IORING_CQE_F_BUFFER == 0 {
+            return (0, usize::try_from(cqe.res).unwrap());
+        }
+        (
+            u16::try_from(
+                cqe.flags >> IORING_CQE_BUFFER_SHIFT,
+            )
+            .unwrap(),
+            usize::try_from(cqe.res).unwrap(),
+        )
+    }
+}
+
 impl FromCqe for TcpStream {
     fn from_cqe(cqe: io_uring_cqe) -> TcpStream {
         #[allow(unsafe_code)]
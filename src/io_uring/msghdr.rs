@@ -0,0 +1,138 @@
+use std::{
+    io::{IoSlice, IoSliceMut},
+    marker::PhantomData,
+};
+
+use super::*;
+
+/// The description of a message to be sent with
+/// `sendmsg`, borrowing the buffers that make up
+/// its data, its destination address, and its
+/// ancillary data for as long as it lives.
+#[derive(Debug)]
+pub struct Msghdr<'a> {
+    pub(crate) inner: libc::msghdr,
+    lifetime: PhantomData<&'a [u8]>,
+}
+
+impl<'a> Msghdr<'a> {
+    /// A message consisting of the data in `iov`,
+    /// gathered in order.
+    pub const fn new(iov: &'a [IoSlice<'a>]) -> Msghdr<'a> {
+        #[allow(unsafe_code)]
+        let mut inner: libc::msghdr =
+            unsafe { MaybeUninit::zeroed().assume_init() };
+        inner.msg_iov = iov.as_ptr() as *mut libc::iovec;
+        inner.msg_iovlen = iov.len();
+
+        Msghdr {
+            inner,
+            lifetime: PhantomData,
+        }
+    }
+
+    /// Sends the message to the given address, such
+    /// as a `libc::sockaddr_in`, for sockets that
+    /// aren't connected.
+    pub fn name<T>(mut self, name: &'a T) -> Msghdr<'a> {
+        let name_ptr: *const T = name;
+        self.inner.msg_name = name_ptr as *mut libc::c_void;
+        self.inner.msg_namelen =
+            u32::try_from(size_of::<T>()).unwrap();
+        self
+    }
+
+    /// Attaches ancillary data, which is a sequence of
+    /// control messages laid out with the `CMSG_*`
+    /// functions, such as `SCM_RIGHTS`. The buffer
+    /// must be aligned like a `libc::cmsghdr`.
+    pub const fn control(
+        mut self,
+        control: &'a [u8],
+    ) -> Msghdr<'a> {
+        self.inner.msg_control =
+            control.as_ptr() as *mut libc::c_void;
+        self.inner.msg_controllen = control.len();
+        self
+    }
+}
+
+/// The description of a message to be received
+/// with `recvmsg`, borrowing the buffers that its
+/// data, its source address and its ancillary data
+/// are written into for as long as it lives.
+///
+/// Once the receive completes, it reports how much
+/// of the address and ancillary data buffers were
+/// filled in, and the flags of the received message.
+#[derive(Debug)]
+pub struct MsghdrMut<'a> {
+    pub(crate) inner: libc::msghdr,
+    lifetime: PhantomData<&'a mut [u8]>,
+}
+
+impl<'a> MsghdrMut<'a> {
+    /// A message whose data is scattered into the
+    /// buffers in `iov`, in order.
+    pub const fn new(
+        iov: &'a mut [IoSliceMut<'a>],
+    ) -> MsghdrMut<'a> {
+        #[allow(unsafe_code)]
+        let mut inner: libc::msghdr =
+            unsafe { MaybeUninit::zeroed().assume_init() };
+        inner.msg_iov =
+            iov.as_mut_ptr() as *mut libc::iovec;
+        inner.msg_iovlen = iov.len();
+
+        MsghdrMut {
+            inner,
+            lifetime: PhantomData,
+        }
+    }
+
+    /// Receives the address that the message was sent
+    /// from, into a buffer such as a
+    /// `libc::sockaddr_storage`.
+    pub fn name<T>(
+        mut self,
+        name: &'a mut T,
+    ) -> MsghdrMut<'a> {
+        let name_ptr: *mut T = name;
+        self.inner.msg_name = name_ptr as *mut libc::c_void;
+        self.inner.msg_namelen =
+            u32::try_from(size_of::<T>()).unwrap();
+        self
+    }
+
+    /// Receives ancillary data into `control`, which
+    /// can then be walked with the `CMSG_*`
+    /// functions. The buffer must be aligned like a
+    /// `libc::cmsghdr`.
+    pub const fn control(
+        mut self,
+        control: &'a mut [u8],
+    ) -> MsghdrMut<'a> {
+        self.inner.msg_control =
+            control.as_mut_ptr() as *mut libc::c_void;
+        self.inner.msg_controllen = control.len();
+        self
+    }
+
+    /// The `MSG_*` flags of the received message,
+    /// such as `MSG_TRUNC` or `MSG_CTRUNC`.
+    pub const fn flags(&self) -> i32 {
+        self.inner.msg_flags
+    }
+
+    /// The length of the source address that was
+    /// written into the buffer passed to `name`.
+    pub fn name_len(&self) -> usize {
+        usize::try_from(self.inner.msg_namelen).unwrap()
+    }
+
+    /// The length of the ancillary data that was
+    /// written into the buffer passed to `control`.
+    pub const fn control_len(&self) -> usize {
+        self.inner.msg_controllen
+    }
+}
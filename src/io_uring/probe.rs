@@ -0,0 +1,125 @@
+use super::*;
+
+/// The set of operations supported by the
+/// running kernel, as reported by
+/// `IORING_REGISTER_PROBE`.
+///
+/// # Examples
+///
+/// ```no_run
+/// use rio::opcode::IORING_OP_SEND_ZC;
+///
+/// let ring = rio::new().unwrap();
+/// let probe = ring.probe().unwrap();
+/// if !probe.is_supported(IORING_OP_SEND_ZC) {
+///     // fall back to a plain send
+/// }
+/// ```
+#[derive(Clone, Copy)]
+pub struct Probe {
+    supported: [bool; 256],
+}
+
+impl std::fmt::Debug for Probe {
+    fn fmt(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+    ) -> std::fmt::Result {
+        write!(f, "Probe {{ .. }}")
+    }
+}
+
+impl Probe {
+    pub(crate) fn new(ring_fd: i32) -> io::Result<Probe> {
+        let mut probe = Box::new(io_uring_probe::default());
+        let probe_ptr: *mut io_uring_probe = &mut *probe;
+
+        register(
+            ring_fd,
+            IORING_REGISTER_PROBE,
+            probe_ptr as *const libc::c_void,
+            256,
+        )?;
+
+        let mut supported = [false; 256];
+        for op in &probe.ops[..probe.ops_len as usize] {
+            supported[op.op as usize] =
+                op.flags & IO_URING_OP_SUPPORTED != 0;
+        }
+
+        Ok(Probe { supported })
+    }
+
+    /// Kernels older than 5.6 can't be probed,
+    /// but they also lack every operation from
+    /// `IORING_OP_FALLOCATE` on, which were all
+    /// introduced in the same release as the
+    /// probe itself.
+    pub(crate) fn fallback() -> Probe {
+        let mut supported = [false; 256];
+        for op in 0..IORING_OP_FALLOCATE {
+            supported[op as usize] = true;
+        }
+        Probe { supported }
+    }
+
+    /// Whether the running kernel supports the
+    /// operation with the given `opcode`, one of the
+    /// constants in `rio::opcode`.
+    pub const fn is_supported(&self, opcode: u8) -> bool {
+        self.supported[opcode as usize]
+    }
+}
+
+/// The error returned in place of `EINVAL`,
+/// `EOPNOTSUPP` or `ENOSYS` when the kernel does not
+/// support an operation at all.
+pub(crate) fn unsupported(opcode: u8) -> io::Error {
+    RioError::Unsupported(opcode).into()
+}
+
+/// The error returned in place of `EINVAL` from a
+/// rename, which the kernel also reports when it or
+/// the filesystem does not know the requested
+/// `RENAME_*` flags.
+pub(crate) fn invalid_rename() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        "renameat failed with EINVAL: either the \
+         rename itself is invalid, such as moving a \
+         directory into itself, or the RENAME_* flags \
+         are not supported by the running kernel or \
+         the filesystem.",
+    )
+}
+
+/// The error returned in place of `EINVAL` from
+/// `openat2`, which the kernel reports for invalid
+/// flags or modes, and for `RESOLVE_*` flags it
+/// does not know.
+pub(crate) fn invalid_open_how() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        "openat2 failed with EINVAL: either the flags \
+         or mode of the OpenHow are invalid, such as a \
+         mode without O_CREAT, or its RESOLVE_* flags \
+         are not supported by the running kernel.",
+    )
+}
+
+/// The error returned in place of `EOPNOTSUPP` on a
+/// ring in `IOPOLL` mode, which can only poll for
+/// the completion of some operations on some files.
+pub(crate) fn not_pollable(opcode: u8) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Other,
+        format!(
+            "{} (opcode {}) can't be polled for in \
+             io_poll mode, which only supports reads and \
+             writes on files opened with O_DIRECT on \
+             block devices that support polling.",
+            op_name(opcode),
+            opcode,
+        ),
+    )
+}
@@ -0,0 +1,137 @@
+use super::*;
+
+/// A set of rings, one per CPU by default, that
+/// spreads submissions over several `Sq` locks
+/// instead of contending on a single one.
+///
+/// It derefs to the ring of the CPU that the
+/// calling thread currently runs on, so it offers
+/// every operation of `Rio`, and each `Completion`
+/// stays tied to the ring it was submitted to, even
+/// if the thread migrates to another CPU before
+/// waiting on it. The reaper thread of each ring is
+/// pinned onto its CPU.
+///
+/// Registrations, like `register_files`, only
+/// apply to the ring they are made on, so they
+/// need to be made on each of `shards` to be
+/// usable through the deref.
+///
+/// # Examples
+///
+/// ```no_run
+/// let rings = rio::ShardedRio::new(rio::Config::default())
+///     .expect("create rings");
+///
+/// let file = std::fs::File::open("file").unwrap();
+/// let buf = &mut [0_u8; 4096];
+/// rings.read_at(&file, buf, 0).wait().unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct ShardedRio {
+    shards: Vec<Rio>,
+    /// The index of the shard for each CPU id.
+    by_cpu: Vec<usize>,
+}
+
+impl ShardedRio {
+    /// Starts one ring with the given `Config` for
+    /// each CPU that this process may run on.
+    pub fn new(config: Config) -> io::Result<ShardedRio> {
+        let count = allowed_cpus()?.len();
+        ShardedRio::with_shards(config, count)
+    }
+
+    /// Starts `count` rings with the given `Config`,
+    /// spreading them over the CPUs that this process
+    /// may run on. If there are more CPUs than rings,
+    /// several CPUs share a ring, and if there are
+    /// fewer, the rings beyond one per CPU are only
+    /// reachable through `shards`.
+    pub fn with_shards(
+        config: Config,
+        count: usize,
+    ) -> io::Result<ShardedRio> {
+        if count == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "a ShardedRio needs at least one shard.",
+            ));
+        }
+
+        let cpus = allowed_cpus()?;
+
+        let mut shards = Vec::with_capacity(count);
+        for index in 0..count {
+            let mut shard_config = config;
+            shard_config.reaper_affinity =
+                Some(cpus[index % cpus.len()]);
+            shards.push(shard_config.start()?);
+        }
+
+        let max_cpu = cpus.iter().copied().max().unwrap();
+        let mut by_cpu: Vec<usize> =
+            (0..=max_cpu).map(|cpu| cpu % count).collect();
+        for (index, cpu) in cpus.iter().enumerate() {
+            by_cpu[*cpu] = index % count;
+        }
+
+        Ok(ShardedRio { shards, by_cpu })
+    }
+
+    /// The ring of the CPU that the calling thread
+    /// currently runs on.
+    pub fn current(&self) -> &Rio {
+        #[allow(unsafe_code)]
+        let cpu = unsafe { libc::sched_getcpu() };
+        let index = usize::try_from(cpu)
+            .ok()
+            .and_then(|id| self.by_cpu.get(id))
+            .copied()
+            .unwrap_or(0);
+        &self.shards[index]
+    }
+
+    /// All of the rings, in the order of the CPUs
+    /// that their reapers are pinned onto.
+    pub fn shards(&self) -> &[Rio] {
+        &self.shards
+    }
+}
+
+impl std::ops::Deref for ShardedRio {
+    type Target = Uring;
+
+    fn deref(&self) -> &Uring {
+        self.current()
+    }
+}
+
+/// The ids of the CPUs that this process may run
+/// on, in ascending order.
+fn allowed_cpus() -> io::Result<Vec<usize>> {
+    #[allow(unsafe_code)]
+    let mut set: libc::cpu_set_t =
+        unsafe { MaybeUninit::zeroed().assume_init() };
+
+    #[allow(unsafe_code)]
+    let ret = unsafe {
+        libc::sched_getaffinity(
+            0,
+            size_of::<libc::cpu_set_t>(),
+            &mut set,
+        )
+    };
+    if ret < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    let setsize =
+        usize::try_from(libc::CPU_SETSIZE).unwrap();
+    #[allow(unsafe_code)]
+    Ok((0..setsize)
+        .filter(|cpu| unsafe {
+            libc::CPU_ISSET(*cpu, &set)
+        })
+        .collect())
+}
@@ -0,0 +1,119 @@
+use super::*;
+
+/// The capacity that the pipes of `sendfile` ask
+/// for, which is as much as unprivileged processes
+/// may give a pipe by default.
+const PIPE_SIZE: i32 = 1024 * 1024;
+
+/// A pipe that `Uring::sendfile` moves data through
+/// on its way from a file into a socket.
+#[derive(Debug)]
+pub(crate) struct SplicePipe {
+    pub(crate) read: RawFd,
+    pub(crate) write: RawFd,
+    /// How many bytes fit into the pipe at once.
+    pub(crate) capacity: usize,
+}
+
+/// A pipe on loan from the spare pipes of a
+/// `Uring`, which goes back to them when this is
+/// dropped if it was drained, and is closed
+/// otherwise.
+#[derive(Debug)]
+pub(crate) struct PipeLease {
+    pipe: Option<SplicePipe>,
+    spares: Arc<Mutex<Vec<SplicePipe>>>,
+}
+
+impl SplicePipe {
+    fn new() -> io::Result<SplicePipe> {
+        let mut fds = [0; 2];
+        #[allow(unsafe_code)]
+        let ret = unsafe {
+            libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC)
+        };
+        if ret < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let mut pipe = SplicePipe {
+            read: fds[0],
+            write: fds[1],
+            capacity: 0,
+        };
+
+        // a smaller pipe still works, it just takes
+        // more calls to send the same file.
+        #[allow(unsafe_code)]
+        let capacity = unsafe {
+            libc::fcntl(
+                pipe.write,
+                libc::F_SETPIPE_SZ,
+                PIPE_SIZE,
+            );
+            libc::fcntl(pipe.write, libc::F_GETPIPE_SZ)
+        };
+        if capacity < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        pipe.capacity = usize::try_from(capacity).unwrap();
+        Ok(pipe)
+    }
+
+    /// Whether nothing is left in the pipe, so that
+    /// it can be reused without sending stale bytes.
+    fn is_empty(&self) -> bool {
+        let mut queued: libc::c_int = 0;
+        #[allow(unsafe_code)]
+        let ret = unsafe {
+            libc::ioctl(
+                self.read,
+                libc::FIONREAD,
+                &mut queued,
+            )
+        };
+        ret == 0 && queued == 0
+    }
+}
+
+impl Drop for SplicePipe {
+    fn drop(&mut self) {
+        #[allow(unsafe_code)]
+        unsafe {
+            libc::close(self.read);
+            libc::close(self.write);
+        }
+    }
+}
+
+impl PipeLease {
+    /// Takes one of the `spares`, or creates a new
+    /// pipe if there are none.
+    pub(crate) fn take(
+        spares: &Arc<Mutex<Vec<SplicePipe>>>,
+    ) -> io::Result<PipeLease> {
+        let spare = spares.lock().unwrap().pop();
+        let pipe = match spare {
+            Some(pipe) => pipe,
+            None => SplicePipe::new()?,
+        };
+        Ok(PipeLease {
+            pipe: Some(pipe),
+            spares: spares.clone(),
+        })
+    }
+
+    pub(crate) const fn pipe(&self) -> &SplicePipe {
+        self.pipe.as_ref().unwrap()
+    }
+}
+
+impl Drop for PipeLease {
+    fn drop(&mut self) {
+        if let Some(pipe) = self.pipe.take() {
+            if pipe.is_empty() {
+                self.spares.lock().unwrap().push(pipe);
+            }
+        }
+    }
+}
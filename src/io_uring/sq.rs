@@ -16,6 +16,19 @@ pub(crate) struct Sq {
     sqes: &'static mut [io_uring_sqe],
     sqe_head: u32,
     sqe_tail: u32,
+    /// How many of the sqes before `sqe_head` were
+    /// published to the kernel without an
+    /// `io_uring_enter` taking them yet, because the
+    /// last one failed.
+    unentered: u32,
+    /// Identifies this ring in the registrations of
+    /// `Uring::register_ring_fd`, which may outlive
+    /// it on threads that did not drop it, and whose
+    /// fd a later ring may then reuse.
+    ring_id: Arc<()>,
+    /// Tickets that the running `Uring::batch_n`
+    /// reserved for the operations it queues.
+    pub(crate) reserved: Vec<usize>,
     ring_ptr: *const libc::c_void,
     ring_mmap_sz: usize,
     sqes_mmap_sz: usize,
@@ -70,6 +83,9 @@ impl Sq {
             Sq {
                 sqe_head: 0,
                 sqe_tail: 0,
+                unentered: 0,
+                ring_id: Arc::new(()),
+                reserved: Vec::new(),
                 ring_ptr: sq_ring_ptr,
                 ring_mmap_sz: sq_ring_mmap_sz,
                 sqes_mmap_sz,
@@ -102,20 +118,39 @@ impl Sq {
         })
     }
 
+    pub(crate) const fn ring_id(&self) -> &Arc<()> {
+        &self.ring_id
+    }
+
+    fn head(&self, ring_flags: u32) -> u32 {
+        if (ring_flags & IORING_SETUP_SQPOLL) == 0 {
+            // non-polling mode, where the kernel has
+            // yet to read the sqes that no enter took.
+            self.sqe_head - self.unentered
+        } else {
+            // polling mode
+            unsafe { &*self.khead }.load(Acquire)
+        }
+    }
+
+    /// Whether `count` more sqes can be queued
+    /// before the queue must be submitted.
+    pub(crate) fn has_room(
+        &self,
+        count: u32,
+        ring_flags: u32,
+    ) -> bool {
+        self.sqe_tail + count - self.head(ring_flags)
+            <= u32::try_from(self.sqes.len()).unwrap()
+    }
+
     pub(crate) fn try_get_sqe(
         &mut self,
         ring_flags: u32,
     ) -> Option<&mut io_uring_sqe> {
         let next = self.sqe_tail + 1;
 
-        let head =
-            if (ring_flags & IORING_SETUP_SQPOLL) == 0 {
-                // non-polling mode
-                self.sqe_head
-            } else {
-                // polling mode
-                unsafe { &*self.khead }.load(Acquire)
-            };
+        let head = self.head(ring_flags);
 
         if next - head <= self.sqes.len() as u32 {
             let idx =
@@ -129,6 +164,73 @@ impl Sq {
         }
     }
 
+    /// The position of the sqe with `user_data`, if
+    /// it is still queued.
+    fn queued_position(
+        &self,
+        user_data: u64,
+    ) -> Option<u32> {
+        let mask = unsafe { *self.kring_mask };
+
+        let mut position = self.sqe_head;
+        while position != self.sqe_tail {
+            if self.sqes[(position & mask) as usize]
+                .user_data
+                == user_data
+            {
+                return Some(position);
+            }
+            position += 1;
+        }
+        None
+    }
+
+    /// The sqe with `user_data`, unless it was already
+    /// flushed to the kernel.
+    pub(crate) fn queued_sqe(
+        &mut self,
+        user_data: u64,
+    ) -> Option<&mut io_uring_sqe> {
+        let mask = unsafe { *self.kring_mask };
+        let position = self.queued_position(user_data)?;
+        Some(&mut self.sqes[(position & mask) as usize])
+    }
+
+    /// Links the queued sqe with `user_data` to a
+    /// fresh sqe right behind it, and returns that
+    /// one, along with the link flags that the
+    /// target had, which the fresh sqe must carry on
+    /// to whatever is queued next. Returns `None`
+    /// if the target was already flushed to the
+    /// kernel, if other sqes were queued after it,
+    /// which must not be reordered, as earlier sqes
+    /// may be linked to them, or if there is no room
+    /// for another sqe.
+    pub(crate) fn try_get_linked_sqe(
+        &mut self,
+        user_data: u64,
+        ring_flags: u32,
+    ) -> Option<(&mut io_uring_sqe, u8)> {
+        let mask = unsafe { *self.kring_mask };
+        let position = self.queued_position(user_data)?;
+        if position != self.sqe_tail - 1 {
+            return None;
+        }
+        self.try_get_sqe(ring_flags)?;
+
+        let target =
+            &mut self.sqes[(position & mask) as usize];
+        let chained = target.flags
+            & (IOSQE_IO_LINK | IOSQE_IO_HARDLINK);
+        target.flags |= IOSQE_IO_LINK;
+
+        Some((
+            &mut self.sqes
+                [((self.sqe_tail - 1) & mask) as usize],
+            chained,
+        ))
+    }
+
     // sets sq.array to point to current sq.sqe_head
     fn flush(&mut self) -> u32 {
         let mask: u32 = unsafe { *self.kring_mask };
@@ -153,11 +255,41 @@ impl Sq {
         to_submit
     }
 
+    /// Whether the kernel signals with
+    /// `IORING_SQ_NEED_WAKEUP` that the SQPOLL
+    /// thread that checks the submission queue has
+    /// gone to sleep due to inactivity, and needs to
+    /// be woken up with `IORING_ENTER_SQ_WAKEUP` to
+    /// see new entries.
+    pub(crate) fn needs_wakeup(&self) -> bool {
+        // the thread sets the flag before checking the
+        // tail one last time, so the tail published by
+        // `flush` must be visible before the flag is
+        // read, or both sides may miss each other and
+        // leave the entries stalled.
+        fence(SeqCst);
+        unsafe { &*self.kflags }.load(Relaxed)
+            & IORING_SQ_NEED_WAKEUP
+            != 0
+    }
+
     pub(crate) fn submit_all(
         &mut self,
         ring_flags: u32,
         ring_fd: i32,
-    ) -> u64 {
+    ) -> io::Result<u64> {
+        self.submit_and_wait(ring_flags, ring_fd, 0)
+    }
+
+    /// Submits everything queued, and blocks until
+    /// the kernel posted `wait` completions, in the
+    /// same `io_uring_enter`.
+    pub(crate) fn submit_and_wait(
+        &mut self,
+        ring_flags: u32,
+        ring_fd: i32,
+        wait: u32,
+    ) -> io::Result<u64> {
         let submitted = if ring_flags & IORING_SETUP_SQPOLL
             == 0
         {
@@ -166,54 +298,81 @@ impl Sq {
 
             // TODO for polling, keep flags at 0
 
-            let flags = IORING_ENTER_GETEVENTS;
-            let flushed = self.flush();
+            let (enter_fd, registered) =
+                enter_target(ring_fd, &self.ring_id);
+            let flags = IORING_ENTER_GETEVENTS | registered;
+            // sqes that a failed enter left in the ring
+            // are taken first, along with the new ones.
+            let flushed = self.flush() + self.unentered;
             let mut to_submit = flushed;
-            while to_submit > 0 {
+            while to_submit > 0 || wait > 0 {
+                // the kernel only waits in an enter that
+                // takes the rest of the batch, which ends
+                // the loop, so `wait` is waited for once.
                 let _ = Measure::new(&M.enter_sqe);
                 let ret = enter(
-                    ring_fd,
+                    enter_fd,
                     to_submit,
-                    0,
+                    wait,
                     flags,
                     std::ptr::null_mut(),
-                )
-                .expect(
-                    "Failed to submit items to kernel via \
-                     io_uring. This should never fail.",
                 );
-                to_submit -= u32::try_from(ret).unwrap();
+                let entered = match ret {
+                    Ok(entered) => {
+                        u32::try_from(entered).unwrap()
+                    }
+                    Err(e) => {
+                        self.unentered = to_submit;
+                        return Err(e);
+                    }
+                };
+                if entered == to_submit {
+                    break;
+                }
+                if entered == 0 {
+                    // retrying would spin, so leave the
+                    // rest to a later submission.
+                    self.unentered = to_submit;
+                    return Err(
+                        io::Error::from_raw_os_error(
+                            libc::EAGAIN,
+                        ),
+                    );
+                }
+                to_submit -= entered;
             }
+            self.unentered = 0;
             flushed
-        } else if unsafe { &*self.kflags }.load(Acquire)
-            & IORING_SQ_NEED_WAKEUP
-            != 0
-        {
-            // the kernel has signalled to us that the
-            // SQPOLL thread that checks the submission
-            // queue has terminated due to inactivity,
-            // and needs to be restarted.
-            let to_submit = self.sqe_tail - self.sqe_head;
-            let _ = Measure::new(&M.enter_sqe);
-            enter(
-                ring_fd,
-                to_submit,
-                0,
-                IORING_ENTER_SQ_WAKEUP,
-                std::ptr::null_mut(),
-            )
-            .expect(
-                "Failed to wake up SQPOLL io_uring \
-                 kernel thread. This should never fail.",
-            );
-            0
         } else {
-            0
+            // SQPOLL mode, the kernel thread picks up
+            // our SQEs once they are published in the
+            // ring's tail.
+            let flushed = self.flush();
+            let mut flags = 0;
+            if self.needs_wakeup() {
+                flags |= IORING_ENTER_SQ_WAKEUP;
+            }
+            if wait > 0 {
+                flags |= IORING_ENTER_GETEVENTS;
+            }
+            if flags != 0 {
+                let _ = Measure::new(&M.enter_sqe);
+                let (enter_fd, registered) =
+                    enter_target(ring_fd, &self.ring_id);
+                enter(
+                    enter_fd,
+                    flushed,
+                    wait,
+                    flags | registered,
+                    std::ptr::null_mut(),
+                )?;
+            }
+            flushed
         };
         assert_eq!(
             unsafe { &*self.kdropped }.load(Relaxed),
             0
         );
-        u64::from(submitted)
+        Ok(u64::from(submitted))
     }
 }
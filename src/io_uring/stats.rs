@@ -0,0 +1,25 @@
+/// Counts of the operations that went through a
+/// ring, as returned by `Uring::stats`.
+///
+/// Unlike the profile that `Uring::profile`
+/// returns, these are always kept, whatever the
+/// `no_metrics` feature says.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RioStats {
+    /// How many submission queue entries were
+    /// handed to the kernel.
+    pub submitted: u64,
+    /// How many operations received their final
+    /// completion. A multishot operation counts
+    /// once, when it stops.
+    pub completed: u64,
+    /// How many operations are queued or submitted
+    /// but not yet completed. Once this reaches the
+    /// size of the completion queue, further
+    /// operations wait for earlier ones to complete.
+    pub in_flight: u64,
+    /// How many times an operation found the
+    /// submission queue full, and had to submit it
+    /// to the kernel to make room.
+    pub submission_queue_full_events: u64,
+}
@@ -4,7 +4,10 @@ use std::{convert::TryFrom, io};
 
 use libc::{c_int, c_long, c_uint, syscall};
 
-use super::io_uring_params;
+use super::{
+    __kernel_timespec, io_uring_getevents_arg,
+    io_uring_params, IORING_ENTER_EXT_ARG,
+};
 
 const SETUP: c_long = 425;
 const ENTER: c_long = 426;
@@ -14,10 +17,7 @@ pub(crate) fn setup(
     entries: c_uint,
     p: *mut io_uring_params,
 ) -> io::Result<c_int> {
-    assert!(
-        (1..=4096).contains(&entries),
-        "entries must be between 1 and 4096 (inclusive)"
-    );
+    assert!(entries >= 1, "entries must be at least 1");
     assert_eq!(
         entries.count_ones(),
         1,
@@ -78,6 +78,47 @@ pub(crate) fn enter(
     }
 }
 
+/// Like `enter` without submitting, but gives up
+/// waiting for `min_complete` completions once
+/// `timeout` has passed, which is not an error.
+pub(crate) fn enter_timeout(
+    fd: c_int,
+    min_complete: c_uint,
+    flags: c_uint,
+    timeout: &__kernel_timespec,
+) -> io::Result<c_int> {
+    let ts: *const __kernel_timespec = timeout;
+    let arg = io_uring_getevents_arg {
+        ts: ts as u64,
+        ..io_uring_getevents_arg::default()
+    };
+    let arg_ptr: *const io_uring_getevents_arg = &arg;
+    let arg_size = size_of::<io_uring_getevents_arg>();
+    loop {
+        #[allow(unsafe_code)]
+        let ret = unsafe {
+            syscall(
+                ENTER,
+                i64::from(fd),
+                0,
+                i64::from(min_complete),
+                i64::from(flags | IORING_ENTER_EXT_ARG),
+                arg_ptr as c_long,
+                c_long::try_from(arg_size).unwrap(),
+            )
+        };
+        if ret >= 0 {
+            return Ok(i32::try_from(ret).unwrap());
+        }
+        let err = io::Error::last_os_error();
+        match err.raw_os_error() {
+            Some(libc::EINTR) => {}
+            Some(libc::ETIME) => return Ok(0),
+            _ => return Err(err),
+        }
+    }
+}
+
 pub(crate) fn register(
     fd: c_int,
     opcode: c_uint,
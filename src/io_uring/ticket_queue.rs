@@ -1,3 +1,5 @@
+use std::collections::VecDeque;
+
 use super::*;
 
 /// Exerts backpressure on submission threads
@@ -7,16 +9,29 @@ use super::*;
 /// and just drop the overflowing completions.
 #[derive(Debug)]
 pub(crate) struct TicketQueue {
-    tickets: Mutex<Vec<usize>>,
+    tickets: Mutex<Tickets>,
     cv: Condvar,
+    size: usize,
+}
+
+#[derive(Debug)]
+struct Tickets {
+    free: Vec<usize>,
+    /// Tasks waiting in `poll_available`, in the
+    /// order they started waiting.
+    wakers: VecDeque<Waker>,
 }
 
 impl TicketQueue {
     pub(crate) fn new(size: usize) -> TicketQueue {
-        let tickets = Mutex::new((0..size).collect());
+        let tickets = Mutex::new(Tickets {
+            free: (0..size).collect(),
+            wakers: VecDeque::new(),
+        });
         TicketQueue {
             tickets,
             cv: Condvar::new(),
+            size,
         }
     }
 
@@ -26,16 +41,57 @@ impl TicketQueue {
     ) {
         let _ = Measure::new(&M.ticket_queue_push);
         let mut tickets = self.tickets.lock().unwrap();
-        tickets.append(&mut new_tickets);
-        self.cv.notify_one();
+        tickets.free.append(&mut new_tickets);
+        self.cv.notify_all();
+        for waker in tickets.wakers.drain(..) {
+            waker.wake();
+        }
     }
 
     pub(crate) fn pop(&self) -> usize {
         let _ = Measure::new(&M.ticket_queue_pop);
         let mut tickets = self.tickets.lock().unwrap();
-        while tickets.is_empty() {
+        while tickets.free.is_empty() {
             tickets = self.cv.wait(tickets).unwrap();
         }
-        tickets.pop().unwrap()
+        tickets.free.pop().unwrap()
+    }
+
+    /// Takes a ticket if one is free, without
+    /// waiting for one.
+    pub(crate) fn try_pop(&self) -> Option<usize> {
+        let _ = Measure::new(&M.ticket_queue_pop);
+        self.tickets.lock().unwrap().free.pop()
+    }
+
+    /// Takes `count` tickets at once, or all of them
+    /// if there are fewer than `count` in total,
+    /// waiting until that many are free.
+    pub(crate) fn pop_n(&self, count: usize) -> Vec<usize> {
+        let _ = Measure::new(&M.ticket_queue_pop);
+        let wanted = count.min(self.size);
+        let mut tickets = self.tickets.lock().unwrap();
+        while tickets.free.len() < wanted {
+            tickets = self.cv.wait(tickets).unwrap();
+        }
+        let start = tickets.free.len() - wanted;
+        tickets.free.split_off(start)
+    }
+
+    /// Ready once at least `count` tickets are free,
+    /// or all of them if there are fewer than
+    /// `count` in total, and otherwise registers the
+    /// task to be woken when tickets come back.
+    pub(crate) fn poll_available(
+        &self,
+        count: usize,
+        cx: &mut Context<'_>,
+    ) -> Poll<()> {
+        let mut tickets = self.tickets.lock().unwrap();
+        if tickets.free.len() >= count.min(self.size) {
+            return Poll::Ready(());
+        }
+        tickets.wakers.push_back(cx.waker().clone());
+        Poll::Pending
     }
 }
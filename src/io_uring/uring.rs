@@ -12,17 +12,199 @@ impl std::ops::Deref for Rio {
     }
 }
 
+impl Rio {
+    /// Starts a `Rio` with the given `Config`, the
+    /// same as `config.start()`, for when a custom
+    /// configuration is the only difference to
+    /// `rio::new`.
+    pub fn with_config(config: Config) -> io::Result<Rio> {
+        config.start()
+    }
+
+    /// Registers a ring of up to `entries` buffers
+    /// for the buffer group `group_id`, which
+    /// operations that select a buffer from that
+    /// group take their buffers from. `entries` must
+    /// be a power of two no larger than 32768.
+    ///
+    /// # Warning
+    ///
+    /// This only becomes usable on linux kernels
+    /// 5.19 and up. Older kernels fail it with an
+    /// error saying so.
+    pub fn register_buf_ring(
+        &self,
+        group_id: u16,
+        entries: u16,
+    ) -> io::Result<BufRing> {
+        BufRing::register(self, group_id, entries)
+    }
+
+    /// Reads into `buf` from the file-like object at
+    /// the given offset, like `read_at`, but takes the
+    /// buffer by value and hands it back along with
+    /// the number of bytes read, so that nothing has
+    /// to outlive the future but the file and the
+    /// ring, which a task can own a clone of.
+    ///
+    /// Dropping the future before it is done blocks
+    /// until the kernel is done with the buffer, like
+    /// dropping a `Completion`, and the buffer is
+    /// dropped after that.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// let ring = rio::new().unwrap();
+    /// let file = std::fs::File::open("file").unwrap();
+    ///
+    /// // the `File` only has to outlive the future,
+    /// // while the buffer moves into it and back out.
+    /// let (res, buf) =
+    ///     extreme::run(ring.read(&file, vec![0; 4096], 0));
+    /// let len = res.unwrap();
+    /// println!("read {:?}", &buf[..len]);
+    /// ```
+    pub async fn read<F>(
+        &self,
+        file: &F,
+        mut buf: Vec<u8>,
+        at: u64,
+    ) -> (io::Result<usize>, Vec<u8>)
+    where
+        F: AsRingFd,
+    {
+        let res = {
+            let slice: &mut [u8] = &mut buf;
+            self.read_at(file, &slice, at).await
+        };
+        (res, buf)
+    }
+
+    /// Writes `buf` to the file-like object at the
+    /// given offset, like `write_at`, but takes the
+    /// buffer by value and hands it back along with
+    /// the number of bytes written, like `read`. Be
+    /// sure to check that count, as the write may be
+    /// short.
+    pub async fn write<F>(
+        &self,
+        file: &F,
+        buf: Vec<u8>,
+        at: u64,
+    ) -> (io::Result<usize>, Vec<u8>)
+    where
+        F: AsRingFd,
+    {
+        let res = {
+            let slice: &[u8] = &buf;
+            self.write_at(file, &slice, at).await
+        };
+        (res, buf)
+    }
+
+    /// Like `read`, but shares ownership of the file
+    /// through an `Arc`, and of the ring through a
+    /// clone of this `Rio`, so that the future
+    /// borrows nothing and can be handed to a task
+    /// that outlives the caller. Both are held until
+    /// the read is done.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::{fs::File, sync::Arc};
+    ///
+    /// let ring = rio::new().unwrap();
+    /// let file = Arc::new(File::open("file").unwrap());
+    ///
+    /// // neither `ring` nor `file` need to outlive
+    /// // the future, which only holds clones.
+    /// let read =
+    ///     ring.read_owned(file.clone(), vec![0; 4096], 0);
+    /// drop(file);
+    /// let (res, buf) = extreme::run(read);
+    /// let len = res.unwrap();
+    /// println!("read {:?}", &buf[..len]);
+    /// ```
+    pub fn read_owned<F>(
+        &self,
+        file: Arc<F>,
+        buf: Vec<u8>,
+        at: u64,
+    ) -> impl std::future::Future<
+        Output = (io::Result<usize>, Vec<u8>),
+    > + 'static
+    where
+        F: AsRingFd + 'static,
+    {
+        let ring = self.clone();
+        async move { ring.read(&*file, buf, at).await }
+    }
+
+    /// Like `write`, but shares ownership of the file
+    /// and the ring, like `read_owned` does.
+    pub fn write_owned<F>(
+        &self,
+        file: Arc<F>,
+        buf: Vec<u8>,
+        at: u64,
+    ) -> impl std::future::Future<
+        Output = (io::Result<usize>, Vec<u8>),
+    > + 'static
+    where
+        F: AsRingFd + 'static,
+    {
+        let ring = self.clone();
+        async move { ring.write(&*file, buf, at).await }
+    }
+}
+
 /// The top-level `io_uring` structure.
+///
+/// Dropping it blocks until every operation that
+/// was submitted through it has completed. On linux
+/// kernels 5.19 and up, operations that are still
+/// pending at that point, such as a read from a
+/// socket that never receives anything, are
+/// cancelled first, so that this doesn't block
+/// forever and the kernel never writes into a
+/// buffer after the ring is gone. The reaper thread
+/// then exits, and is joined before the ring is
+/// closed.
 #[derive(Debug)]
 pub struct Uring {
     sq: Mutex<Sq>,
     ticket_queue: Arc<TicketQueue>,
     in_flight: Arc<InFlight>,
+    inbox: Arc<Inbox>,
     flags: u32,
     ring_fd: i32,
+    /// Identifies this ring in the registrations of
+    /// `register_ring_fd`, see `Sq::ring_id`.
+    ring_id: Arc<()>,
+    /// The queue sizes that the kernel picked, as
+    /// `(sq_entries, cq_entries)`.
+    entries: (u32, u32),
     config: Config,
     loaded: AtomicU64,
     submitted: AtomicU64,
+    sq_full: AtomicU64,
+    probe: Probe,
+    /// The `(major, minor)` version of the running
+    /// kernel, for operations that only gained some
+    /// features later than their opcode.
+    kernel: (u32, u32),
+    files: Arc<Mutex<Vec<RawFd>>>,
+    buffers: Mutex<Vec<(u64, usize)>>,
+    /// Drained pipes of earlier `sendfile` calls.
+    pipes: Arc<Mutex<Vec<SplicePipe>>>,
+    /// The files written to with `flush_on_drop`
+    /// set, as the fd or registered slot that was
+    /// written to, whether it is a slot, and a
+    /// duplicate of the file's descriptor.
+    written: Mutex<Vec<(RawFd, bool, RawFd)>>,
+    reaper: Option<JoinHandle<()>>,
 }
 
 #[allow(unsafe_code)]
@@ -33,6 +215,28 @@ unsafe impl Sync for Uring {}
 
 impl Drop for Uring {
     fn drop(&mut self) {
+        if self.config.flush_on_drop {
+            self.flush_written();
+        }
+
+        // cancel anything that is still pending. This
+        // fails with EINVAL on kernels without
+        // IORING_ASYNC_CANCEL_ANY, where the drained
+        // NOP below still waits for it to complete.
+        let cancel_res =
+            self.with_sqe::<_, ()>(None, false, |sqe| {
+                sqe.prep_rw(
+                    IORING_OP_ASYNC_CANCEL,
+                    -1,
+                    0,
+                    0,
+                    Ordering::None,
+                );
+                sqe.__bindgen_anon_1.cancel_flags =
+                    IORING_ASYNC_CANCEL_ANY;
+            });
+        drop(cancel_res);
+
         let poison_pill_res =
             self.with_sqe::<_, ()>(None, false, |sqe| {
                 sqe.prep_rw(
@@ -49,6 +253,22 @@ impl Drop for Uring {
         // this waits for the NOP event to complete.
         drop(poison_pill_res);
 
+        // the reaper returns right after filling the
+        // poison pill, so this only waits for it to
+        // unmap the completion queue.
+        if let Some(reaper) = self.reaper.take() {
+            let _ = reaper.join();
+        }
+
+        // the kernel would otherwise keep the ring
+        // alive for as long as this thread lives.
+        let _ = self.unregister_ring_fd();
+
+        #[allow(unsafe_code)]
+        unsafe {
+            libc::close(self.ring_fd);
+        }
+
         if self.config.print_profile_on_drop {
             #[cfg(not(feature = "no_metrics"))]
             M.print_profile();
@@ -56,24 +276,49 @@ impl Drop for Uring {
     }
 }
 
+/// The descriptor of the ring itself, which
+/// `Config::attach_wq_fd` takes to share its async
+/// backend with another ring.
+impl AsRawFd for Uring {
+    fn as_raw_fd(&self) -> RawFd {
+        self.ring_fd
+    }
+}
+
 impl Uring {
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn new(
         config: Config,
-        flags: u32,
+        params: &io_uring_params,
         ring_fd: i32,
         sq: Sq,
         in_flight: Arc<InFlight>,
         ticket_queue: Arc<TicketQueue>,
+        inbox: Arc<Inbox>,
+        reaper: JoinHandle<()>,
+        probe: Probe,
+        kernel: (u32, u32),
     ) -> Uring {
         Uring {
-            flags,
+            flags: params.flags,
             ring_fd,
+            ring_id: sq.ring_id().clone(),
+            entries: (params.sq_entries, params.cq_entries),
             sq: Mutex::new(sq),
             config,
             in_flight,
+            inbox,
             ticket_queue,
             loaded: 0.into(),
             submitted: 0.into(),
+            sq_full: 0.into(),
+            probe,
+            kernel,
+            files: Arc::default(),
+            buffers: Mutex::new(vec![]),
+            pipes: Arc::default(),
+            written: Mutex::new(vec![]),
+            reaper: Some(reaper),
         }
     }
 
@@ -85,13 +330,11 @@ impl Uring {
         if current >= sqe_id {
             return Ok(());
         }
-        let mut sq = {
-            let _get_sq_mu = Measure::new(&M.sq_mu_wait);
-            self.sq.lock().unwrap()
-        };
+        let mut sq = self.lock_sq();
         let _hold_sq_mu = Measure::new(&M.sq_mu_hold);
-        let submitted =
-            sq.submit_all(self.flags, self.ring_fd);
+        let submitted = sq
+            .submit_all(self.flags, self.ring_fd)
+            .map_err(RioError::submission)?;
         let old =
             self.submitted.fetch_add(submitted, Release);
 
@@ -113,7 +356,10 @@ impl Uring {
     }
 
     /// Asynchronously accepts a `TcpStream` from
-    /// a provided `TcpListener`.
+    /// a provided `TcpListener`, along with the
+    /// address of the peer that connected. Like the
+    /// `accept` of `std`, this sets `SOCK_CLOEXEC` on
+    /// the accepted socket.
     ///
     /// # Warning
     ///
@@ -122,631 +368,5681 @@ impl Uring {
     pub fn accept<'a>(
         &'a self,
         tcp_listener: &'a TcpListener,
-    ) -> Completion<'a, TcpStream> {
+    ) -> Completion<'a, (TcpStream, SocketAddr)> {
+        self.accept_with_flags(
+            tcp_listener,
+            libc::SOCK_CLOEXEC,
+        )
+    }
+
+    /// Asynchronously accepts a `TcpStream` from
+    /// a provided `TcpListener`, along with the
+    /// address of the peer that connected, passing
+    /// `flags` along as the flags of `accept4(2)`,
+    /// which are `SOCK_NONBLOCK` and `SOCK_CLOEXEC`.
+    ///
+    /// # Warning
+    ///
+    /// This only becomes usable on linux kernels
+    /// 5.5 and up.
+    pub fn accept_with_flags<'a>(
+        &'a self,
+        tcp_listener: &'a TcpListener,
+        flags: i32,
+    ) -> Completion<'a, (TcpStream, SocketAddr)> {
+        #[allow(clippy::cast_sign_loss)]
+        let accept_flags = flags as u32;
+        #[allow(unsafe_code)]
+        let storage: libc::sockaddr_storage =
+            unsafe { MaybeUninit::zeroed().assume_init() };
+        let len = libc::socklen_t::try_from(size_of::<
+            libc::sockaddr_storage,
+        >())
+        .unwrap();
+
+        self.with_pinned_sqe(
+            None,
+            false,
+            Pinned::SockAddr(Box::new((storage, len))),
+            |cqe, pinned| {
+                #[allow(unsafe_code)]
+                let stream =
+                    unsafe { TcpStream::from_raw_fd(cqe.res) };
+                if let Pinned::SockAddr(buf) = pinned {
+                    (stream, raw2addr(&buf.0))
+                } else {
+                    unreachable!()
+                }
+            },
+            |sqe, pinned| {
+                sqe.prep_rw(
+                    IORING_OP_ACCEPT,
+                    tcp_listener.as_raw_fd(),
+                    0,
+                    0,
+                    Ordering::None,
+                );
+                sqe.__bindgen_anon_1.accept_flags =
+                    accept_flags;
+                if let Pinned::SockAddr(buf) = pinned {
+                    let storage_ptr: *mut libc::sockaddr_storage =
+                        &mut buf.0;
+                    let len_ptr: *mut libc::socklen_t =
+                        &mut buf.1;
+                    sqe.addr = storage_ptr as u64;
+                    sqe.off = len_ptr as u64;
+                }
+            },
+        )
+    }
+
+    /// Accepts a connection from a listening socket,
+    /// returning only its raw fd, which spares
+    /// decoding the address of the peer. `flags` are
+    /// the flags of `accept4(2)`, which are
+    /// `SOCK_NONBLOCK` and `SOCK_CLOEXEC`.
+    ///
+    /// # Warning
+    ///
+    /// This only becomes usable on linux kernels
+    /// 5.5 and up.
+    pub fn accept_fd<'a, F>(
+        &'a self,
+        listener: &'a F,
+        flags: i32,
+    ) -> Completion<'a, RawFd>
+    where
+        F: AsRawFd,
+    {
+        #[allow(clippy::cast_sign_loss)]
+        let accept_flags = flags as u32;
+
         self.with_sqe(None, false, |sqe| {
             sqe.prep_rw(
                 IORING_OP_ACCEPT,
-                tcp_listener.as_raw_fd(),
+                listener.as_raw_fd(),
                 0,
                 0,
                 Ordering::None,
-            )
+            );
+            sqe.__bindgen_anon_1.accept_flags =
+                accept_flags;
         })
     }
 
-    /// Asynchronously connects a `TcpStream` from
-    /// a provided `SocketAddr`.
+    /// Accepts a connection from a listening socket,
+    /// installing it straight into `slot` of the
+    /// registered file table instead of giving it an
+    /// fd, and returns the index of the slot, to pass
+    /// as a `FixedFile` to later operations.
+    ///
+    /// The socket only lives in the table, so once
+    /// the `Completion` is waited on, or dropped,
+    /// after the accept succeeded, `registered_files`
+    /// shows its slot as `rio::DIRECT_DESCRIPTOR`,
+    /// and it is closed with `close_direct`. With
+    /// `FileSlot::Alloc`, the kernel picks a free
+    /// slot, such as one left empty by
+    /// `register_files_sparse`, and fails with
+    /// `ENFILE` if every slot is occupied.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `FileSlot::Index` is past the end
+    /// of the registered file table.
     ///
     /// # Warning
     ///
     /// This only becomes usable on linux kernels
-    /// 5.5 and up.
-    pub fn connect<'a, F>(
+    /// 5.15 and up, and `FileSlot::Alloc` on 5.19
+    /// and up.
+    pub fn accept_direct<'a, F>(
         &'a self,
-        socket: &'a F,
-        address: &std::net::SocketAddr,
-        order: Ordering,
-    ) -> Completion<'a, ()>
+        listener: &'a F,
+        slot: FileSlot,
+    ) -> Completion<'a, u32>
     where
-        F: AsRawFd,
+        F: AsRingFd,
     {
-        let (addr, len) = addr2raw(address);
-        self.with_sqe(None, false, |sqe| {
-            sqe.prep_rw(
-                IORING_OP_CONNECT,
-                socket.as_raw_fd(),
-                0,
-                len as u64,
-                order,
-            );
+        let (file_index, given) = match slot {
+            FileSlot::Index(index) => {
+                let files = self.files.lock().unwrap();
+                assert!(
+                    usize::try_from(index).unwrap()
+                        < files.len(),
+                    "accept_direct slot is past the end \
+                     of the registered file table",
+                );
+                (index + 1, Some(index))
+            }
+            FileSlot::Alloc => {
+                (IORING_FILE_INDEX_ALLOC, None)
+            }
+        };
 
-            sqe.addr = addr as u64;
-        })
+        self.with_pinned_sqe(
+            None,
+            false,
+            Pinned::FileTable(self.files.clone(), given),
+            |cqe, pinned| {
+                if let Pinned::FileTable(table, index) =
+                    pinned
+                {
+                    // the kernel only reports the slot it
+                    // picked, and 0 for a given one.
+                    let installed =
+                        index.unwrap_or_else(|| {
+                            u32::try_from(cqe.res).unwrap()
+                        });
+                    // whatever fd was registered there is
+                    // replaced by the accepted socket.
+                    set_registered_file(
+                        table,
+                        installed,
+                        DIRECT_DESCRIPTOR,
+                    );
+                    installed
+                } else {
+                    unreachable!()
+                }
+            },
+            |sqe, _| {
+                sqe.prep_rw_file(
+                    IORING_OP_ACCEPT,
+                    listener,
+                    0,
+                    0,
+                    Ordering::None,
+                );
+                #[allow(clippy::cast_possible_wrap)]
+                let raw_index = file_index as i32;
+                sqe.__bindgen_anon_2.splice_fd_in =
+                    raw_index;
+            },
+        )
     }
 
-    /// Send a buffer to the target socket
-    /// or file-like destination.
+    /// Accepts connections from a listening socket
+    /// with a single multishot submission, yielding
+    /// the raw fd of each accepted connection.
     ///
-    /// Returns the length that was successfully
-    /// written.
+    /// The kernel may stop the multishot, for
+    /// instance when it runs out of file
+    /// descriptors, in which case the stream ends
+    /// after yielding the error that stopped it.
     ///
     /// # Warning
     ///
     /// This only becomes usable on linux kernels
-    /// 5.6 and up.
-    pub fn send<'a, F, B>(
+    /// 5.19 and up.
+    pub fn accept_multi<'a, F>(
         &'a self,
-        stream: &'a F,
-        iov: &'a B,
-    ) -> Completion<'a, usize>
+        listener: &'a F,
+    ) -> CompletionStream<'a, RawFd>
     where
         F: AsRawFd,
-        B: 'a + AsIoVec,
     {
-        self.send_ordered(stream, iov, Ordering::None)
+        self.with_stream_sqe(|sqe| {
+            sqe.prep_rw(
+                IORING_OP_ACCEPT,
+                listener.as_raw_fd(),
+                0,
+                0,
+                Ordering::None,
+            );
+            sqe.ioprio |= IORING_ACCEPT_MULTISHOT;
+        })
     }
 
-    /// Send a buffer to the target socket
-    /// or file-like destination.
+    /// Creates a socket, as with `socket(2)`,
+    /// returning its raw fd. `flags` is currently
+    /// unused by the kernel and should be 0, pass
+    /// flags like `libc::SOCK_CLOEXEC` in
+    /// `socket_type` instead.
     ///
-    /// Returns the length that was successfully
-    /// written.
+    /// # Warning
+    ///
+    /// This only becomes usable on linux kernels
+    /// 5.19 and up.
+    pub fn socket<'a>(
+        &'a self,
+        domain: i32,
+        socket_type: i32,
+        protocol: i32,
+        flags: u32,
+    ) -> Completion<'a, RawFd> {
+        self.socket_ordered(
+            domain,
+            socket_type,
+            protocol,
+            flags,
+            Ordering::None,
+        )
+    }
+
+    /// Creates a socket, as with `socket(2)`,
+    /// returning its raw fd. `flags` is currently
+    /// unused by the kernel and should be 0, pass
+    /// flags like `libc::SOCK_CLOEXEC` in
+    /// `socket_type` instead.
     ///
     /// Accepts an `Ordering` specification.
     ///
     /// # Warning
     ///
     /// This only becomes usable on linux kernels
-    /// 5.6 and up.
-    pub fn send_ordered<'a, F, B>(
+    /// 5.19 and up.
+    pub fn socket_ordered<'a>(
         &'a self,
-        stream: &'a F,
-        iov: &'a B,
+        domain: i32,
+        socket_type: i32,
+        protocol: i32,
+        flags: u32,
         ordering: Ordering,
-    ) -> Completion<'a, usize>
-    where
-        F: AsRawFd,
-        B: 'a + AsIoVec,
-    {
-        let iov = iov.into_new_iovec();
+    ) -> Completion<'a, RawFd> {
+        // the kernel rejects negative values.
+        #[allow(clippy::cast_sign_loss)]
+        let (raw_type, raw_protocol) =
+            (socket_type as u32, protocol as u32);
 
-        self.with_sqe(None, true, |sqe| {
+        self.with_sqe(None, false, |sqe| {
             sqe.prep_rw(
-                IORING_OP_SEND,
-                stream.as_raw_fd(),
-                0,
-                0,
+                IORING_OP_SOCKET,
+                domain,
+                usize::try_from(raw_protocol).unwrap(),
+                u64::from(raw_type),
                 ordering,
             );
-            sqe.addr = iov.iov_base as u64;
-            sqe.len = u32::try_from(iov.iov_len).unwrap();
+            #[allow(clippy::cast_possible_wrap)]
+            let rw_flags = flags as i32;
+            sqe.__bindgen_anon_1.rw_flags = rw_flags;
         })
     }
 
-    /// Receive data from the target socket
-    /// or file-like destination, and place
-    /// it in the given buffer.
-    ///
-    /// Returns the length that was successfully
-    /// read.
+    /// Asynchronously connects a socket, such as one
+    /// created by `socket`, to the given address.
     ///
     /// # Warning
     ///
     /// This only becomes usable on linux kernels
-    /// 5.6 and up.
-    pub fn recv<'a, F, B>(
+    /// 5.5 and up.
+    pub fn connect<'a, F>(
         &'a self,
-        stream: &'a F,
-        iov: &'a B,
-    ) -> Completion<'a, usize>
+        socket: &'a F,
+        address: SocketAddr,
+    ) -> Completion<'a, ()>
     where
         F: AsRawFd,
-        B: AsIoVec + AsIoVecMut,
     {
-        self.recv_ordered(stream, iov, Ordering::None)
+        self.connect_ordered(
+            socket,
+            address,
+            Ordering::None,
+        )
     }
 
-    /// Receive data from the target socket
-    /// or file-like destination, and place
-    /// it in the given buffer.
-    ///
-    /// Returns the length that was successfully
-    /// read.
+    /// Asynchronously connects a socket to the given
+    /// address.
     ///
     /// Accepts an `Ordering` specification.
     ///
     /// # Warning
     ///
     /// This only becomes usable on linux kernels
-    /// 5.6 and up.
-    pub fn recv_ordered<'a, F, B>(
+    /// 5.5 and up.
+    pub fn connect_ordered<'a, F>(
         &'a self,
-        stream: &'a F,
-        iov: &'a B,
+        socket: &'a F,
+        address: SocketAddr,
         ordering: Ordering,
-    ) -> Completion<'a, usize>
+    ) -> Completion<'a, ()>
     where
         F: AsRawFd,
-        B: AsIoVec + AsIoVecMut,
     {
-        let iov = iov.into_new_iovec();
+        self.with_pinned_sqe(
+            None,
+            false,
+            Pinned::SockAddr(Box::new(addr2raw(address))),
+            |_, _| (),
+            |sqe, pinned| {
+                sqe.prep_rw(
+                    IORING_OP_CONNECT,
+                    socket.as_raw_fd(),
+                    0,
+                    0,
+                    ordering,
+                );
+                if let Pinned::SockAddr(buf) = pinned {
+                    let storage_ptr: *const libc::sockaddr_storage =
+                        &buf.0;
+                    sqe.addr = storage_ptr as u64;
+                    sqe.off = u64::from(buf.1);
+                }
+            },
+        )
+    }
 
-        self.with_sqe(Some(iov), true, |sqe| {
+    /// Asynchronously connects a socket to an address
+    /// of any family, such as a `libc::sockaddr_un`
+    /// for unix sockets, which the kernel reads as
+    /// `size_of::<T>()` bytes.
+    ///
+    /// Accepts an `Ordering` specification.
+    ///
+    /// # Warning
+    ///
+    /// This only becomes usable on linux kernels
+    /// 5.5 and up.
+    pub fn connect_raw<'a, F, T>(
+        &'a self,
+        socket: &'a F,
+        address: &'a T,
+        ordering: Ordering,
+    ) -> Completion<'a, ()>
+    where
+        F: AsRawFd,
+    {
+        let address_ptr: *const T = address;
+        self.with_sqe(None, false, |sqe| {
             sqe.prep_rw(
-                IORING_OP_RECV,
-                stream.as_raw_fd(),
-                0,
+                IORING_OP_CONNECT,
+                socket.as_raw_fd(),
                 0,
+                u64::try_from(size_of::<T>()).unwrap(),
                 ordering,
             );
-            sqe.len = u32::try_from(iov.iov_len).unwrap();
+            sqe.addr = address_ptr as u64;
         })
     }
 
-    /// Flushes all buffered writes, and associated
-    /// metadata changes.
+    /// Shuts down the read side, the write side, or
+    /// both sides of a socket, as with `shutdown(2)`.
+    /// `how` takes `libc::SHUT_RD`, `libc::SHUT_WR`
+    /// or `libc::SHUT_RDWR`.
     ///
     /// # Warning
     ///
-    /// You usually don't want to do this without
-    /// linking to a previous write, because
-    /// `io_uring` will execute operations out-of-order.
-    /// Without setting a `Link` ordering on the previous
-    /// operation, or using `fsync_ordered` with
-    /// the `Drain` ordering, causing all previous
-    /// operations to complete before itself.
-    ///
-    /// Additionally, fsync does not ensure that
-    /// the file actually exists in its parent
-    /// directory. So, for new files, you must
-    /// also fsync the parent directory.
-    ///
-    /// This does nothing for files opened in
-    /// `O_DIRECT` mode.
-    pub fn fsync<'a>(
+    /// This only becomes usable on linux kernels
+    /// 5.11 and up.
+    pub fn shutdown<'a, F>(
         &'a self,
-        file: &'a File,
-    ) -> Completion<'a, ()> {
-        self.fsync_ordered(file, Ordering::None)
+        socket: &'a F,
+        how: i32,
+    ) -> Completion<'a, ()>
+    where
+        F: AsRingFd,
+    {
+        self.shutdown_ordered(socket, how, Ordering::None)
     }
 
-    /// Flushes all buffered writes, and associated
-    /// metadata changes.
-    ///
-    /// You probably want to
-    /// either use a `Link` ordering on a previous
-    /// write (or chain of separate writes), or
-    /// use the `Drain` ordering on this operation.
-    ///
-    /// You may pass in an `Ordering` to specify
-    /// two different optional behaviors:
+    /// Shuts down the read side, the write side, or
+    /// both sides of a socket, as with `shutdown(2)`.
+    /// `how` takes `libc::SHUT_RD`, `libc::SHUT_WR`
+    /// or `libc::SHUT_RDWR`.
     ///
-    /// * `Ordering::Link` causes the next
-    ///   submitted operation to wait until
-    ///   this one finishes. Useful for
-    ///   things like file copy, fsync-after-write,
-    ///   or proxies.
-    /// * `Ordering::Drain` causes all previously
-    ///   submitted operations to complete before
-    ///   this one begins.
+    /// Accepts an `Ordering` specification, so that
+    /// the shutdown may for instance be linked after
+    /// the last `send` on a connection.
     ///
     /// # Warning
     ///
-    /// fsync does not ensure that
-    /// the file actually exists in its parent
-    /// directory. So, for new files, you must
-    /// also fsync the parent directory.
-    /// This does nothing for files opened in
-    /// `O_DIRECT` mode.
-    pub fn fsync_ordered<'a>(
+    /// This only becomes usable on linux kernels
+    /// 5.11 and up.
+    pub fn shutdown_ordered<'a, F>(
         &'a self,
-        file: &'a File,
+        socket: &'a F,
+        how: i32,
         ordering: Ordering,
-    ) -> Completion<'a, ()> {
+    ) -> Completion<'a, ()>
+    where
+        F: AsRingFd,
+    {
+        // the kernel rejects a negative `how`.
+        #[allow(clippy::cast_sign_loss)]
+        let raw_how = how as u32;
+
         self.with_sqe(None, false, |sqe| {
-            sqe.prep_rw(
-                IORING_OP_FSYNC,
-                file.as_raw_fd(),
-                0,
+            sqe.prep_rw_file(
+                IORING_OP_SHUTDOWN,
+                socket,
+                usize::try_from(raw_how).unwrap(),
                 0,
                 ordering,
             )
         })
     }
 
-    /// Flushes all buffered writes, and the specific
-    /// metadata required to access the data. This
-    /// will skip syncing metadata like atime.
+    /// Send a buffer to the target socket
+    /// or file-like destination.
     ///
-    /// You probably want to
-    /// either use a `Link` ordering on a previous
-    /// write (or chain of separate writes), or
-    /// use the `Drain` ordering on this operation
-    /// with the `fdatasync_ordered` method.
+    /// Returns the length that was successfully
+    /// written.
     ///
     /// # Warning
     ///
-    /// fdatasync does not ensure that
-    /// the file actually exists in its parent
-    /// directory. So, for new files, you must
-    /// also fsync the parent directory.
-    /// This does nothing for files opened in
-    /// `O_DIRECT` mode.
-    pub fn fdatasync<'a>(
+    /// This only becomes usable on linux kernels
+    /// 5.6 and up.
+    pub fn send<'a, F, B>(
         &'a self,
-        file: &'a File,
-    ) -> Completion<'a, ()> {
-        self.fdatasync_ordered(file, Ordering::None)
+        stream: &'a F,
+        iov: &'a B,
+    ) -> Completion<'a, usize>
+    where
+        F: AsRingFd,
+        B: 'a + AsIoVec,
+    {
+        self.send_ordered(stream, iov, Ordering::None)
     }
 
-    /// Flushes all buffered writes, and the specific
-    /// metadata required to access the data. This
-    /// will skip syncing metadata like atime.
+    /// Send a buffer to the target socket
+    /// or file-like destination.
     ///
-    /// You probably want to
-    /// either use a `Link` ordering on a previous
-    /// write (or chain of separate writes), or
-    /// use the `Drain` ordering on this operation.
+    /// Returns the length that was successfully
+    /// written.
     ///
-    /// You may pass in an `Ordering` to specify
-    /// two different optional behaviors:
+    /// Accepts an `Ordering` specification.
     ///
-    /// * `Ordering::Link` causes the next
-    ///   submitted operation to wait until
-    ///   this one finishes. Useful for
-    ///   things like file copy, fsync-after-write,
-    ///   or proxies.
-    /// * `Ordering::Drain` causes all previously
-    ///   submitted operations to complete before
-    ///   this one begins.
+    /// # Warning
+    ///
+    /// This only becomes usable on linux kernels
+    /// 5.6 and up.
+    pub fn send_ordered<'a, F, B>(
+        &'a self,
+        stream: &'a F,
+        iov: &'a B,
+        ordering: Ordering,
+    ) -> Completion<'a, usize>
+    where
+        F: AsRingFd,
+        B: 'a + AsIoVec,
+    {
+        self.send_with_flags(stream, iov, 0, ordering)
+    }
+
+    /// Send a buffer to the target socket, passing
+    /// `flags` along as the `MSG_*` flags of
+    /// `send(2)`, such as `MSG_DONTWAIT` or
+    /// `MSG_NOSIGNAL`.
+    ///
+    /// Returns the length that was successfully
+    /// written.
+    ///
+    /// Accepts an `Ordering` specification.
     ///
     /// # Warning
     ///
-    /// fdatasync does not ensure that
-    /// the file actually exists in its parent
-    /// directory. So, for new files, you must
-    /// also fsync the parent directory.
-    /// This does nothing for files opened in
-    /// `O_DIRECT` mode.
-    pub fn fdatasync_ordered<'a>(
+    /// This only becomes usable on linux kernels
+    /// 5.6 and up.
+    pub fn send_with_flags<'a, F, B>(
         &'a self,
-        file: &'a File,
+        stream: &'a F,
+        iov: &'a B,
+        flags: i32,
         ordering: Ordering,
-    ) -> Completion<'a, ()> {
-        self.with_sqe(None, false, |mut sqe| {
-            sqe.prep_rw(
-                IORING_OP_FSYNC,
-                file.as_raw_fd(),
+    ) -> Completion<'a, usize>
+    where
+        F: AsRingFd,
+        B: 'a + AsIoVec,
+    {
+        let iov = iov.into_new_iovec();
+        #[allow(clippy::cast_sign_loss)]
+        let msg_flags = flags as u32;
+
+        self.with_sqe(None, false, |sqe| {
+            sqe.prep_rw_file(
+                IORING_OP_SEND,
+                stream,
                 0,
                 0,
                 ordering,
             );
-            sqe.flags |= IORING_FSYNC_DATASYNC;
+            sqe.addr = iov.iov_base as u64;
+            sqe.len = u32::try_from(iov.iov_len).unwrap();
+            sqe.__bindgen_anon_1.msg_flags = msg_flags;
         })
     }
 
-    /// Synchronizes the data associated with a range
-    /// in a file. Does not synchronize any metadata
-    /// updates, which can cause data loss if you
-    /// are not writing to a file whose metadata
-    /// has previously been synchronized.
+    /// Send a buffer to the target socket without
+    /// copying it into the kernel, passing `flags`
+    /// along as the `MSG_*` flags of `send(2)`.
     ///
-    /// You probably want to have a prior write
-    /// linked to this, or set `Ordering::Drain`
-    /// by using `sync_file_range_ordered` instead.
+    /// Returns the length that was successfully
+    /// written. The kernel keeps reading from the
+    /// buffer after the send itself completes, so
+    /// the `Completion` only finishes once the
+    /// kernel notifies that it is done with it.
+    /// Like `send`, this also takes `FixedFile`
+    /// indices.
     ///
-    /// Under the hood, this uses the "pessimistic"
-    /// set of flags:
-    /// `SYNC_FILE_RANGE_WRITE | SYNC_FILE_RANGE_WAIT_AFTER`
-    pub fn sync_file_range<'a>(
+    /// # Warning
+    ///
+    /// This only becomes usable on linux kernels
+    /// 6.0 and up.
+    pub fn send_zc<'a, F, B>(
         &'a self,
-        file: &'a File,
-        offset: u64,
-        len: usize,
-    ) -> Completion<'a, ()> {
-        self.sync_file_range_ordered(
-            file,
-            offset,
-            len,
+        stream: &'a F,
+        buf: &'a B,
+        flags: i32,
+    ) -> Completion<'a, usize>
+    where
+        F: AsRingFd,
+        B: 'a + AsIoVec,
+    {
+        self.send_zc_ordered(
+            stream,
+            buf,
+            flags,
             Ordering::None,
         )
     }
 
-    /// Synchronizes the data associated with a range
-    /// in a file. Does not synchronize any metadata
-    /// updates, which can cause data loss if you
-    /// are not writing to a file whose metadata
-    /// has previously been synchronized.
+    /// Send a buffer to the target socket without
+    /// copying it into the kernel, passing `flags`
+    /// along as the `MSG_*` flags of `send(2)`.
     ///
-    /// You probably want to have a prior write
-    /// linked to this, or set `Ordering::Drain`.
+    /// Returns the length that was successfully
+    /// written. The kernel keeps reading from the
+    /// buffer after the send itself completes, so
+    /// the `Completion` only finishes once the
+    /// kernel notifies that it is done with it.
     ///
-    /// Under the hood, this uses the "pessimistic"
-    /// set of flags:
-    /// `SYNC_FILE_RANGE_WRITE | SYNC_FILE_RANGE_WAIT_AFTER`
-    pub fn sync_file_range_ordered<'a>(
+    /// # Warning
+    ///
+    /// This only becomes usable on linux kernels
+    /// 6.0 and up.
+    pub fn send_zc_ordered<'a, F, B>(
         &'a self,
-        file: &'a File,
-        offset: u64,
-        len: usize,
+        stream: &'a F,
+        buf: &'a B,
+        flags: i32,
         ordering: Ordering,
-    ) -> Completion<'a, ()> {
-        self.with_sqe(None, false, |mut sqe| {
-            sqe.prep_rw(
-                IORING_OP_SYNC_FILE_RANGE,
-                file.as_raw_fd(),
-                len,
-                offset,
+    ) -> Completion<'a, usize>
+    where
+        F: AsRingFd,
+        B: 'a + AsIoVec,
+    {
+        let iov = buf.into_new_iovec();
+        #[allow(clippy::cast_sign_loss)]
+        let msg_flags = flags as u32;
+
+        self.with_sqe(None, false, |sqe| {
+            sqe.prep_rw_file(
+                IORING_OP_SEND_ZC,
+                stream,
+                iov.iov_len,
+                0,
                 ordering,
             );
-            sqe.flags |= u8::try_from(
-                // We don't use this because it causes
-                // EBADF to be thrown. Looking at
-                // linux's fs/sync.c, it seems as though
-                // it performs an identical operation
-                // as SYNC_FILE_RANGE_WAIT_AFTER.
-                // libc::SYNC_FILE_RANGE_WAIT_BEFORE |
-                libc::SYNC_FILE_RANGE_WRITE
-                    | libc::SYNC_FILE_RANGE_WAIT_AFTER,
-            )
-            .unwrap();
+            sqe.addr = iov.iov_base as u64;
+            sqe.__bindgen_anon_1.msg_flags = msg_flags;
         })
     }
 
-    /// Writes data at the provided buffer using
-    /// vectored IO. Be sure to check the returned
-    /// `io_uring_cqe`'s `res` field to see if a
-    /// short write happened. This will contain
-    /// the number of bytes written.
+    /// Receive data from the target socket
+    /// or file-like destination, and place
+    /// it in the given buffer.
     ///
-    /// Note that the file argument is generic
-    /// for anything that supports AsRawFd:
-    /// sockets, files, etc...
-    pub fn write_at<'a, F, B>(
+    /// Returns the length that was successfully
+    /// read.
+    ///
+    /// # Warning
+    ///
+    /// This only becomes usable on linux kernels
+    /// 5.6 and up.
+    pub fn recv<'a, F, B>(
         &'a self,
-        file: &'a F,
+        stream: &'a F,
         iov: &'a B,
-        at: u64,
     ) -> Completion<'a, usize>
     where
-        F: AsRawFd,
-        B: 'a + AsIoVec,
+        F: AsRingFd,
+        B: AsIoVec + AsIoVecMut,
     {
-        self.write_at_ordered(file, iov, at, Ordering::None)
+        self.recv_ordered(stream, iov, Ordering::None)
     }
 
-    /// Writes data at the provided buffer using
-    /// vectored IO.
+    /// Receive data from the target socket
+    /// or file-like destination, and place
+    /// it in the given buffer.
     ///
-    /// Be sure to check the returned
-    /// `io_uring_cqe`'s `res` field to see if a
-    /// short write happened. This will contain
-    /// the number of bytes written.
+    /// Returns the length that was successfully
+    /// read.
     ///
-    /// You may pass in an `Ordering` to specify
-    /// two different optional behaviors:
+    /// Accepts an `Ordering` specification.
     ///
-    /// * `Ordering::Link` causes the next
-    ///   submitted operation to wait until
-    ///   this one finishes. Useful for
-    ///   things like file copy, fsync-after-write,
-    ///   or proxies.
-    /// * `Ordering::Drain` causes all previously
-    ///   submitted operations to complete before
-    ///   this one begins.
+    /// # Warning
     ///
-    /// Note that the file argument is generic
-    /// for anything that supports AsRawFd:
-    /// sockets, files, etc...
-    pub fn write_at_ordered<'a, F, B>(
+    /// This only becomes usable on linux kernels
+    /// 5.6 and up.
+    pub fn recv_ordered<'a, F, B>(
         &'a self,
-        file: &'a F,
+        stream: &'a F,
         iov: &'a B,
-        at: u64,
         ordering: Ordering,
     ) -> Completion<'a, usize>
     where
-        F: AsRawFd,
-        B: 'a + AsIoVec,
+        F: AsRingFd,
+        B: AsIoVec + AsIoVecMut,
     {
-        self.with_sqe(
-            Some(iov.into_new_iovec()),
-            false,
-            |sqe| {
-                sqe.prep_rw(
-                    IORING_OP_WRITEV,
-                    file.as_raw_fd(),
-                    1,
-                    at,
-                    ordering,
-                )
-            },
-        )
+        self.recv_with_flags(stream, iov, 0, ordering)
     }
 
-    /// Reads data into the provided buffer from the
-    /// given file-like object, at the given offest,
-    /// using vectored IO. Be sure to check the returned
-    /// `io_uring_cqe`'s `res` field to see if a
-    /// short read happened. This will contain
-    /// the number of bytes read.
+    /// Receive data from the target socket into the
+    /// given buffer, passing `flags` along as the
+    /// `MSG_*` flags of `recv(2)`, such as
+    /// `MSG_PEEK` or `MSG_WAITALL`.
     ///
-    /// Note that the file argument is generic
-    /// for anything that supports AsRawFd:
-    /// sockets, files, etc...
-    pub fn read_at<'a, F, B>(
+    /// Returns the length that was successfully
+    /// read.
+    ///
+    /// Accepts an `Ordering` specification.
+    ///
+    /// # Warning
+    ///
+    /// This only becomes usable on linux kernels
+    /// 5.6 and up.
+    pub fn recv_with_flags<'a, F, B>(
         &'a self,
-        file: &'a F,
+        stream: &'a F,
         iov: &'a B,
-        at: u64,
+        flags: i32,
+        ordering: Ordering,
     ) -> Completion<'a, usize>
     where
-        F: AsRawFd,
+        F: AsRingFd,
         B: AsIoVec + AsIoVecMut,
     {
-        self.read_at_ordered(file, iov, at, Ordering::None)
+        let iov = iov.into_new_iovec();
+        #[allow(clippy::cast_sign_loss)]
+        let msg_flags = flags as u32;
+
+        self.with_sqe(None, false, |sqe| {
+            sqe.prep_rw_file(
+                IORING_OP_RECV,
+                stream,
+                0,
+                0,
+                ordering,
+            );
+            sqe.addr = iov.iov_base as u64;
+            sqe.len = u32::try_from(iov.iov_len).unwrap();
+            sqe.__bindgen_anon_1.msg_flags = msg_flags;
+        })
     }
 
-    /// Reads data into the provided buffer using
-    /// vectored IO. Be sure to check the returned
-    /// `io_uring_cqe`'s `res` field to see if a
-    /// short read happened. This will contain
-    /// the number of bytes read.
+    /// Send a message described by a `Msghdr`,
+    /// which can carry a destination address and
+    /// ancillary data, to the target socket.
     ///
-    /// You may pass in an `Ordering` to specify
-    /// two different optional behaviors:
+    /// Returns the length of the data that was
+    /// successfully written. Like `send`, this also
+    /// takes `FixedFile` indices.
     ///
-    /// * `Ordering::Link` causes the next
-    ///   submitted operation to wait until
-    ///   this one finishes. Useful for
-    ///   things like file copy, fsync-after-write,
-    ///   or proxies.
-    /// * `Ordering::Drain` causes all previously
-    ///   submitted operations to complete before
-    ///   this one begins.
+    /// # Warning
     ///
-    /// Note that the file argument is generic
-    /// for anything that supports AsRawFd:
-    /// sockets, files, etc...
-    pub fn read_at_ordered<'a, F, B>(
+    /// This only becomes usable on linux kernels
+    /// 5.3 and up.
+    pub fn sendmsg<'a, 'b, F>(
         &'a self,
-        file: &'a F,
-        iov: &'a B,
-        at: u64,
-        ordering: Ordering,
+        socket: &'a F,
+        msg: &'a Msghdr<'b>,
     ) -> Completion<'a, usize>
     where
-        F: AsRawFd,
-        B: AsIoVec + AsIoVecMut,
+        F: AsRingFd,
     {
-        self.with_sqe(
-            Some(iov.into_new_iovec()),
-            false,
-            |sqe| {
-                sqe.prep_rw(
-                    IORING_OP_READV,
-                    file.as_raw_fd(),
-                    1,
-                    at,
-                    ordering,
-                )
-            },
-        )
-    }
-
-    /// Don't do anything. This is
-    /// mostly for debugging and tuning.
-    pub fn nop<'a>(&'a self) -> Completion<'a, ()> {
-        self.nop_ordered(Ordering::None)
+        self.sendmsg_ordered(socket, msg, Ordering::None)
     }
 
-    /// Don't do anything. This is
-    /// mostly for debugging and tuning.
-    pub fn nop_ordered<'a>(
+    /// Send a message described by a `Msghdr`,
+    /// which can carry a destination address and
+    /// ancillary data, to the target socket.
+    ///
+    /// Returns the length of the data that was
+    /// successfully written.
+    ///
+    /// Accepts an `Ordering` specification.
+    ///
+    /// # Warning
+    ///
+    /// This only becomes usable on linux kernels
+    /// 5.3 and up.
+    pub fn sendmsg_ordered<'a, 'b, F>(
         &'a self,
+        socket: &'a F,
+        msg: &'a Msghdr<'b>,
         ordering: Ordering,
-    ) -> Completion<'a, ()> {
+    ) -> Completion<'a, usize>
+    where
+        F: AsRingFd,
+    {
+        let msghdr_ptr: *const libc::msghdr = &msg.inner;
+
         self.with_sqe(None, false, |sqe| {
-            sqe.prep_rw(IORING_OP_NOP, 0, 1, 0, ordering)
+            sqe.prep_rw_file(
+                IORING_OP_SENDMSG,
+                socket,
+                1,
+                0,
+                ordering,
+            );
+            sqe.addr = msghdr_ptr as u64;
         })
     }
 
-    /// Block until all items in the submission queue
-    /// are submitted to the kernel. This can
-    /// be avoided by using the `SQPOLL` mode
-    /// (a privileged operation) on the `Config`
-    /// struct.
+    /// Receive a message from the target socket
+    /// into the buffers described by a `MsghdrMut`,
+    /// which can also receive its source address
+    /// and ancillary data.
     ///
-    /// Note that this is performed automatically
-    /// and in a more fine-grained way when a
-    /// `Completion` is consumed via `Completion::wait`
-    /// or awaited in a Future context.
+    /// Returns the length of the data that was
+    /// successfully read. The `MsghdrMut` reports
+    /// the rest once the `Completion` is done. Like
+    /// `recv`, this also takes `FixedFile` indices.
     ///
-    /// You don't need to call this if you are
-    /// calling `.wait()` or `.await` on the
-    /// `Completion` quickly, but if you are
-    /// doing some other stuff that could take
-    /// a while first, calling this will ensure
-    /// that the operation is being executed
-    /// by the kernel in the mean time.
-    pub fn submit_all(&self) {
-        let mut sq = {
-            let _get_sq_mu = Measure::new(&M.sq_mu_wait);
-            self.sq.lock().unwrap()
-        };
-        let _hold_sq_mu = Measure::new(&M.sq_mu_hold);
-        sq.submit_all(self.flags, self.ring_fd);
+    /// # Warning
+    ///
+    /// This only becomes usable on linux kernels
+    /// 5.3 and up.
+    pub fn recvmsg<'a, 'b, F>(
+        &'a self,
+        socket: &'a F,
+        msg: &'a mut MsghdrMut<'b>,
+    ) -> Completion<'a, usize>
+    where
+        F: AsRingFd,
+    {
+        self.recvmsg_ordered(socket, msg, Ordering::None)
     }
 
-    fn with_sqe<'a, F, C>(
+    /// Receive a message from the target socket
+    /// into the buffers described by a `MsghdrMut`,
+    /// which can also receive its source address
+    /// and ancillary data.
+    ///
+    /// Returns the length of the data that was
+    /// successfully read. The `MsghdrMut` reports
+    /// the rest once the `Completion` is done.
+    ///
+    /// Accepts an `Ordering` specification.
+    ///
+    /// # Warning
+    ///
+    /// This only becomes usable on linux kernels
+    /// 5.3 and up.
+    pub fn recvmsg_ordered<'a, 'b, F>(
         &'a self,
-        iovec: Option<libc::iovec>,
-        msghdr: bool,
-        f: F,
-    ) -> Completion<'a, C>
+        socket: &'a F,
+        msg: &'a mut MsghdrMut<'b>,
+        ordering: Ordering,
+    ) -> Completion<'a, usize>
     where
-        F: FnOnce(&mut io_uring_sqe),
-        C: FromCqe,
+        F: AsRingFd,
     {
-        let ticket = self.ticket_queue.pop();
-        let (mut completion, filler) = pair(self);
+        let msghdr_ptr: *mut libc::msghdr = &mut msg.inner;
 
-        let data_ptr = self
-            .in_flight
-            .insert(ticket, iovec, msghdr, filler);
+        self.with_sqe(None, false, |sqe| {
+            sqe.prep_rw_file(
+                IORING_OP_RECVMSG,
+                socket,
+                1,
+                0,
+                ordering,
+            );
+            sqe.addr = msghdr_ptr as u64;
+        })
+    }
 
-        let mut sq = {
-            let _get_sq_mu = Measure::new(&M.sq_mu_wait);
-            self.sq.lock().unwrap()
-        };
-        let _hold_sq_mu = Measure::new(&M.sq_mu_hold);
+    /// Receive up to `len` bytes from the target
+    /// socket or file-like destination into a buffer
+    /// that the kernel picks from the group
+    /// `group_id`, previously populated with
+    /// `provide_buffers` or through a `BufRing`.
+    ///
+    /// Returns the id of the chosen buffer and
+    /// the length that was successfully read. The
+    /// buffer is consumed from the group, and may be
+    /// handed back to it with `provide_buffers`, or
+    /// `BufRing::provide`, once its contents have
+    /// been used. Like `recv`, this also takes
+    /// `FixedFile` indices.
+    ///
+    /// # Warning
+    ///
+    /// This only becomes usable on linux kernels
+    /// 5.7 and up.
+    pub fn recv_buf_select<'a, F>(
+        &'a self,
+        stream: &'a F,
+        len: usize,
+        group_id: u16,
+    ) -> Completion<'a, (u16, usize)>
+    where
+        F: AsRingFd,
+    {
+        self.with_sqe(None, false, |sqe| {
+            sqe.prep_rw_file(
+                IORING_OP_RECV,
+                stream,
+                len,
+                0,
+                Ordering::None,
+            );
+            sqe.flags |= IOSQE_BUFFER_SELECT;
+            sqe.__bindgen_anon_2.buf_index = group_id;
+        })
+    }
 
-        completion.sqe_id =
-            self.loaded.fetch_add(1, Release) + 1;
+    /// Arms a multishot receive on the target socket,
+    /// yielding the id of a buffer picked from the
+    /// group `group_id`, previously populated with
+    /// `provide_buffers` or through a `BufRing`, and
+    /// the number of bytes received into it, every
+    /// time data arrives.
+    ///
+    /// The kernel stops the multishot when the group
+    /// runs out of buffers, in which case the stream
+    /// ends after yielding an `ENOBUFS` error, or when
+    /// the peer shuts down, in which case it ends
+    /// after yielding a zero length. Use
+    /// `CompletionStream::is_armed` to tell whether
+    /// the operation needs to be submitted again.
+    /// Like `recv`, this also takes `FixedFile`
+    /// indices.
+    ///
+    /// # Warning
+    ///
+    /// This only becomes usable on linux kernels
+    /// 6.0 and up.
+    pub fn recv_multi<'a, F>(
+        &'a self,
+        socket: &'a F,
+        group_id: u16,
+    ) -> CompletionStream<'a, (u16, usize)>
+    where
+        F: AsRingFd,
+    {
+        self.with_stream_sqe(|sqe| {
+            sqe.prep_rw_file(
+                IORING_OP_RECV,
+                socket,
+                0,
+                0,
+                Ordering::None,
+            );
+            sqe.ioprio |= IORING_RECV_MULTISHOT;
+            sqe.flags |= IOSQE_BUFFER_SELECT;
+            sqe.__bindgen_anon_2.buf_index = group_id;
+        })
+    }
 
-        let sqe = {
-            let _get_sqe = Measure::new(&M.get_sqe);
-            loop {
-                if let Some(sqe) =
-                    sq.try_get_sqe(self.flags)
-                {
-                    break sqe;
-                } else {
-                    let submitted = sq.submit_all(
-                        self.flags,
-                        self.ring_fd,
-                    );
-                    self.submitted
-                        .fetch_add(submitted, Release);
-                };
-            }
-        };
+    /// Hands the kernel `count` equally-sized buffers
+    /// carved out of `buf`, with ids counting up from
+    /// `start_bid`, for operations that select a
+    /// buffer from the group `group_id` such as
+    /// `recv_buf_select` and `read_at_buf_select`.
+    /// Each buffer is `buf.len() / count` bytes long,
+    /// and whatever is left over at the end of `buf`
+    /// is not handed out.
+    ///
+    /// Fails with `io::ErrorKind::InvalidInput` for a
+    /// `count` of 0.
+    ///
+    /// # Safety
+    ///
+    /// The kernel keeps writing into these buffers
+    /// after the returned `Completion` is done, up
+    /// until each one is selected by an operation
+    /// or taken back with `remove_buffers`. The
+    /// caller must keep `buf` alive and must not
+    /// access a buffer in the meantime.
+    ///
+    /// # Warning
+    ///
+    /// This only becomes usable on linux kernels
+    /// 5.7 and up.
+    #[allow(unsafe_code)]
+    pub unsafe fn provide_buffers<'a, B>(
+        &'a self,
+        buf: &'a B,
+        count: u16,
+        group_id: u16,
+        start_bid: u16,
+    ) -> Completion<'a, ()>
+    where
+        B: AsIoVec + AsIoVecMut,
+    {
+        if count == 0 {
+            return failed(
+                self,
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "provide_buffers needs at least one \
+                     buffer",
+                ),
+            );
+        }
 
-        sqe.user_data = ticket as u64;
-        sqe.addr = data_ptr;
-        f(sqe);
+        let iov = buf.into_new_iovec();
+        let buf_len = iov.iov_len / usize::from(count);
 
-        completion
+        self.with_sqe(None, false, |sqe| {
+            sqe.prep_rw(
+                IORING_OP_PROVIDE_BUFFERS,
+                i32::from(count),
+                buf_len,
+                u64::from(start_bid),
+                Ordering::None,
+            );
+            sqe.addr = iov.iov_base as u64;
+            sqe.__bindgen_anon_2.buf_index = group_id;
+        })
     }
-}
 
-fn addr2raw(
-    addr: &std::net::SocketAddr,
-) -> (*const libc::sockaddr, libc::socklen_t) {
-    match *addr {
-        std::net::SocketAddr::V4(ref a) => {
-            let b: *const std::net::SocketAddrV4 = a;
-            (
-                b as *const _,
-                std::mem::size_of_val(a) as libc::socklen_t,
-            )
-        }
-        std::net::SocketAddr::V6(ref a) => {
-            let b: *const std::net::SocketAddrV6 = a;
-            (
-                b as *const _,
-                std::mem::size_of_val(a) as libc::socklen_t,
-            )
-        }
-    }
+    /// Takes back up to `count` unused buffers from
+    /// the group `group_id`, which were previously
+    /// provided with `provide_buffers`.
+    ///
+    /// Returns the number of buffers removed.
+    ///
+    /// # Warning
+    ///
+    /// This only becomes usable on linux kernels
+    /// 5.7 and up.
+    pub fn remove_buffers<'a>(
+        &'a self,
+        count: u16,
+        group_id: u16,
+    ) -> Completion<'a, usize> {
+        self.with_sqe(None, false, |sqe| {
+            sqe.prep_rw(
+                IORING_OP_REMOVE_BUFFERS,
+                i32::from(count),
+                0,
+                0,
+                Ordering::None,
+            );
+            sqe.__bindgen_anon_2.buf_index = group_id;
+        })
+    }
+
+    /// Opens the file at `path`, relative to the
+    /// directory `dirfd`, returning the new raw
+    /// file descriptor. `flags` and `mode` are the
+    /// same as for `openat(2)`. Pass `&libc::AT_FDCWD`
+    /// as `dirfd` to resolve relative paths against
+    /// the current working directory.
+    ///
+    /// The returned descriptor is owned by the
+    /// caller, who is responsible for closing it,
+    /// for instance by wrapping it in a `File`
+    /// using `FromRawFd`.
+    ///
+    /// The `path` is borrowed for the lifetime
+    /// of the `Completion`, because the kernel
+    /// may read it at any point until the
+    /// operation completes.
+    ///
+    /// # Warning
+    ///
+    /// This only becomes usable on linux kernels
+    /// 5.6 and up.
+    pub fn openat<'a, F>(
+        &'a self,
+        dirfd: &'a F,
+        path: &'a CStr,
+        flags: i32,
+        mode: u32,
+    ) -> Completion<'a, RawFd>
+    where
+        F: AsRawFd,
+    {
+        self.openat_ordered(
+            dirfd,
+            path,
+            flags,
+            mode,
+            Ordering::None,
+        )
+    }
+
+    /// Opens the file at `path`, relative to the
+    /// directory `dirfd`, returning the new raw
+    /// file descriptor.
+    ///
+    /// Accepts an `Ordering` specification.
+    ///
+    /// # Warning
+    ///
+    /// This only becomes usable on linux kernels
+    /// 5.6 and up.
+    pub fn openat_ordered<'a, F>(
+        &'a self,
+        dirfd: &'a F,
+        path: &'a CStr,
+        flags: i32,
+        mode: u32,
+        ordering: Ordering,
+    ) -> Completion<'a, RawFd>
+    where
+        F: AsRawFd,
+    {
+        self.with_sqe(None, false, |sqe| {
+            sqe.prep_rw(
+                IORING_OP_OPENAT,
+                dirfd.as_raw_fd(),
+                usize::try_from(mode).unwrap(),
+                0,
+                ordering,
+            );
+            sqe.addr = path.as_ptr() as u64;
+            #[allow(clippy::cast_sign_loss)]
+            let open_flags = flags as u32;
+            sqe.__bindgen_anon_1.open_flags = open_flags;
+        })
+    }
+
+    /// Opens the file at `path`, relative to the
+    /// directory `dirfd`, like `openat`, but as
+    /// `how` says, whose `resolve` flags can keep
+    /// the path from escaping `dirfd` or following
+    /// symlinks. This returns the new raw file
+    /// descriptor, which the caller owns.
+    ///
+    /// Both `path` and `how` are borrowed for the
+    /// lifetime of the `Completion`, because the
+    /// kernel may read them at any point until the
+    /// operation completes.
+    ///
+    /// # Warning
+    ///
+    /// This only becomes usable on linux kernels
+    /// 5.6 and up. Older kernels fail the operation
+    /// with an error saying so.
+    pub fn openat2<'a, F>(
+        &'a self,
+        dirfd: &'a F,
+        path: &'a CStr,
+        how: &'a OpenHow,
+    ) -> Completion<'a, RawFd>
+    where
+        F: AsRawFd,
+    {
+        self.openat2_ordered(
+            dirfd,
+            path,
+            how,
+            Ordering::None,
+        )
+    }
+
+    /// Opens the file at `path`, relative to the
+    /// directory `dirfd`, as `how` says, returning
+    /// the new raw file descriptor.
+    ///
+    /// Accepts an `Ordering` specification.
+    ///
+    /// # Warning
+    ///
+    /// This only becomes usable on linux kernels
+    /// 5.6 and up. Older kernels fail the operation
+    /// with an error saying so.
+    pub fn openat2_ordered<'a, F>(
+        &'a self,
+        dirfd: &'a F,
+        path: &'a CStr,
+        how: &'a OpenHow,
+        ordering: Ordering,
+    ) -> Completion<'a, RawFd>
+    where
+        F: AsRawFd,
+    {
+        self.with_sqe(None, false, |sqe| {
+            let how_ptr: *const OpenHow = how;
+            sqe.prep_rw(
+                IORING_OP_OPENAT2,
+                dirfd.as_raw_fd(),
+                size_of::<OpenHow>(),
+                how_ptr as u64,
+                ordering,
+            );
+            sqe.addr = path.as_ptr() as u64;
+        })
+    }
+
+    /// Closes the raw file descriptor `fd`.
+    ///
+    /// The descriptor is taken by value, and
+    /// it must not be used again once this is
+    /// submitted. If it is owned by a `File`
+    /// or socket, give up that ownership first
+    /// with `IntoRawFd` so that it is not closed
+    /// a second time on drop.
+    ///
+    /// # Warning
+    ///
+    /// This only becomes usable on linux kernels
+    /// 5.6 and up.
+    pub fn close<'a>(
+        &'a self,
+        fd: RawFd,
+    ) -> Completion<'a, ()> {
+        self.close_ordered(fd, Ordering::None)
+    }
+
+    /// Closes the raw file descriptor `fd`.
+    ///
+    /// Accepts an `Ordering` specification.
+    ///
+    /// # Warning
+    ///
+    /// This only becomes usable on linux kernels
+    /// 5.6 and up.
+    pub fn close_ordered<'a>(
+        &'a self,
+        fd: RawFd,
+        ordering: Ordering,
+    ) -> Completion<'a, ()> {
+        self.with_sqe(None, false, |sqe| {
+            sqe.prep_rw(IORING_OP_CLOSE, fd, 0, 0, ordering)
+        })
+    }
+
+    /// Removes the file at `path`, relative to the
+    /// directory `dir`, as with `unlinkat(2)`. Pass
+    /// `libc::AT_REMOVEDIR` in `flags` to remove an
+    /// empty directory instead.
+    ///
+    /// # Warning
+    ///
+    /// This only becomes usable on linux kernels
+    /// 5.11 and up.
+    pub fn unlinkat<'a, F>(
+        &'a self,
+        dir: &'a F,
+        path: &'a CStr,
+        flags: i32,
+    ) -> Completion<'a, ()>
+    where
+        F: AsRawFd,
+    {
+        self.unlinkat_ordered(
+            dir,
+            path,
+            flags,
+            Ordering::None,
+        )
+    }
+
+    /// Removes the file at `path`, relative to the
+    /// directory `dir`, as with `unlinkat(2)`. Pass
+    /// `libc::AT_REMOVEDIR` in `flags` to remove an
+    /// empty directory instead.
+    ///
+    /// Accepts an `Ordering` specification.
+    ///
+    /// # Warning
+    ///
+    /// This only becomes usable on linux kernels
+    /// 5.11 and up.
+    pub fn unlinkat_ordered<'a, F>(
+        &'a self,
+        dir: &'a F,
+        path: &'a CStr,
+        flags: i32,
+        ordering: Ordering,
+    ) -> Completion<'a, ()>
+    where
+        F: AsRawFd,
+    {
+        self.with_sqe(None, false, |sqe| {
+            sqe.prep_rw(
+                IORING_OP_UNLINKAT,
+                dir.as_raw_fd(),
+                0,
+                0,
+                ordering,
+            );
+            sqe.addr = path.as_ptr() as u64;
+            #[allow(clippy::cast_sign_loss)]
+            let unlink_flags = flags as u32;
+            sqe.__bindgen_anon_1.unlink_flags =
+                unlink_flags;
+        })
+    }
+
+    /// Creates a directory at `path`, relative to
+    /// the directory `dir`, with the permission bits
+    /// in `mode`, as with `mkdirat(2)`.
+    ///
+    /// # Warning
+    ///
+    /// This only becomes usable on linux kernels
+    /// 5.15 and up.
+    pub fn mkdirat<'a, F>(
+        &'a self,
+        dir: &'a F,
+        path: &'a CStr,
+        mode: u32,
+    ) -> Completion<'a, ()>
+    where
+        F: AsRawFd,
+    {
+        self.mkdirat_ordered(
+            dir,
+            path,
+            mode,
+            Ordering::None,
+        )
+    }
+
+    /// Creates a directory at `path`, relative to
+    /// the directory `dir`, with the permission bits
+    /// in `mode`, as with `mkdirat(2)`.
+    ///
+    /// Accepts an `Ordering` specification, so that
+    /// the new directory may for instance be opened
+    /// by a linked `openat`.
+    ///
+    /// # Warning
+    ///
+    /// This only becomes usable on linux kernels
+    /// 5.15 and up.
+    pub fn mkdirat_ordered<'a, F>(
+        &'a self,
+        dir: &'a F,
+        path: &'a CStr,
+        mode: u32,
+        ordering: Ordering,
+    ) -> Completion<'a, ()>
+    where
+        F: AsRawFd,
+    {
+        self.with_sqe(None, false, |sqe| {
+            sqe.prep_rw(
+                IORING_OP_MKDIRAT,
+                dir.as_raw_fd(),
+                usize::try_from(mode).unwrap(),
+                0,
+                ordering,
+            );
+            sqe.addr = path.as_ptr() as u64;
+        })
+    }
+
+    /// Creates a symbolic link at `linkpath`,
+    /// relative to the directory `new_dir`, that
+    /// points to `target`, as with `symlinkat(2)`.
+    ///
+    /// # Warning
+    ///
+    /// This only becomes usable on linux kernels
+    /// 5.15 and up.
+    pub fn symlinkat<'a, F>(
+        &'a self,
+        target: &'a CStr,
+        new_dir: &'a F,
+        linkpath: &'a CStr,
+    ) -> Completion<'a, ()>
+    where
+        F: AsRawFd,
+    {
+        self.symlinkat_ordered(
+            target,
+            new_dir,
+            linkpath,
+            Ordering::None,
+        )
+    }
+
+    /// Creates a symbolic link at `linkpath`,
+    /// relative to the directory `new_dir`, that
+    /// points to `target`, as with `symlinkat(2)`.
+    ///
+    /// Accepts an `Ordering` specification.
+    ///
+    /// # Warning
+    ///
+    /// This only becomes usable on linux kernels
+    /// 5.15 and up.
+    pub fn symlinkat_ordered<'a, F>(
+        &'a self,
+        target: &'a CStr,
+        new_dir: &'a F,
+        linkpath: &'a CStr,
+        ordering: Ordering,
+    ) -> Completion<'a, ()>
+    where
+        F: AsRawFd,
+    {
+        self.with_sqe(None, false, |sqe| {
+            sqe.prep_rw(
+                IORING_OP_SYMLINKAT,
+                new_dir.as_raw_fd(),
+                0,
+                linkpath.as_ptr() as u64,
+                ordering,
+            );
+            sqe.addr = target.as_ptr() as u64;
+        })
+    }
+
+    /// Creates a hard link at `new_path`, relative
+    /// to the directory `new_dir`, to the file at
+    /// `old_path`, relative to `old_dir`, as with
+    /// `linkat(2)`. `flags` takes the `AT_*` flags,
+    /// such as `libc::AT_SYMLINK_FOLLOW`.
+    ///
+    /// # Warning
+    ///
+    /// This only becomes usable on linux kernels
+    /// 5.15 and up.
+    pub fn linkat<'a, F1, F2>(
+        &'a self,
+        old_dir: &'a F1,
+        old_path: &'a CStr,
+        new_dir: &'a F2,
+        new_path: &'a CStr,
+        flags: i32,
+    ) -> Completion<'a, ()>
+    where
+        F1: AsRawFd,
+        F2: AsRawFd,
+    {
+        self.linkat_ordered(
+            old_dir,
+            old_path,
+            new_dir,
+            new_path,
+            flags,
+            Ordering::None,
+        )
+    }
+
+    /// Creates a hard link at `new_path`, relative
+    /// to the directory `new_dir`, to the file at
+    /// `old_path`, relative to `old_dir`, as with
+    /// `linkat(2)`. `flags` takes the `AT_*` flags,
+    /// such as `libc::AT_SYMLINK_FOLLOW`.
+    ///
+    /// Accepts an `Ordering` specification.
+    ///
+    /// # Warning
+    ///
+    /// This only becomes usable on linux kernels
+    /// 5.15 and up.
+    pub fn linkat_ordered<'a, F1, F2>(
+        &'a self,
+        old_dir: &'a F1,
+        old_path: &'a CStr,
+        new_dir: &'a F2,
+        new_path: &'a CStr,
+        flags: i32,
+        ordering: Ordering,
+    ) -> Completion<'a, ()>
+    where
+        F1: AsRawFd,
+        F2: AsRawFd,
+    {
+        self.with_sqe(None, false, |sqe| {
+            sqe.prep_rw(
+                IORING_OP_LINKAT,
+                old_dir.as_raw_fd(),
+                0,
+                new_path.as_ptr() as u64,
+                ordering,
+            );
+            sqe.addr = old_path.as_ptr() as u64;
+            // the kernel reads `len` back as a signed
+            // fd, so `AT_FDCWD` survives the cast
+            #[allow(clippy::cast_sign_loss)]
+            let (raw_new_dir, hardlink_flags) =
+                (new_dir.as_raw_fd() as u32, flags as u32);
+            sqe.len = raw_new_dir;
+            sqe.__bindgen_anon_1.hardlink_flags =
+                hardlink_flags;
+        })
+    }
+
+    /// Renames the file at `old_path`, relative to
+    /// the directory `old_dir`, to `new_path`,
+    /// relative to `new_dir`, as with
+    /// `renameat2(2)`. `flags` takes the
+    /// `libc::RENAME_*` flags, such as
+    /// `RENAME_NOREPLACE` and `RENAME_EXCHANGE`.
+    ///
+    /// # Warning
+    ///
+    /// This only becomes usable on linux kernels
+    /// 5.11 and up.
+    pub fn renameat<'a, F1, F2>(
+        &'a self,
+        old_dir: &'a F1,
+        old_path: &'a CStr,
+        new_dir: &'a F2,
+        new_path: &'a CStr,
+        flags: u32,
+    ) -> Completion<'a, ()>
+    where
+        F1: AsRawFd,
+        F2: AsRawFd,
+    {
+        self.renameat_ordered(
+            old_dir,
+            old_path,
+            new_dir,
+            new_path,
+            flags,
+            Ordering::None,
+        )
+    }
+
+    /// Renames the file at `old_path`, relative to
+    /// the directory `old_dir`, to `new_path`,
+    /// relative to `new_dir`, as with
+    /// `renameat2(2)`. `flags` takes the
+    /// `libc::RENAME_*` flags, such as
+    /// `RENAME_NOREPLACE` and `RENAME_EXCHANGE`.
+    ///
+    /// Accepts an `Ordering` specification.
+    ///
+    /// # Warning
+    ///
+    /// This only becomes usable on linux kernels
+    /// 5.11 and up.
+    pub fn renameat_ordered<'a, F1, F2>(
+        &'a self,
+        old_dir: &'a F1,
+        old_path: &'a CStr,
+        new_dir: &'a F2,
+        new_path: &'a CStr,
+        flags: u32,
+        ordering: Ordering,
+    ) -> Completion<'a, ()>
+    where
+        F1: AsRawFd,
+        F2: AsRawFd,
+    {
+        self.with_sqe(None, false, |sqe| {
+            sqe.prep_rw(
+                IORING_OP_RENAMEAT,
+                old_dir.as_raw_fd(),
+                0,
+                new_path.as_ptr() as u64,
+                ordering,
+            );
+            sqe.addr = old_path.as_ptr() as u64;
+            // the kernel reads `len` back as a signed
+            // fd, so `AT_FDCWD` survives the cast
+            #[allow(clippy::cast_sign_loss)]
+            let raw_new_dir = new_dir.as_raw_fd() as u32;
+            sqe.len = raw_new_dir;
+            sqe.__bindgen_anon_1.rename_flags = flags;
+        })
+    }
+
+    /// Retrieves extended file status for the file at
+    /// `path`, relative to the directory `dirfd`, as
+    /// with `statx(2)`. `mask` selects which `STATX_*`
+    /// fields are requested, and `flags` takes the
+    /// `AT_*` flags.
+    ///
+    /// To stat an already-open file descriptor, pass
+    /// it as `dirfd` along with an empty `path` and
+    /// `libc::AT_EMPTY_PATH` in `flags`.
+    ///
+    /// The kernel writes the result into a `statx`
+    /// buffer that is heap-allocated and owned by
+    /// the returned `Completion`. The timestamps
+    /// may be converted with `StatxExt`.
+    ///
+    /// # Warning
+    ///
+    /// This only becomes usable on linux kernels
+    /// 5.6 and up.
+    pub fn statx<'a, F>(
+        &'a self,
+        dirfd: &'a F,
+        path: &'a CStr,
+        flags: i32,
+        mask: u32,
+    ) -> Completion<'a, libc::statx>
+    where
+        F: AsRawFd,
+    {
+        self.statx_ordered(
+            dirfd,
+            path,
+            flags,
+            mask,
+            Ordering::None,
+        )
+    }
+
+    /// Retrieves extended file status for the file at
+    /// `path`, relative to the directory `dirfd`.
+    ///
+    /// Accepts an `Ordering` specification.
+    ///
+    /// # Warning
+    ///
+    /// This only becomes usable on linux kernels
+    /// 5.6 and up.
+    pub fn statx_ordered<'a, F>(
+        &'a self,
+        dirfd: &'a F,
+        path: &'a CStr,
+        flags: i32,
+        mask: u32,
+        ordering: Ordering,
+    ) -> Completion<'a, libc::statx>
+    where
+        F: AsRawFd,
+    {
+        self.statx_into(dirfd, path, flags, mask, ordering)
+    }
+
+    /// Retrieves the metadata of the file at `path`,
+    /// relative to the directory `dir`, like
+    /// `std::fs::metadata`. Symbolic links are
+    /// followed, and the basic fields of `statx(2)`
+    /// are requested along with the creation time.
+    ///
+    /// # Warning
+    ///
+    /// This only becomes usable on linux kernels
+    /// 5.6 and up.
+    pub fn stat<'a, F>(
+        &'a self,
+        dir: &'a F,
+        path: &'a CStr,
+    ) -> Completion<'a, FileStat>
+    where
+        F: AsRawFd,
+    {
+        self.statx_into(
+            dir,
+            path,
+            0,
+            libc::STATX_BASIC_STATS | libc::STATX_BTIME,
+            Ordering::None,
+        )
+    }
+
+    /// Issues a `statx`, decoding its result into
+    /// `C`.
+    fn statx_into<'a, F, C>(
+        &'a self,
+        dirfd: &'a F,
+        path: &'a CStr,
+        flags: i32,
+        mask: u32,
+        ordering: Ordering,
+    ) -> Completion<'a, C>
+    where
+        F: AsRawFd,
+        C: From<libc::statx>,
+    {
+        #[allow(unsafe_code)]
+        let statx: libc::statx =
+            unsafe { MaybeUninit::zeroed().assume_init() };
+
+        self.with_pinned_sqe(
+            None,
+            false,
+            Pinned::Statx(Box::new(statx)),
+            |_, pinned| {
+                if let Pinned::Statx(buf) = pinned {
+                    C::from(**buf)
+                } else {
+                    unreachable!()
+                }
+            },
+            |sqe, pinned| {
+                sqe.prep_rw(
+                    IORING_OP_STATX,
+                    dirfd.as_raw_fd(),
+                    usize::try_from(mask).unwrap(),
+                    0,
+                    ordering,
+                );
+                sqe.addr = path.as_ptr() as u64;
+                if let Pinned::Statx(buf) = pinned {
+                    let statx_ptr: *mut libc::statx =
+                        &mut **buf;
+                    sqe.off = statx_ptr as u64;
+                }
+                #[allow(clippy::cast_sign_loss)]
+                let statx_flags = flags as u32;
+                sqe.__bindgen_anon_1.statx_flags =
+                    statx_flags;
+            },
+        )
+    }
+
+    /// Gives the kernel advice about the expected
+    /// access pattern for the `len` bytes starting at
+    /// `offset` in the given file, as with
+    /// `posix_fadvise(2)`. `advice` is one of the
+    /// `libc::POSIX_FADV_*` constants, and a `len` of
+    /// 0 extends to the end of the file.
+    ///
+    /// For instance, `POSIX_FADV_DONTNEED` can be
+    /// issued behind the cursor of a large sequential
+    /// scan to keep it from filling the page cache.
+    ///
+    /// # Warning
+    ///
+    /// This only becomes usable on linux kernels
+    /// 5.6 and up. Older kernels fail the operation
+    /// with an error saying so.
+    pub fn fadvise<'a, F>(
+        &'a self,
+        file: &'a F,
+        offset: u64,
+        len: u32,
+        advice: i32,
+    ) -> Completion<'a, ()>
+    where
+        F: AsRawFd,
+    {
+        self.fadvise_ordered(
+            file,
+            offset,
+            len,
+            advice,
+            Ordering::None,
+        )
+    }
+
+    /// Gives the kernel advice about the expected
+    /// access pattern for a range of the given file,
+    /// where a `len` of 0 extends to the end of the
+    /// file.
+    ///
+    /// Accepts an `Ordering` specification.
+    ///
+    /// # Warning
+    ///
+    /// This only becomes usable on linux kernels
+    /// 5.6 and up.
+    pub fn fadvise_ordered<'a, F>(
+        &'a self,
+        file: &'a F,
+        offset: u64,
+        len: u32,
+        advice: i32,
+        ordering: Ordering,
+    ) -> Completion<'a, ()>
+    where
+        F: AsRawFd,
+    {
+        // the kernel rejects negative advice.
+        #[allow(clippy::cast_sign_loss)]
+        let raw_advice = advice as u32;
+
+        self.with_sqe(None, false, |sqe| {
+            sqe.prep_rw(
+                IORING_OP_FADVISE,
+                file.as_raw_fd(),
+                usize::try_from(len).unwrap(),
+                offset,
+                ordering,
+            );
+            sqe.__bindgen_anon_1.fadvise_advice =
+                raw_advice;
+        })
+    }
+
+    /// Manipulates the space allocated to the `len`
+    /// bytes starting at `offset` in the given file,
+    /// as with `fallocate(2)`. A `mode` of 0
+    /// preallocates the range, and the
+    /// `libc::FALLOC_FL_*` flags select other
+    /// behavior, such as `FALLOC_FL_PUNCH_HOLE |
+    /// FALLOC_FL_KEEP_SIZE` to deallocate it while
+    /// leaving the file size alone, or
+    /// `FALLOC_FL_ZERO_RANGE`.
+    ///
+    /// # Warning
+    ///
+    /// This only becomes usable on linux kernels
+    /// 5.6 and up.
+    pub fn fallocate<'a, F>(
+        &'a self,
+        file: &'a F,
+        mode: i32,
+        offset: u64,
+        len: u64,
+    ) -> Completion<'a, ()>
+    where
+        F: AsRingFd,
+    {
+        self.fallocate_ordered(
+            file,
+            mode,
+            offset,
+            len,
+            Ordering::None,
+        )
+    }
+
+    /// Manipulates the space allocated to a range of
+    /// the given file.
+    ///
+    /// Accepts an `Ordering` specification.
+    ///
+    /// # Warning
+    ///
+    /// This only becomes usable on linux kernels
+    /// 5.6 and up.
+    pub fn fallocate_ordered<'a, F>(
+        &'a self,
+        file: &'a F,
+        mode: i32,
+        offset: u64,
+        len: u64,
+        ordering: Ordering,
+    ) -> Completion<'a, ()>
+    where
+        F: AsRingFd,
+    {
+        self.with_sqe(None, false, |sqe| {
+            sqe.prep_rw_file(
+                IORING_OP_FALLOCATE,
+                file,
+                usize::try_from(mode).unwrap(),
+                offset,
+                ordering,
+            );
+            sqe.addr = len;
+        })
+    }
+
+    /// Deallocates the `len` bytes starting at
+    /// `offset` in the given file, which then read
+    /// back as zeroes, while leaving the file size
+    /// alone, as `fallocate` does with
+    /// `FALLOC_FL_PUNCH_HOLE | FALLOC_FL_KEEP_SIZE`.
+    /// Use `fallocate_ordered` with those flags to
+    /// pass an `Ordering`.
+    ///
+    /// # Warning
+    ///
+    /// This only becomes usable on linux kernels
+    /// 5.6 and up, and fails with `EOPNOTSUPP` on
+    /// filesystems that can't punch holes.
+    pub fn punch_hole<'a, F>(
+        &'a self,
+        file: &'a F,
+        offset: u64,
+        len: u64,
+    ) -> Completion<'a, ()>
+    where
+        F: AsRingFd,
+    {
+        self.fallocate(
+            file,
+            libc::FALLOC_FL_PUNCH_HOLE
+                | libc::FALLOC_FL_KEEP_SIZE,
+            offset,
+            len,
+        )
+    }
+
+    /// Zeroes the `len` bytes starting at `offset` in
+    /// the given file, keeping them allocated, as
+    /// `fallocate` does with `FALLOC_FL_ZERO_RANGE`.
+    /// This extends the file if the range ends past
+    /// its end. Use `fallocate_ordered` with that
+    /// flag to pass an `Ordering`.
+    ///
+    /// # Warning
+    ///
+    /// This only becomes usable on linux kernels
+    /// 5.6 and up, and fails with `EOPNOTSUPP` on
+    /// filesystems that can't zero ranges.
+    pub fn zero_range<'a, F>(
+        &'a self,
+        file: &'a F,
+        offset: u64,
+        len: u64,
+    ) -> Completion<'a, ()>
+    where
+        F: AsRingFd,
+    {
+        self.fallocate(
+            file,
+            libc::FALLOC_FL_ZERO_RANGE,
+            offset,
+            len,
+        )
+    }
+
+    /// Truncates or extends the given file to `len`
+    /// bytes, as with `ftruncate(2)`.
+    ///
+    /// # Warning
+    ///
+    /// This only becomes usable on linux kernels
+    /// 6.9 and up. Older kernels fail the operation
+    /// with an error saying so.
+    pub fn ftruncate<'a, F>(
+        &'a self,
+        file: &'a F,
+        len: u64,
+    ) -> Completion<'a, ()>
+    where
+        F: AsRingFd,
+    {
+        self.ftruncate_ordered(file, len, Ordering::None)
+    }
+
+    /// Truncates or extends the given file to `len`
+    /// bytes, as with `ftruncate(2)`.
+    ///
+    /// Accepts an `Ordering` specification.
+    ///
+    /// # Warning
+    ///
+    /// This only becomes usable on linux kernels
+    /// 6.9 and up. Older kernels fail the operation
+    /// with an error saying so.
+    pub fn ftruncate_ordered<'a, F>(
+        &'a self,
+        file: &'a F,
+        len: u64,
+        ordering: Ordering,
+    ) -> Completion<'a, ()>
+    where
+        F: AsRingFd,
+    {
+        self.with_sqe(None, false, |sqe| {
+            sqe.prep_rw_file(
+                IORING_OP_FTRUNCATE,
+                file,
+                0,
+                len,
+                ordering,
+            );
+        })
+    }
+
+    /// Gives the kernel advice about the expected
+    /// access pattern for the `len` bytes of memory
+    /// starting at `addr`, as with `madvise(2)`.
+    /// `advice` is one of the `libc::MADV_*` constants.
+    ///
+    /// # Safety
+    ///
+    /// Some kinds of advice, like `MADV_DONTNEED`,
+    /// change the contents of the memory they are
+    /// given, so the same care must be taken as
+    /// when calling `libc::madvise` directly.
+    ///
+    /// # Warning
+    ///
+    /// This only becomes usable on linux kernels
+    /// 5.6 and up. Older kernels fail the operation
+    /// with an error saying so.
+    #[allow(unsafe_code)]
+    pub unsafe fn madvise<'a>(
+        &'a self,
+        addr: *mut libc::c_void,
+        len: usize,
+        advice: i32,
+    ) -> Completion<'a, ()> {
+        // the kernel rejects negative advice.
+        #[allow(clippy::cast_sign_loss)]
+        let raw_advice = advice as u32;
+
+        self.with_sqe(None, false, |sqe| {
+            sqe.prep_rw(
+                IORING_OP_MADVISE,
+                -1,
+                len,
+                0,
+                Ordering::None,
+            );
+            sqe.addr = addr as u64;
+            sqe.__bindgen_anon_1.fadvise_advice =
+                raw_advice;
+        })
+    }
+
+    /// Moves up to `len` bytes from `fd_in` to
+    /// `fd_out` without copying them through
+    /// userspace, as with `splice(2)`. One of the
+    /// two must be a pipe. An offset of `-1` means
+    /// to use (and advance) the file's current
+    /// position, and must be used for pipes.
+    /// `flags` takes the `libc::SPLICE_F_*` flags.
+    /// Either side may also be a `FixedFile` index.
+    ///
+    /// Returns the number of bytes moved.
+    ///
+    /// # Warning
+    ///
+    /// This only becomes usable on linux kernels
+    /// 5.7 and up.
+    pub fn splice<'a, F1, F2>(
+        &'a self,
+        fd_in: &'a F1,
+        off_in: i64,
+        fd_out: &'a F2,
+        off_out: i64,
+        len: u32,
+        flags: u32,
+    ) -> Completion<'a, usize>
+    where
+        F1: AsRingFd,
+        F2: AsRingFd,
+    {
+        self.splice_ordered(
+            fd_in,
+            off_in,
+            fd_out,
+            off_out,
+            len,
+            flags,
+            Ordering::None,
+        )
+    }
+
+    /// Moves up to `len` bytes from `fd_in` to
+    /// `fd_out` without copying them through
+    /// userspace.
+    ///
+    /// Accepts an `Ordering` specification, so that
+    /// for instance a socket to pipe splice may be
+    /// linked to a following pipe to file splice.
+    /// Note that a splice which moves fewer than `len`
+    /// bytes breaks the chain, failing the operations
+    /// linked after it with `ECANCELED`.
+    ///
+    /// # Warning
+    ///
+    /// This only becomes usable on linux kernels
+    /// 5.7 and up.
+    #[allow(clippy::too_many_arguments)]
+    pub fn splice_ordered<'a, F1, F2>(
+        &'a self,
+        fd_in: &'a F1,
+        off_in: i64,
+        fd_out: &'a F2,
+        off_out: i64,
+        len: u32,
+        flags: u32,
+        ordering: Ordering,
+    ) -> Completion<'a, usize>
+    where
+        F1: AsRingFd,
+        F2: AsRingFd,
+    {
+        self.with_sqe(None, false, |sqe| {
+            // -1 wraps around to the kernel's
+            // "no offset" sentinel
+            #[allow(clippy::cast_sign_loss)]
+            let (raw_off_in, raw_off_out) =
+                (off_in as u64, off_out as u64);
+            sqe.prep_rw_file(
+                IORING_OP_SPLICE,
+                fd_out,
+                usize::try_from(len).unwrap(),
+                raw_off_out,
+                ordering,
+            );
+            sqe.addr = raw_off_in;
+            sqe.set_splice_fd_in(fd_in, flags);
+        })
+    }
+
+    /// Duplicates up to `len` bytes from the pipe
+    /// `fd_in` into the pipe `fd_out` without
+    /// consuming them from `fd_in`, as with `tee(2)`.
+    /// `flags` takes the `libc::SPLICE_F_*` flags.
+    /// Either side may also be a `FixedFile` index.
+    ///
+    /// Returns the number of bytes duplicated.
+    ///
+    /// # Warning
+    ///
+    /// This only becomes usable on linux kernels
+    /// 5.8 and up.
+    pub fn tee<'a, F1, F2>(
+        &'a self,
+        fd_in: &'a F1,
+        fd_out: &'a F2,
+        len: u32,
+        flags: u32,
+    ) -> Completion<'a, usize>
+    where
+        F1: AsRingFd,
+        F2: AsRingFd,
+    {
+        self.tee_ordered(
+            fd_in,
+            fd_out,
+            len,
+            flags,
+            Ordering::None,
+        )
+    }
+
+    /// Duplicates up to `len` bytes from the pipe
+    /// `fd_in` into the pipe `fd_out` without
+    /// consuming them from `fd_in`.
+    ///
+    /// Accepts an `Ordering` specification.
+    ///
+    /// # Warning
+    ///
+    /// This only becomes usable on linux kernels
+    /// 5.8 and up.
+    pub fn tee_ordered<'a, F1, F2>(
+        &'a self,
+        fd_in: &'a F1,
+        fd_out: &'a F2,
+        len: u32,
+        flags: u32,
+        ordering: Ordering,
+    ) -> Completion<'a, usize>
+    where
+        F1: AsRingFd,
+        F2: AsRingFd,
+    {
+        self.with_sqe(None, false, |sqe| {
+            sqe.prep_rw_file(
+                IORING_OP_TEE,
+                fd_out,
+                usize::try_from(len).unwrap(),
+                0,
+                ordering,
+            );
+            sqe.set_splice_fd_in(fd_in, flags);
+        })
+    }
+
+    /// Sends up to `len` bytes of `file`, starting at
+    /// `offset`, to `socket` without copying them
+    /// through userspace, like `sendfile(2)`. The
+    /// bytes are spliced from the file into a pipe,
+    /// and from there into the socket by a splice
+    /// that is linked to the first one. The pipe is
+    /// kept for later calls once it was drained.
+    ///
+    /// Returns the number of bytes sent, which may be
+    /// fewer than `len`: a call moves no more than
+    /// fits into the pipe, usually 1 MiB, nor more
+    /// than the file has past `offset`, and the
+    /// socket may take fewer. The rest can be sent by
+    /// calling this again at the offset past the sent
+    /// bytes, and a call at or past the end of the
+    /// file sends nothing. If reading from the file
+    /// fails, or comes up short because it is not a
+    /// regular file, this fails with `ECANCELED`,
+    /// since the send is linked to it. Creating
+    /// a pipe may fail when the process is out of
+    /// file descriptors, which the returned
+    /// `Completion` then fails with.
+    ///
+    /// # Warning
+    ///
+    /// This only becomes usable on linux kernels
+    /// 5.7 and up.
+    pub fn sendfile<'a, F, S>(
+        &'a self,
+        file: &'a F,
+        socket: &'a S,
+        offset: u64,
+        len: usize,
+    ) -> Completion<'a, usize>
+    where
+        F: AsRawFd,
+        S: AsRawFd,
+    {
+        let lease = match PipeLease::take(&self.pipes) {
+            Ok(lease) => lease,
+            Err(e) => return failed(self, e),
+        };
+        let (pipe_read, pipe_write) =
+            (lease.pipe().read, lease.pipe().write);
+        let chunk = len
+            .min(lease.pipe().capacity)
+            .min(remaining_after(file, offset));
+        let (file_fd, socket_fd) =
+            (file.as_raw_fd(), socket.as_raw_fd());
+
+        let fill_ticket = self.take_ticket();
+        let send_ticket = self.take_ticket();
+
+        // the two splices must sit next to each other
+        // in the queue for the link to join them.
+        let mut sq = self.lock_sq();
+        let _hold_sq_mu = Measure::new(&M.sq_mu_hold);
+        if let Err(e) = self.make_room(&mut sq, 2) {
+            drop(sq);
+            self.ticket_queue
+                .push_multi(vec![fill_ticket, send_ticket]);
+            return failed(self, e);
+        }
+
+        let (mut fill, fill_filler) =
+            pair(self, Pinned::None, |_, _| ());
+        let (mut send, send_filler) =
+            pair(self, Pinned::Pipe(lease), |cqe, _| {
+                usize::from_cqe(cqe)
+            });
+
+        let (fill_sqe_id, fill_user_data) = self.fill_sqe(
+            &mut sq,
+            2,
+            fill_ticket,
+            None,
+            false,
+            fill_filler,
+            |sqe| {
+                sqe.prep_rw(
+                    IORING_OP_SPLICE,
+                    pipe_write,
+                    chunk,
+                    CURRENT_POSITION,
+                    Ordering::Link,
+                );
+                sqe.addr = offset;
+                sqe.__bindgen_anon_2.splice_fd_in = file_fd;
+            },
+        );
+        fill.sqe_id = fill_sqe_id;
+        fill.user_data = fill_user_data;
+
+        let (send_sqe_id, send_user_data) = self.fill_sqe(
+            &mut sq,
+            1,
+            send_ticket,
+            None,
+            false,
+            send_filler,
+            |sqe| {
+                sqe.prep_rw(
+                    IORING_OP_SPLICE,
+                    socket_fd,
+                    chunk,
+                    CURRENT_POSITION,
+                    Ordering::None,
+                );
+                sqe.addr = CURRENT_POSITION;
+                sqe.__bindgen_anon_2.splice_fd_in =
+                    pipe_read;
+            },
+        );
+        send.sqe_id = send_sqe_id;
+        send.user_data = send_user_data;
+        send.linked.push(fill);
+
+        send
+    }
+
+    /// Flushes all buffered writes, and associated
+    /// metadata changes.
+    ///
+    /// # Warning
+    ///
+    /// You usually don't want to do this without
+    /// linking to a previous write, because
+    /// `io_uring` will execute operations out-of-order.
+    /// Without setting a `Link` ordering on the previous
+    /// operation, or using `fsync_ordered` with
+    /// the `Drain` ordering, causing all previous
+    /// operations to complete before itself.
+    ///
+    /// Additionally, fsync does not ensure that
+    /// the file actually exists in its parent
+    /// directory. So, for new files, you must
+    /// also fsync the parent directory.
+    ///
+    /// This does nothing for files opened in
+    /// `O_DIRECT` mode.
+    pub fn fsync<'a>(
+        &'a self,
+        file: &'a File,
+    ) -> Completion<'a, ()> {
+        self.fsync_ordered(file, Ordering::None)
+    }
+
+    /// Flushes all buffered writes, and associated
+    /// metadata changes.
+    ///
+    /// You probably want to
+    /// either use a `Link` ordering on a previous
+    /// write (or chain of separate writes), or
+    /// use the `Drain` ordering on this operation.
+    ///
+    /// You may pass in an `Ordering` to specify
+    /// two different optional behaviors:
+    ///
+    /// * `Ordering::Link` causes the next
+    ///   submitted operation to wait until
+    ///   this one finishes. Useful for
+    ///   things like file copy, fsync-after-write,
+    ///   or proxies.
+    /// * `Ordering::Drain` causes all previously
+    ///   submitted operations to complete before
+    ///   this one begins.
+    ///
+    /// # Warning
+    ///
+    /// fsync does not ensure that
+    /// the file actually exists in its parent
+    /// directory. So, for new files, you must
+    /// also fsync the parent directory.
+    /// This does nothing for files opened in
+    /// `O_DIRECT` mode.
+    pub fn fsync_ordered<'a>(
+        &'a self,
+        file: &'a File,
+        ordering: Ordering,
+    ) -> Completion<'a, ()> {
+        self.fsync_with_ordered(file, false, ordering)
+    }
+
+    /// Flushes all buffered writes, along with either
+    /// all associated metadata changes, like `fsync`,
+    /// or with `datasync` only the metadata required
+    /// to access the data, like `fdatasync`, so that
+    /// this can be decided at runtime.
+    ///
+    /// # Warning
+    ///
+    /// This does not ensure that the file actually
+    /// exists in its parent directory. So, for new
+    /// files, you must also fsync the parent
+    /// directory. This does nothing for files opened
+    /// in `O_DIRECT` mode.
+    pub fn fsync_with<'a>(
+        &'a self,
+        file: &'a File,
+        datasync: bool,
+    ) -> Completion<'a, ()> {
+        self.fsync_with_ordered(
+            file,
+            datasync,
+            Ordering::None,
+        )
+    }
+
+    /// Flushes all buffered writes, along with either
+    /// all associated metadata changes, or with
+    /// `datasync` only the metadata required to
+    /// access the data.
+    ///
+    /// Accepts an `Ordering` specification.
+    ///
+    /// # Warning
+    ///
+    /// This does not ensure that the file actually
+    /// exists in its parent directory. So, for new
+    /// files, you must also fsync the parent
+    /// directory. This does nothing for files opened
+    /// in `O_DIRECT` mode.
+    pub fn fsync_with_ordered<'a>(
+        &'a self,
+        file: &'a File,
+        datasync: bool,
+        ordering: Ordering,
+    ) -> Completion<'a, ()> {
+        self.with_sqe(None, false, |sqe| {
+            sqe.prep_rw(
+                IORING_OP_FSYNC,
+                file.as_raw_fd(),
+                0,
+                0,
+                ordering,
+            );
+            if datasync {
+                sqe.__bindgen_anon_1.fsync_flags =
+                    IORING_FSYNC_DATASYNC;
+            }
+        })
+    }
+
+    /// Flushes all buffered writes, and the specific
+    /// metadata required to access the data. This
+    /// will skip syncing metadata like atime.
+    ///
+    /// You probably want to
+    /// either use a `Link` ordering on a previous
+    /// write (or chain of separate writes), or
+    /// use the `Drain` ordering on this operation
+    /// with the `fdatasync_ordered` method.
+    ///
+    /// # Warning
+    ///
+    /// fdatasync does not ensure that
+    /// the file actually exists in its parent
+    /// directory. So, for new files, you must
+    /// also fsync the parent directory.
+    /// This does nothing for files opened in
+    /// `O_DIRECT` mode.
+    pub fn fdatasync<'a>(
+        &'a self,
+        file: &'a File,
+    ) -> Completion<'a, ()> {
+        self.fdatasync_ordered(file, Ordering::None)
+    }
+
+    /// Flushes all buffered writes, and the specific
+    /// metadata required to access the data. This
+    /// will skip syncing metadata like atime.
+    ///
+    /// You probably want to
+    /// either use a `Link` ordering on a previous
+    /// write (or chain of separate writes), or
+    /// use the `Drain` ordering on this operation.
+    ///
+    /// You may pass in an `Ordering` to specify
+    /// two different optional behaviors:
+    ///
+    /// * `Ordering::Link` causes the next
+    ///   submitted operation to wait until
+    ///   this one finishes. Useful for
+    ///   things like file copy, fsync-after-write,
+    ///   or proxies.
+    /// * `Ordering::Drain` causes all previously
+    ///   submitted operations to complete before
+    ///   this one begins.
+    ///
+    /// # Warning
+    ///
+    /// fdatasync does not ensure that
+    /// the file actually exists in its parent
+    /// directory. So, for new files, you must
+    /// also fsync the parent directory.
+    /// This does nothing for files opened in
+    /// `O_DIRECT` mode.
+    pub fn fdatasync_ordered<'a>(
+        &'a self,
+        file: &'a File,
+        ordering: Ordering,
+    ) -> Completion<'a, ()> {
+        self.fsync_with_ordered(file, true, ordering)
+    }
+
+    /// Synchronizes the data associated with a range
+    /// in a file, as with `sync_file_range(2)`. Does
+    /// not synchronize any metadata updates, which
+    /// can cause data loss if you are not writing to
+    /// a file whose metadata has previously been
+    /// synchronized.
+    ///
+    /// `flags` is a combination of
+    /// `libc::SYNC_FILE_RANGE_WAIT_BEFORE`,
+    /// `SYNC_FILE_RANGE_WRITE`, and
+    /// `SYNC_FILE_RANGE_WAIT_AFTER`, with the same
+    /// meaning as for the syscall. Writing out a range
+    /// and waiting for it to reach the device takes
+    /// `SYNC_FILE_RANGE_WRITE |
+    /// SYNC_FILE_RANGE_WAIT_AFTER`, and a `flags` of
+    /// 0 does nothing at all. A `len` of 0 extends
+    /// to the end of the file.
+    ///
+    /// You probably want to have a prior write
+    /// linked to this, or set `Ordering::Drain`
+    /// by using `sync_file_range_ordered` instead.
+    pub fn sync_file_range<'a>(
+        &'a self,
+        file: &'a File,
+        offset: u64,
+        len: u32,
+        flags: u32,
+    ) -> Completion<'a, ()> {
+        self.sync_file_range_ordered(
+            file,
+            offset,
+            len,
+            flags,
+            Ordering::None,
+        )
+    }
+
+    /// Synchronizes the data associated with a range
+    /// in a file. Does not synchronize any metadata
+    /// updates, which can cause data loss if you
+    /// are not writing to a file whose metadata
+    /// has previously been synchronized.
+    ///
+    /// You probably want to have a prior write
+    /// linked to this, or set `Ordering::Drain`.
+    ///
+    /// Accepts an `Ordering` specification.
+    pub fn sync_file_range_ordered<'a>(
+        &'a self,
+        file: &'a File,
+        offset: u64,
+        len: u32,
+        flags: u32,
+        ordering: Ordering,
+    ) -> Completion<'a, ()> {
+        self.with_sqe(None, false, |sqe| {
+            sqe.prep_rw(
+                IORING_OP_SYNC_FILE_RANGE,
+                file.as_raw_fd(),
+                usize::try_from(len).unwrap(),
+                offset,
+                ordering,
+            );
+            sqe.__bindgen_anon_1.sync_range_flags = flags;
+        })
+    }
+
+    /// Writes data at the provided buffer using
+    /// vectored IO. Be sure to check the returned
+    /// `io_uring_cqe`'s `res` field to see if a
+    /// short write happened. This will contain
+    /// the number of bytes written.
+    ///
+    /// Pass `rio::CURRENT_POSITION` as `at` to write
+    /// at the file's own position, such as for pipes.
+    ///
+    /// Note that the file argument is generic
+    /// for anything that supports AsRawFd:
+    /// sockets, files, etc... as well as
+    /// `FixedFile` indices into the table set
+    /// up by `register_files`.
+    pub fn write_at<'a, F, B>(
+        &'a self,
+        file: &'a F,
+        iov: &'a B,
+        at: u64,
+    ) -> Completion<'a, usize>
+    where
+        F: AsRingFd,
+        B: 'a + AsIoVec,
+    {
+        self.write_at_ordered(file, iov, at, Ordering::None)
+    }
+
+    /// Writes data at the provided buffer using
+    /// vectored IO.
+    ///
+    /// Be sure to check the returned
+    /// `io_uring_cqe`'s `res` field to see if a
+    /// short write happened. This will contain
+    /// the number of bytes written.
+    ///
+    /// You may pass in an `Ordering` to specify
+    /// two different optional behaviors:
+    ///
+    /// * `Ordering::Link` causes the next
+    ///   submitted operation to wait until
+    ///   this one finishes. Useful for
+    ///   things like file copy, fsync-after-write,
+    ///   or proxies.
+    /// * `Ordering::Drain` causes all previously
+    ///   submitted operations to complete before
+    ///   this one begins.
+    ///
+    /// Note that the file argument is generic
+    /// for anything that supports AsRawFd:
+    /// sockets, files, etc... as well as
+    /// `FixedFile` indices into the table set
+    /// up by `register_files`.
+    pub fn write_at_ordered<'a, F, B>(
+        &'a self,
+        file: &'a F,
+        iov: &'a B,
+        at: u64,
+        ordering: Ordering,
+    ) -> Completion<'a, usize>
+    where
+        F: AsRingFd,
+        B: 'a + AsIoVec,
+    {
+        self.write_at_with_flags(file, iov, at, 0, ordering)
+    }
+
+    /// Like `write_at_ordered`, but passes `rw_flags`
+    /// along as the `RWF_*` flags of `pwritev2(2)`,
+    /// such as `RWF_DSYNC` to make just this write
+    /// durable, or `RWF_NOWAIT`, with which a write
+    /// that would block fails with `EAGAIN`, which is
+    /// `io::ErrorKind::WouldBlock`, instead.
+    ///
+    /// Kernels that support `IORING_OP_WRITE`, from
+    /// linux 5.6 on, are handed the buffer directly,
+    /// and older ones an iovec that points to it.
+    /// Either way, a buffer over 4 GiB is written
+    /// short.
+    pub fn write_at_with_flags<'a, F, B>(
+        &'a self,
+        file: &'a F,
+        iov: &'a B,
+        at: u64,
+        rw_flags: i32,
+        ordering: Ordering,
+    ) -> Completion<'a, usize>
+    where
+        F: AsRingFd,
+        B: 'a + AsIoVec,
+    {
+        self.track_write(file);
+        let iovec = iov.into_new_iovec();
+        if !self.probe.is_supported(IORING_OP_WRITE) {
+            return self.with_sqe(
+                Some(iovec),
+                false,
+                |sqe| {
+                    sqe.prep_rw_file(
+                        IORING_OP_WRITEV,
+                        file,
+                        1,
+                        at,
+                        ordering,
+                    );
+                    sqe.__bindgen_anon_1.rw_flags =
+                        rw_flags;
+                },
+            );
+        }
+
+        // the buffer is passed directly, sparing the
+        // kernel from reading an iovec.
+        self.with_sqe(None, false, |sqe| {
+            sqe.prep_rw_file(
+                IORING_OP_WRITE,
+                file,
+                single_op_len(iovec.iov_len),
+                at,
+                ordering,
+            );
+            sqe.addr = iovec.iov_base as u64;
+            sqe.__bindgen_anon_1.rw_flags = rw_flags;
+        })
+    }
+
+    /// Writes all of `buf` to the file at the given
+    /// offset, following up short writes with the
+    /// rest of the buffer at the offset just past
+    /// the written part.
+    ///
+    /// Once this returns `Ok`, all of `buf` has been
+    /// handed to the kernel, which for buffered files
+    /// means it is in the page cache, but not that it
+    /// survives a crash. Follow it up with `fsync` or
+    /// `fdatasync` for that. A write that makes no
+    /// progress fails with `io::ErrorKind::WriteZero`,
+    /// and when a write fails, some part of `buf` may
+    /// already have been written.
+    pub fn write_all<F>(
+        &self,
+        file: &F,
+        buf: &[u8],
+        at: u64,
+    ) -> io::Result<()>
+    where
+        F: AsRingFd,
+    {
+        let mut written = 0;
+        while written < buf.len() {
+            let rest = &buf[written..];
+            let res = self
+                .write_at(
+                    file,
+                    &rest,
+                    offset_after(at, written),
+                )
+                .wait();
+            written += wrote_some(res)?;
+        }
+        Ok(())
+    }
+
+    /// Like `write_all`, but awaits each write
+    /// instead of blocking on it.
+    pub async fn write_all_async<F>(
+        &self,
+        file: &F,
+        buf: &[u8],
+        at: u64,
+    ) -> io::Result<()>
+    where
+        F: AsRingFd,
+    {
+        let mut written = 0;
+        while written < buf.len() {
+            let rest = &buf[written..];
+            let res = self
+                .write_at(
+                    file,
+                    &rest,
+                    offset_after(at, written),
+                )
+                .await;
+            written += wrote_some(res)?;
+        }
+        Ok(())
+    }
+
+    /// Reads data into the provided buffer from the
+    /// given file-like object, at the given offest,
+    /// using vectored IO. Be sure to check the returned
+    /// `io_uring_cqe`'s `res` field to see if a
+    /// short read happened. This will contain
+    /// the number of bytes read.
+    ///
+    /// Pass `rio::CURRENT_POSITION` as `at` to read
+    /// at the file's own position, such as for pipes.
+    ///
+    /// Note that the file argument is generic
+    /// for anything that supports AsRawFd:
+    /// sockets, files, etc... as well as
+    /// `FixedFile` indices into the table set
+    /// up by `register_files`.
+    pub fn read_at<'a, F, B>(
+        &'a self,
+        file: &'a F,
+        iov: &'a B,
+        at: u64,
+    ) -> Completion<'a, usize>
+    where
+        F: AsRingFd,
+        B: AsIoVec + AsIoVecMut,
+    {
+        self.read_at_ordered(file, iov, at, Ordering::None)
+    }
+
+    /// Reads data into the provided buffer using
+    /// vectored IO. Be sure to check the returned
+    /// `io_uring_cqe`'s `res` field to see if a
+    /// short read happened. This will contain
+    /// the number of bytes read.
+    ///
+    /// You may pass in an `Ordering` to specify
+    /// two different optional behaviors:
+    ///
+    /// * `Ordering::Link` causes the next
+    ///   submitted operation to wait until
+    ///   this one finishes. Useful for
+    ///   things like file copy, fsync-after-write,
+    ///   or proxies.
+    /// * `Ordering::Drain` causes all previously
+    ///   submitted operations to complete before
+    ///   this one begins.
+    ///
+    /// Note that the file argument is generic
+    /// for anything that supports AsRawFd:
+    /// sockets, files, etc... as well as
+    /// `FixedFile` indices into the table set
+    /// up by `register_files`.
+    pub fn read_at_ordered<'a, F, B>(
+        &'a self,
+        file: &'a F,
+        iov: &'a B,
+        at: u64,
+        ordering: Ordering,
+    ) -> Completion<'a, usize>
+    where
+        F: AsRingFd,
+        B: AsIoVec + AsIoVecMut,
+    {
+        self.read_at_with_flags(file, iov, at, 0, ordering)
+    }
+
+    /// Like `read_at_ordered`, but passes `rw_flags`
+    /// along as the `RWF_*` flags of `preadv2(2)`,
+    /// such as `RWF_HIPRI` for polled reads, or
+    /// `RWF_NOWAIT`, with which a read that would
+    /// block, like one that misses the page cache,
+    /// fails with `EAGAIN`, which is
+    /// `io::ErrorKind::WouldBlock`, instead.
+    ///
+    /// Kernels that support `IORING_OP_READ`, from
+    /// linux 5.6 on, are handed the buffer directly,
+    /// and older ones an iovec that points to it.
+    /// Either way, a buffer over 4 GiB is read
+    /// short.
+    pub fn read_at_with_flags<'a, F, B>(
+        &'a self,
+        file: &'a F,
+        iov: &'a B,
+        at: u64,
+        rw_flags: i32,
+        ordering: Ordering,
+    ) -> Completion<'a, usize>
+    where
+        F: AsRingFd,
+        B: AsIoVec + AsIoVecMut,
+    {
+        self.read_iovec_at(
+            file,
+            iov.into_new_iovec(),
+            at,
+            rw_flags,
+            ordering,
+        )
+    }
+
+    /// Reads data into the provided uninitialized
+    /// buffer from the given file-like object, at the
+    /// given offset, sparing large buffers from being
+    /// zeroed before every read.
+    ///
+    /// Returns the number of bytes read, and once it
+    /// has, the kernel has initialized `buf[..count]`
+    /// with them, so that prefix may be treated as
+    /// initialized with `MaybeUninit::assume_init`,
+    /// while the rest of `buf` is left as it was.
+    /// Like `read_at`, this also takes `FixedFile`
+    /// indices.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::mem::MaybeUninit;
+    ///
+    /// let ring = rio::new().unwrap();
+    /// let file = std::fs::File::open("file").unwrap();
+    ///
+    /// let mut buf = vec![MaybeUninit::<u8>::uninit(); 4096];
+    /// let count =
+    ///     ring.read_at_uninit(&file, &mut buf, 0).wait().unwrap();
+    ///
+    /// // the kernel wrote exactly `count` bytes.
+    /// let read: Vec<u8> = buf[..count]
+    ///     .iter()
+    ///     .map(|byte| unsafe { byte.assume_init() })
+    ///     .collect();
+    /// ```
+    pub fn read_at_uninit<'a, F>(
+        &'a self,
+        file: &'a F,
+        buf: &'a mut [MaybeUninit<u8>],
+        at: u64,
+    ) -> Completion<'a, usize>
+    where
+        F: AsRingFd,
+    {
+        let base: *mut MaybeUninit<u8> = buf.as_mut_ptr();
+        let iovec = libc::iovec {
+            iov_base: base.cast(),
+            iov_len: buf.len(),
+        };
+        self.read_iovec_at(
+            file,
+            iovec,
+            at,
+            0,
+            Ordering::None,
+        )
+    }
+
+    /// Reads into the memory `iovec` points to, whose
+    /// borrow the public callers tie to the returned
+    /// `Completion`.
+    fn read_iovec_at<'a, F>(
+        &'a self,
+        file: &'a F,
+        iovec: libc::iovec,
+        at: u64,
+        rw_flags: i32,
+        ordering: Ordering,
+    ) -> Completion<'a, usize>
+    where
+        F: AsRingFd,
+    {
+        if !self.probe.is_supported(IORING_OP_READ) {
+            return self.with_sqe(
+                Some(iovec),
+                false,
+                |sqe| {
+                    sqe.prep_rw_file(
+                        IORING_OP_READV,
+                        file,
+                        1,
+                        at,
+                        ordering,
+                    );
+                    sqe.__bindgen_anon_1.rw_flags =
+                        rw_flags;
+                },
+            );
+        }
+
+        // the buffer is passed directly, sparing the
+        // kernel from reading an iovec.
+        self.with_sqe(None, false, |sqe| {
+            sqe.prep_rw_file(
+                IORING_OP_READ,
+                file,
+                single_op_len(iovec.iov_len),
+                at,
+                ordering,
+            );
+            sqe.addr = iovec.iov_base as u64;
+            sqe.__bindgen_anon_1.rw_flags = rw_flags;
+        })
+    }
+
+    /// Reads the given file from its current position
+    /// until the kernel reports its end, appending
+    /// everything to `buf` and advancing the position,
+    /// like `std::io::Read::read_to_end`.
+    /// Short reads are followed up by reading the
+    /// rest, so this suits pipes and sockets as well.
+    ///
+    /// Returns the number of bytes that were read. If
+    /// a read fails, its error is returned, and `buf`
+    /// keeps the data that was read before it.
+    pub fn read_to_end<F>(
+        &self,
+        file: &F,
+        buf: &mut Vec<u8>,
+    ) -> io::Result<usize>
+    where
+        F: AsRingFd,
+    {
+        let start = buf.len();
+        loop {
+            let filled = grow_for_read(buf);
+            let res = {
+                let spare: &mut [u8] = &mut buf[filled..];
+                self.read_at(file, &spare, CURRENT_POSITION)
+                    .wait()
+            };
+            if let Some(done) =
+                finish_read(buf, start, filled, res)
+            {
+                return done;
+            }
+        }
+    }
+
+    /// Like `read_to_end`, but awaits each read
+    /// instead of blocking on it.
+    pub async fn read_to_end_async<F>(
+        &self,
+        file: &F,
+        buf: &mut Vec<u8>,
+    ) -> io::Result<usize>
+    where
+        F: AsRingFd,
+    {
+        let start = buf.len();
+        loop {
+            let filled = grow_for_read(buf);
+            let res = {
+                let spare: &mut [u8] = &mut buf[filled..];
+                self.read_at(file, &spare, CURRENT_POSITION)
+                    .await
+            };
+            if let Some(done) =
+                finish_read(buf, start, filled, res)
+            {
+                return done;
+            }
+        }
+    }
+
+    /// Fills all of `buf` from the file at the given
+    /// offset, following up short reads with reads
+    /// into the rest of the buffer at the offset just
+    /// past the filled part.
+    ///
+    /// If the file ends before `buf` is filled, this
+    /// fails with `io::ErrorKind::UnexpectedEof`. The
+    /// contents of `buf` are unspecified when it
+    /// fails.
+    pub fn read_exact<F>(
+        &self,
+        file: &F,
+        buf: &mut [u8],
+        at: u64,
+    ) -> io::Result<()>
+    where
+        F: AsRingFd,
+    {
+        let mut filled = 0;
+        while filled < buf.len() {
+            let rest: &mut [u8] = &mut buf[filled..];
+            let res = self
+                .read_at(
+                    file,
+                    &rest,
+                    offset_after(at, filled),
+                )
+                .wait();
+            filled += read_some(res)?;
+        }
+        Ok(())
+    }
+
+    /// Like `read_exact`, but awaits each read
+    /// instead of blocking on it.
+    pub async fn read_exact_async<F>(
+        &self,
+        file: &F,
+        buf: &mut [u8],
+        at: u64,
+    ) -> io::Result<()>
+    where
+        F: AsRingFd,
+    {
+        let mut filled = 0;
+        while filled < buf.len() {
+            let rest: &mut [u8] = &mut buf[filled..];
+            let res = self
+                .read_at(
+                    file,
+                    &rest,
+                    offset_after(at, filled),
+                )
+                .await;
+            filled += read_some(res)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the buffers in `bufs`, in order, to the
+    /// file-like object at the given offset, as one
+    /// vectored write. Be sure to check the returned
+    /// count of bytes written, which may be short.
+    ///
+    /// The `bufs` array itself is borrowed for the
+    /// lifetime of the `Completion`, since the kernel
+    /// may read it after submission.
+    pub fn writev<'a, 'b, F>(
+        &'a self,
+        file: &'a F,
+        bufs: &'a [IoSlice<'b>],
+        at: u64,
+    ) -> Completion<'a, usize>
+    where
+        F: AsRingFd,
+    {
+        self.writev_ordered(file, bufs, at, Ordering::None)
+    }
+
+    /// Writes the buffers in `bufs`, in order, to the
+    /// file-like object at the given offset, as one
+    /// vectored write. Be sure to check the returned
+    /// count of bytes written, which may be short.
+    ///
+    /// The `bufs` array itself is borrowed for the
+    /// lifetime of the `Completion`, since the kernel
+    /// may read it after submission.
+    ///
+    /// Accepts an `Ordering` specification.
+    pub fn writev_ordered<'a, 'b, F>(
+        &'a self,
+        file: &'a F,
+        bufs: &'a [IoSlice<'b>],
+        at: u64,
+        ordering: Ordering,
+    ) -> Completion<'a, usize>
+    where
+        F: AsRingFd,
+    {
+        self.track_write(file);
+        self.with_sqe(None, false, |sqe| {
+            sqe.prep_rw_file(
+                IORING_OP_WRITEV,
+                file,
+                bufs.len(),
+                at,
+                ordering,
+            );
+            sqe.addr = bufs.as_ptr() as u64;
+        })
+    }
+
+    /// Writes the `chunks`, in order, to the
+    /// file-like object at the given offset, as one
+    /// vectored write, like `writev`, but builds the
+    /// array of `IoSlice`s itself, and keeps it with
+    /// the `Completion`. Be sure to check the
+    /// returned count of bytes written, which may be
+    /// short.
+    ///
+    /// The chunks are borrowed for the lifetime of
+    /// the `Completion`, while the slice holding them
+    /// may go away right after this returns.
+    pub fn writev_slices<'a, F>(
+        &'a self,
+        file: &'a F,
+        chunks: &[&'a [u8]],
+        at: u64,
+    ) -> Completion<'a, usize>
+    where
+        F: AsRingFd,
+    {
+        self.writev_slices_ordered(
+            file,
+            chunks,
+            at,
+            Ordering::None,
+        )
+    }
+
+    /// Writes the `chunks`, in order, to the
+    /// file-like object at the given offset, as one
+    /// vectored write, building the array of
+    /// `IoSlice`s itself.
+    ///
+    /// Accepts an `Ordering` specification.
+    pub fn writev_slices_ordered<'a, F>(
+        &'a self,
+        file: &'a F,
+        chunks: &[&'a [u8]],
+        at: u64,
+        ordering: Ordering,
+    ) -> Completion<'a, usize>
+    where
+        F: AsRingFd,
+    {
+        // the chunks outlive the `Completion`, which
+        // the iovecs can't escape from.
+        let iovecs: Vec<IoSlice<'static>> = chunks
+            .iter()
+            .map(|chunk| {
+                #[allow(unsafe_code)]
+                IoSlice::new(unsafe {
+                    std::slice::from_raw_parts(
+                        chunk.as_ptr(),
+                        chunk.len(),
+                    )
+                })
+            })
+            .collect();
+
+        self.track_write(file);
+        self.with_pinned_sqe(
+            None,
+            false,
+            Pinned::Iovecs(iovecs),
+            |cqe, _| usize::from_cqe(cqe),
+            |sqe, pinned| {
+                if let Pinned::Iovecs(pinned_iovecs) =
+                    pinned
+                {
+                    sqe.prep_rw_file(
+                        IORING_OP_WRITEV,
+                        file,
+                        pinned_iovecs.len(),
+                        at,
+                        ordering,
+                    );
+                    sqe.addr =
+                        pinned_iovecs.as_ptr() as u64;
+                }
+            },
+        )
+    }
+
+    /// Reads from the file-like object at the given
+    /// offset into the buffers in `bufs`, filling
+    /// each in order, as one vectored read. Be sure
+    /// to check the returned count of bytes read,
+    /// which may be short.
+    ///
+    /// The `bufs` array itself is borrowed for the
+    /// lifetime of the `Completion`, since the kernel
+    /// may read it after submission.
+    pub fn readv<'a, 'b, F>(
+        &'a self,
+        file: &'a F,
+        bufs: &'a mut [IoSliceMut<'b>],
+        at: u64,
+    ) -> Completion<'a, usize>
+    where
+        F: AsRingFd,
+    {
+        self.readv_ordered(file, bufs, at, Ordering::None)
+    }
+
+    /// Reads from the file-like object at the given
+    /// offset into the buffers in `bufs`, filling
+    /// each in order, as one vectored read. Be sure
+    /// to check the returned count of bytes read,
+    /// which may be short.
+    ///
+    /// The `bufs` array itself is borrowed for the
+    /// lifetime of the `Completion`, since the kernel
+    /// may read it after submission.
+    ///
+    /// Accepts an `Ordering` specification.
+    pub fn readv_ordered<'a, 'b, F>(
+        &'a self,
+        file: &'a F,
+        bufs: &'a mut [IoSliceMut<'b>],
+        at: u64,
+        ordering: Ordering,
+    ) -> Completion<'a, usize>
+    where
+        F: AsRingFd,
+    {
+        self.with_sqe(None, false, |sqe| {
+            sqe.prep_rw_file(
+                IORING_OP_READV,
+                file,
+                bufs.len(),
+                at,
+                ordering,
+            );
+            sqe.addr = bufs.as_mut_ptr() as u64;
+        })
+    }
+
+    /// Reads up to `len` bytes from the given
+    /// file-like object, at the given offset, into a
+    /// buffer that the kernel picks from the group
+    /// `group_id`, previously populated with
+    /// `provide_buffers` or through a `BufRing`.
+    ///
+    /// Returns the id of the chosen buffer and
+    /// the number of bytes read into it. Like
+    /// `read_at`, this also takes `FixedFile`
+    /// indices.
+    ///
+    /// # Warning
+    ///
+    /// This only becomes usable on linux kernels
+    /// 5.7 and up.
+    pub fn read_at_buf_select<'a, F>(
+        &'a self,
+        file: &'a F,
+        len: usize,
+        at: u64,
+        group_id: u16,
+    ) -> Completion<'a, (u16, usize)>
+    where
+        F: AsRingFd,
+    {
+        self.with_sqe(None, false, |sqe| {
+            sqe.prep_rw_file(
+                IORING_OP_READ,
+                file,
+                single_op_len(len),
+                at,
+                Ordering::None,
+            );
+            sqe.flags |= IOSQE_BUFFER_SELECT;
+            sqe.__bindgen_anon_2.buf_index = group_id;
+        })
+    }
+
+    /// Writes `len` bytes, starting `offset` bytes
+    /// into the registered buffer `buf_index`, to
+    /// the file at `at`. See `register_buffers`.
+    ///
+    /// Returns the number of bytes written.
+    ///
+    /// # Safety
+    ///
+    /// The kernel reads the range straight out of
+    /// the registered buffer while the operation is
+    /// in flight, without anything borrowing it, so
+    /// the caller must not write to the range until
+    /// the returned `Completion` is done.
+    /// `PooledBuffer::write_fixed` borrows the
+    /// buffer for that long instead.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `buf_index` is not a registered
+    /// buffer, or if the range does not fit into
+    /// it.
+    #[allow(unsafe_code)]
+    pub unsafe fn write_fixed<'a, F>(
+        &'a self,
+        file: &'a F,
+        buf_index: u16,
+        offset: usize,
+        len: usize,
+        at: u64,
+    ) -> Completion<'a, usize>
+    where
+        F: AsRingFd,
+    {
+        self.write_fixed_ordered(
+            file,
+            buf_index,
+            offset,
+            len,
+            at,
+            Ordering::None,
+        )
+    }
+
+    /// Writes `len` bytes, starting `offset` bytes
+    /// into the registered buffer `buf_index`, to
+    /// the file at `at`. See `register_buffers`.
+    ///
+    /// Returns the number of bytes written.
+    ///
+    /// Accepts an `Ordering` specification.
+    ///
+    /// # Safety
+    ///
+    /// The kernel reads the range straight out of
+    /// the registered buffer while the operation is
+    /// in flight, without anything borrowing it, so
+    /// the caller must not write to the range until
+    /// the returned `Completion` is done.
+    /// `PooledBuffer::write_fixed` borrows the
+    /// buffer for that long instead.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `buf_index` is not a registered
+    /// buffer, or if the range does not fit into
+    /// it.
+    #[allow(unsafe_code)]
+    pub unsafe fn write_fixed_ordered<'a, F>(
+        &'a self,
+        file: &'a F,
+        buf_index: u16,
+        offset: usize,
+        len: usize,
+        at: u64,
+        ordering: Ordering,
+    ) -> Completion<'a, usize>
+    where
+        F: AsRingFd,
+    {
+        self.track_write(file);
+        self.with_fixed_buffer(
+            IORING_OP_WRITE_FIXED,
+            file,
+            buf_index,
+            offset,
+            len,
+            at,
+            ordering,
+        )
+    }
+
+    /// Reads `len` bytes from the file at `at` into
+    /// the registered buffer `buf_index`, starting
+    /// `offset` bytes into it. See
+    /// `register_buffers`.
+    ///
+    /// Returns the number of bytes read.
+    ///
+    /// # Safety
+    ///
+    /// The kernel writes the range straight into
+    /// the registered buffer while the operation is
+    /// in flight, without anything borrowing it, so
+    /// the caller must not access the range until
+    /// the returned `Completion` is done.
+    /// `PooledBuffer::read_fixed` borrows the
+    /// buffer for that long instead.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `buf_index` is not a registered
+    /// buffer, or if the range does not fit into
+    /// it.
+    #[allow(unsafe_code)]
+    pub unsafe fn read_fixed<'a, F>(
+        &'a self,
+        file: &'a F,
+        buf_index: u16,
+        offset: usize,
+        len: usize,
+        at: u64,
+    ) -> Completion<'a, usize>
+    where
+        F: AsRingFd,
+    {
+        self.read_fixed_ordered(
+            file,
+            buf_index,
+            offset,
+            len,
+            at,
+            Ordering::None,
+        )
+    }
+
+    /// Reads `len` bytes from the file at `at` into
+    /// the registered buffer `buf_index`, starting
+    /// `offset` bytes into it. See
+    /// `register_buffers`.
+    ///
+    /// Returns the number of bytes read.
+    ///
+    /// Accepts an `Ordering` specification.
+    ///
+    /// # Safety
+    ///
+    /// The kernel writes the range straight into
+    /// the registered buffer while the operation is
+    /// in flight, without anything borrowing it, so
+    /// the caller must not access the range until
+    /// the returned `Completion` is done.
+    /// `PooledBuffer::read_fixed` borrows the
+    /// buffer for that long instead.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `buf_index` is not a registered
+    /// buffer, or if the range does not fit into
+    /// it.
+    #[allow(unsafe_code)]
+    pub unsafe fn read_fixed_ordered<'a, F>(
+        &'a self,
+        file: &'a F,
+        buf_index: u16,
+        offset: usize,
+        len: usize,
+        at: u64,
+        ordering: Ordering,
+    ) -> Completion<'a, usize>
+    where
+        F: AsRingFd,
+    {
+        self.with_fixed_buffer(
+            IORING_OP_READ_FIXED,
+            file,
+            buf_index,
+            offset,
+            len,
+            at,
+            ordering,
+        )
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn with_fixed_buffer<'a, F>(
+        &'a self,
+        opcode: u8,
+        file: &'a F,
+        buf_index: u16,
+        offset: usize,
+        len: usize,
+        at: u64,
+        ordering: Ordering,
+    ) -> Completion<'a, usize>
+    where
+        F: AsRingFd,
+    {
+        let (base, buf_len) = self.buffers.lock().unwrap()
+            [usize::from(buf_index)];
+        assert!(
+            offset <= buf_len && len <= buf_len - offset,
+            "offset {} + len {} does not fit into \
+             registered buffer {} of length {}",
+            offset,
+            len,
+            buf_index,
+            buf_len,
+        );
+
+        self.with_sqe(None, false, |sqe| {
+            sqe.prep_rw_file(
+                opcode, file, len, at, ordering,
+            );
+            sqe.addr =
+                base + u64::try_from(offset).unwrap();
+            sqe.__bindgen_anon_2.buf_index = buf_index;
+        })
+    }
+
+    /// Waits for the file-like object to become ready
+    /// for one of the `POLL*` readiness `events`,
+    /// returning the mask of the events that are
+    /// ready, as with `poll(2)`.
+    ///
+    /// # Warning
+    ///
+    /// This only becomes usable on linux kernels
+    /// 5.1 and up, and only the lower 16 bits of
+    /// `events` are used before 5.9.
+    pub fn poll<'a, F>(
+        &'a self,
+        fd: &'a F,
+        events: u32,
+    ) -> Completion<'a, u32>
+    where
+        F: AsRawFd,
+    {
+        self.with_sqe(None, false, |sqe| {
+            sqe.prep_rw(
+                IORING_OP_POLL_ADD,
+                fd.as_raw_fd(),
+                0,
+                0,
+                Ordering::None,
+            );
+            sqe.__bindgen_anon_1.poll32_events = events;
+        })
+    }
+
+    /// Arms a multishot poll, yielding the mask of
+    /// the `POLL*` readiness `events` that are ready
+    /// every time the file-like object becomes ready
+    /// for one of them, until the stream is dropped.
+    ///
+    /// # Warning
+    ///
+    /// This only becomes usable on linux kernels
+    /// 5.13 and up.
+    pub fn poll_multi<'a, F>(
+        &'a self,
+        fd: &'a F,
+        events: u32,
+    ) -> CompletionStream<'a, u32>
+    where
+        F: AsRawFd,
+    {
+        self.with_stream_sqe(|sqe| {
+            sqe.prep_rw(
+                IORING_OP_POLL_ADD,
+                fd.as_raw_fd(),
+                0,
+                0,
+                Ordering::None,
+            );
+            sqe.len = IORING_POLL_ADD_MULTI;
+            sqe.__bindgen_anon_1.poll32_events = events;
+        })
+    }
+
+    /// Removes a pending `poll`, which then fails
+    /// with `ECANCELED`.
+    ///
+    /// The returned `Completion` fails with `ENOENT`
+    /// if the poll already completed.
+    ///
+    /// # Warning
+    ///
+    /// This only becomes usable on linux kernels
+    /// 5.1 and up.
+    pub fn poll_remove<'a>(
+        &'a self,
+        poll: &Completion<'a, u32>,
+    ) -> Completion<'a, ()> {
+        // if this fails, the poll stays queued ahead of
+        // its removal, which then finds it.
+        let _ = self.ensure_submitted(poll.sqe_id);
+
+        self.with_sqe(None, false, |sqe| {
+            sqe.prep_rw(
+                IORING_OP_POLL_REMOVE,
+                -1,
+                0,
+                0,
+                Ordering::None,
+            );
+            sqe.addr = poll.user_data;
+        })
+    }
+
+    /// Completes after the duration `dur` passes,
+    /// without occupying a thread in the meantime.
+    ///
+    /// # Warning
+    ///
+    /// This only becomes usable on linux kernels
+    /// 5.4 and up.
+    pub fn timeout<'a>(
+        &'a self,
+        dur: Duration,
+    ) -> Completion<'a, ()> {
+        self.timeout_ordered(dur, Ordering::None)
+    }
+
+    /// Completes after the duration `dur` passes,
+    /// without occupying a thread in the meantime.
+    ///
+    /// Accepts an `Ordering` specification.
+    ///
+    /// # Warning
+    ///
+    /// This only becomes usable on linux kernels
+    /// 5.4 and up.
+    pub fn timeout_ordered<'a>(
+        &'a self,
+        dur: Duration,
+        ordering: Ordering,
+    ) -> Completion<'a, ()> {
+        self.with_timespec(
+            dur2timespec(dur),
+            0,
+            0,
+            ordering,
+        )
+    }
+
+    /// Completes once `count` other operations of
+    /// this ring have completed after it, or once the
+    /// duration `dur` passes, whichever comes first,
+    /// such as for flushing a batch once it is full
+    /// or has waited long enough.
+    ///
+    /// # Warning
+    ///
+    /// This only becomes usable on linux kernels
+    /// 5.4 and up.
+    pub fn timeout_after_completions<'a>(
+        &'a self,
+        count: u64,
+        dur: Duration,
+    ) -> Completion<'a, ()> {
+        self.timeout_after_completions_ordered(
+            count,
+            dur,
+            Ordering::None,
+        )
+    }
+
+    /// Completes once `count` other operations of
+    /// this ring have completed after it, or once the
+    /// duration `dur` passes, whichever comes first.
+    ///
+    /// Accepts an `Ordering` specification.
+    ///
+    /// # Warning
+    ///
+    /// This only becomes usable on linux kernels
+    /// 5.4 and up.
+    pub fn timeout_after_completions_ordered<'a>(
+        &'a self,
+        count: u64,
+        dur: Duration,
+        ordering: Ordering,
+    ) -> Completion<'a, ()> {
+        self.with_timespec(
+            dur2timespec(dur),
+            count,
+            0,
+            ordering,
+        )
+    }
+
+    /// Completes once the `CLOCK_MONOTONIC` deadline
+    /// passes, which is immediately if it already has.
+    ///
+    /// # Warning
+    ///
+    /// This only becomes usable on linux kernels
+    /// 5.4 and up.
+    pub fn timeout_at<'a>(
+        &'a self,
+        deadline: Instant,
+    ) -> Completion<'a, ()> {
+        self.timeout_at_ordered(deadline, Ordering::None)
+    }
+
+    /// Completes once the `CLOCK_MONOTONIC` deadline
+    /// passes, which is immediately if it already has.
+    ///
+    /// Accepts an `Ordering` specification.
+    ///
+    /// # Warning
+    ///
+    /// This only becomes usable on linux kernels
+    /// 5.4 and up.
+    pub fn timeout_at_ordered<'a>(
+        &'a self,
+        deadline: Instant,
+        ordering: Ordering,
+    ) -> Completion<'a, ()> {
+        self.with_timespec(
+            instant2timespec(deadline),
+            0,
+            IORING_TIMEOUT_ABS,
+            ordering,
+        )
+    }
+
+    /// Removes a pending timeout, which then fails
+    /// with `ECANCELED`.
+    ///
+    /// The returned `Completion` fails with `ENOENT`
+    /// if the timeout already completed, or with
+    /// `EBUSY` if it is already expiring.
+    ///
+    /// # Warning
+    ///
+    /// This only becomes usable on linux kernels
+    /// 5.5 and up.
+    pub fn timeout_remove<'a>(
+        &'a self,
+        timeout: &Completion<'a, ()>,
+    ) -> Completion<'a, ()> {
+        // if this fails, the timeout stays queued ahead
+        // of its removal, which then finds it.
+        let _ = self.ensure_submitted(timeout.sqe_id);
+
+        self.with_sqe(None, false, |sqe| {
+            sqe.prep_rw(
+                IORING_OP_TIMEOUT_REMOVE,
+                -1,
+                0,
+                0,
+                Ordering::None,
+            );
+            sqe.addr = timeout.user_data;
+        })
+    }
+
+    fn with_timespec<'a>(
+        &'a self,
+        timespec: __kernel_timespec,
+        count: u64,
+        flags: u32,
+        ordering: Ordering,
+    ) -> Completion<'a, ()> {
+        self.with_pinned_sqe(
+            None,
+            false,
+            Pinned::Timespec(Box::new(timespec)),
+            |_, _| (),
+            |sqe, pinned| {
+                sqe.prep_rw(
+                    IORING_OP_TIMEOUT,
+                    -1,
+                    1,
+                    count,
+                    ordering,
+                );
+                if let Pinned::Timespec(ts) = pinned {
+                    let ts_ptr: *mut __kernel_timespec =
+                        &mut **ts;
+                    sqe.addr = ts_ptr as u64;
+                }
+                sqe.__bindgen_anon_1.timeout_flags = flags;
+            },
+        )
+    }
+
+    /// Don't do anything. This is
+    /// mostly for debugging and tuning.
+    pub fn nop<'a>(&'a self) -> Completion<'a, ()> {
+        self.nop_ordered(Ordering::None)
+    }
+
+    /// Don't do anything. This is
+    /// mostly for debugging and tuning.
+    ///
+    /// With `Ordering::Drain`, this makes a fence
+    /// that only completes once everything submitted
+    /// before it has completed.
+    pub fn nop_ordered<'a>(
+        &'a self,
+        ordering: Ordering,
+    ) -> Completion<'a, ()> {
+        self.with_sqe(None, false, |sqe| {
+            sqe.prep_rw(IORING_OP_NOP, 0, 1, 0, ordering)
+        })
+    }
+
+    /// Queues an operation that `prep` fills in
+    /// directly, for opcodes that `Uring` has no
+    /// method for yet, and resolves to the raw `res`
+    /// of its completion, or to an error if that is
+    /// negative.
+    ///
+    /// `prep` receives an sqe that is cleared apart
+    /// from its `user_data`, which `rio` overwrites
+    /// again afterwards, as it identifies the
+    /// `Completion` to fill in. Everything else,
+    /// including `opcode`, `fd`, `addr`, `len`,
+    /// `off`, `flags` and the unions, is up to
+    /// `prep`.
+    ///
+    /// # Safety
+    ///
+    /// The kernel reads from and writes into
+    /// whatever the sqe points to until the
+    /// operation completes, so the caller must keep
+    /// that memory alive and must not access memory
+    /// that the kernel writes into until the returned
+    /// `Completion` is done, and may not leak the
+    /// `Completion` in the meantime. The operation
+    /// must complete exactly once, so it must not be
+    /// a multishot operation, and must not set
+    /// `IOSQE_CQE_SKIP_SUCCESS`, or waiting on it
+    /// never returns. A `Completion` that is linked
+    /// with `IOSQE_IO_LINK` must be followed by
+    /// another operation, as with `Ordering::Link`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// let ring = rio::new().unwrap();
+    /// let file = std::fs::File::open("file").unwrap();
+    /// let buf = &mut [0_u8; 64];
+    ///
+    /// // IORING_OP_READ, which `read_at` also issues.
+    /// let read = unsafe {
+    ///     ring.submit_raw(|sqe| {
+    ///         use std::os::unix::io::AsRawFd;
+    ///
+    ///         sqe.opcode = rio::opcode::IORING_OP_READ;
+    ///         sqe.fd = file.as_raw_fd();
+    ///         sqe.addr = buf.as_mut_ptr() as u64;
+    ///         sqe.len = buf.len() as u32;
+    ///         sqe.off = 0;
+    ///     })
+    /// };
+    /// let filled = read.wait().unwrap();
+    /// ```
+    #[allow(unsafe_code)]
+    pub unsafe fn submit_raw<'a, F>(
+        &'a self,
+        prep: F,
+    ) -> Completion<'a, i32>
+    where
+        F: FnOnce(&mut io_uring_sqe),
+    {
+        self.with_sqe(None, false, |sqe| {
+            sqe.prep_rw(
+                IORING_OP_NOP,
+                0,
+                0,
+                0,
+                Ordering::None,
+            );
+            let user_data = sqe.user_data;
+            prep(sqe);
+            sqe.user_data = user_data;
+        })
+    }
+
+    /// Posts a message carrying `data` and `len` to
+    /// the `target` ring, which takes it with
+    /// `next_message`, for instance to hand a
+    /// connection that one per-core ring accepted to
+    /// the ring of the core that will serve it. The
+    /// returned `Completion` is done once the message
+    /// was posted.
+    ///
+    /// The message takes up a slot of the target's
+    /// completion queue until its reaper gets to it,
+    /// without the backpressure that operations on
+    /// the target are under, so a flood of messages
+    /// may overflow it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `data` uses either of its top two
+    /// bits, which tell messages apart from the
+    /// target's own operations.
+    ///
+    /// # Warning
+    ///
+    /// This only becomes usable on linux kernels
+    /// 5.18 and up.
+    pub fn msg_ring<'a>(
+        &'a self,
+        target: &Uring,
+        data: u64,
+        len: u32,
+    ) -> Completion<'a, ()> {
+        assert!(
+            data < MSG_RING_USER_DATA,
+            "msg_ring data must fit into 62 bits"
+        );
+        let target_fd = target.ring_fd;
+        self.with_sqe(None, false, |sqe| {
+            sqe.prep_rw(
+                IORING_OP_MSG_RING,
+                target_fd,
+                usize::try_from(len).unwrap(),
+                data | MSG_RING_USER_DATA,
+                Ordering::None,
+            );
+            // IORING_MSG_DATA, which posts `off` and
+            // `len` as the `user_data` and result.
+            sqe.addr = 0;
+        })
+    }
+
+    /// Blocks until another ring posts a message to
+    /// this one with `msg_ring`, and returns its
+    /// `data` and `len`. Messages are returned in the
+    /// order they arrived.
+    pub fn next_message(&self) -> (u64, u32) {
+        self.inbox.pop()
+    }
+
+    /// Like `next_message`, but awaits the next
+    /// message instead of blocking on it.
+    pub async fn next_message_async(&self) -> (u64, u32) {
+        std::future::poll_fn(|cx| self.inbox.poll_pop(cx))
+            .await
+    }
+
+    /// Resolves once `count` operations can be
+    /// created without blocking, because at most as
+    /// many operations may be in flight as the
+    /// completion queue has room for, parking the
+    /// task until enough of them complete instead of
+    /// blocking its thread on creating the next one.
+    /// Waiting tasks are woken in the order they
+    /// started waiting. A `count` above the capacity
+    /// of the ring waits for all of it to be free.
+    ///
+    /// The room isn't set aside for the task, so
+    /// operations created on other threads in the
+    /// mean time may still take it first. Operations
+    /// that were created but not submitted yet are
+    /// submitted before parking, since they need to
+    /// complete to make room.
+    pub async fn reserve(
+        &self,
+        count: usize,
+    ) -> io::Result<()> {
+        std::future::poll_fn(|cx| {
+            match self
+                .ticket_queue
+                .poll_available(count, cx)
+            {
+                Poll::Ready(()) => Poll::Ready(Ok(())),
+                Poll::Pending => match self.submit_all() {
+                    Ok(_) => Poll::Pending,
+                    Err(e) => Poll::Ready(Err(e)),
+                },
+            }
+        })
+        .await
+    }
+
+    /// Block until all items in the submission queue
+    /// are submitted to the kernel. This can
+    /// be avoided by using the `SQPOLL` mode
+    /// (a privileged operation) on the `Config`
+    /// struct.
+    ///
+    /// Note that this is performed automatically
+    /// and in a more fine-grained way when a
+    /// `Completion` is consumed via `Completion::wait`
+    /// or awaited in a Future context.
+    ///
+    /// You don't need to call this if you are
+    /// calling `.wait()` or `.await` on the
+    /// `Completion` quickly, but if you are
+    /// doing some other stuff that could take
+    /// a while first, calling this will ensure
+    /// that the operation is being executed
+    /// by the kernel in the mean time.
+    ///
+    /// Returns the number of items that were
+    /// submitted. In `SQPOLL` mode, this is the
+    /// number of items handed to the kernel thread,
+    /// which picks them up on its own schedule.
+    pub fn submit_all(&self) -> io::Result<usize> {
+        let mut sq = self.lock_sq();
+        let _hold_sq_mu = Measure::new(&M.sq_mu_hold);
+        let submitted = sq
+            .submit_all(self.flags, self.ring_fd)
+            .map_err(RioError::submission)?;
+        self.submitted.fetch_add(submitted, Release);
+        Ok(usize::try_from(submitted).unwrap())
+    }
+
+    /// Submits all queued operations like
+    /// `submit_all`, and blocks until the kernel has
+    /// posted `n` completions of this ring from then
+    /// on, in the same `io_uring_enter`, which saves
+    /// a syscall when a batch is submitted only to
+    /// wait for it right away. Returns the number of
+    /// items that were submitted.
+    ///
+    /// The reaper thread still reaps the completions
+    /// and fills in their `Completion`s, so they are
+    /// waited on as usual afterwards, which then
+    /// rarely blocks for long. Other threads can't
+    /// queue operations while this blocks. Kernels
+    /// older than 5.14 wait for `n` completions to
+    /// sit in the queue at once instead, which the
+    /// reaper may keep from happening, so there
+    /// this can block until more operations
+    /// complete.
+    pub fn submit_and_wait(
+        &self,
+        n: usize,
+    ) -> io::Result<usize> {
+        let mut sq = self.lock_sq();
+        let _hold_sq_mu = Measure::new(&M.sq_mu_hold);
+        let submitted = sq
+            .submit_and_wait(
+                self.flags,
+                self.ring_fd,
+                u32::try_from(n).unwrap(),
+            )
+            .map_err(RioError::submission)?;
+        self.submitted.fetch_add(submitted, Release);
+        Ok(usize::try_from(submitted).unwrap())
+    }
+
+    /// Runs `f`, which may queue several operations
+    /// on this `Uring`, and then submits them as one
+    /// contiguous run, so that no operation queued
+    /// by another thread can land in between them.
+    /// This keeps `Ordering::Link` chains that span
+    /// several operations from being broken up by
+    /// concurrent submitters.
+    ///
+    /// The submission queue is held for the whole
+    /// duration of `f`, so other threads block as
+    /// soon as they try to queue an operation. The
+    /// operations queued by `f` should fit into the
+    /// submission queue's `depth`, otherwise it is
+    /// submitted early to make room, and `f` should
+    /// not wait on any of the completions it creates.
+    ///
+    /// Each operation also takes one of the slots of
+    /// the completion queue that bound how many
+    /// operations are in flight, which only come back
+    /// once their completions are reaped. When none
+    /// is free, the batch is submitted early as well,
+    /// so that its own operations can give theirs
+    /// back, which may split it up. `batch_n` avoids
+    /// that by reserving the slots up front.
+    ///
+    /// If the queue can't be submitted, such as with
+    /// `RioError::SubmissionQueueFull`, this returns
+    /// that error. Operations that `f` queued stay
+    /// queued until a later submission hands them to
+    /// the kernel, which dropping their `Completion`s
+    /// waits for. An operation that `f` couldn't
+    /// queue at all fails with the error instead.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// let ring = rio::new().unwrap();
+    /// let file = std::fs::File::open("file").unwrap();
+    /// let buf = &mut [0_u8; 4];
+    ///
+    /// let (read, after) = ring.batch(|b| {
+    ///     (
+    ///         b.read_at_ordered(
+    ///             &file,
+    ///             buf,
+    ///             0,
+    ///             rio::Ordering::Link,
+    ///         ),
+    ///         b.nop(),
+    ///     )
+    /// })
+    /// .unwrap();
+    ///
+    /// read.wait().unwrap();
+    /// after.wait().unwrap();
+    /// ```
+    pub fn batch<'a, F, R>(&'a self, f: F) -> io::Result<R>
+    where
+        F: FnOnce(&'a Uring) -> R,
+    {
+        self.batch_n(0, f)
+    }
+
+    /// Like `batch`, but first reserves a slot of the
+    /// completion queue for each of the `count`
+    /// operations that `f` is going to queue, waiting
+    /// until that many are free, or all of them if
+    /// the queue has fewer, before taking hold of the
+    /// submission queue. The operations of `f` then
+    /// never wait for a slot, and slots that `f`
+    /// leaves unused are given back afterwards. If
+    /// `f` queues more than `count` operations, the
+    /// rest take their slots like those of `batch`
+    /// do. Inside another batch on the same `Uring`,
+    /// this just joins that batch and reserves
+    /// nothing.
+    pub fn batch_n<'a, F, R>(
+        &'a self,
+        count: usize,
+        f: F,
+    ) -> io::Result<R>
+    where
+        F: FnOnce(&'a Uring) -> R,
+    {
+        if let Some((uring, _)) = BATCH.with(Cell::get) {
+            if std::ptr::eq(uring, self) {
+                // already batching, the operations of
+                // `f` just join the outer batch.
+                return Ok(f(self));
+            }
+        }
+
+        // waiting for tickets may require the reaper
+        // to make progress, so we can't hold on to the
+        // sq yet.
+        let reserved = self.ticket_queue.pop_n(count);
+
+        let mut sq = {
+            let _get_sq_mu = Measure::new(&M.sq_mu_wait);
+            self.sq.lock().unwrap()
+        };
+        let _hold_sq_mu = Measure::new(&M.sq_mu_hold);
+
+        // start out with an empty submission queue,
+        // so that the batch is only submitted early
+        // if it doesn't fit into the whole queue.
+        match sq.submit_all(self.flags, self.ring_fd) {
+            Ok(pending) => {
+                self.submitted.fetch_add(pending, Release);
+            }
+            Err(e) => {
+                self.ticket_queue.push_multi(reserved);
+                return Err(RioError::submission(e).into());
+            }
+        }
+        sq.reserved = reserved;
+
+        let ret = {
+            let sq_ptr: *mut Sq = &mut *sq;
+            let _scope = BatchScope::enter(self, sq_ptr);
+            f(self)
+        };
+
+        let unused = std::mem::take(&mut sq.reserved);
+        if !unused.is_empty() {
+            self.ticket_queue.push_multi(unused);
+        }
+
+        match sq.submit_all(self.flags, self.ring_fd) {
+            Ok(batched) => {
+                self.submitted.fetch_add(batched, Release);
+                Ok(ret)
+            }
+            Err(e) => {
+                // dropping the completions of `f` waits
+                // for a later submission, which needs
+                // the sq.
+                drop(sq);
+                drop(ret);
+                Err(RioError::submission(e).into())
+            }
+        }
+    }
+
+    /// Starts a `Chain` of operations that each wait
+    /// for the one before them, which takes care of
+    /// linking every step but the last, and of
+    /// queueing them contiguously.
+    pub fn chain(&self) -> Chain<'_, ()> {
+        empty_chain(self)
+    }
+
+    /// Locks the submission queue, unless this
+    /// thread is running a `batch` on this `Uring`,
+    /// which already holds it.
+    fn lock_sq(&self) -> SqGuard<'_> {
+        if let Some(sq) = self.batch_sq() {
+            return sq;
+        }
+        let _get_sq_mu = Measure::new(&M.sq_mu_wait);
+        SqGuard::Locked(self.sq.lock().unwrap())
+    }
+
+    /// The submission queue that the `batch` which
+    /// this thread is running on this `Uring` holds.
+    fn batch_sq(&self) -> Option<SqGuard<'_>> {
+        let (uring, sq) = BATCH.with(Cell::get)?;
+        if !std::ptr::eq(uring, self) {
+            return None;
+        }
+        // the batch holds the lock for as long as this
+        // pointer is set, and doesn't touch the sq
+        // itself in the mean time.
+        #[allow(unsafe_code)]
+        Some(SqGuard::Batch(unsafe { &mut *sq }))
+    }
+
+    /// Takes a ticket for an operation that is about
+    /// to be queued, from those reserved by the
+    /// running `batch_n`, if any.
+    fn take_ticket(&self) -> usize {
+        if let Some(mut sq) = self.batch_sq() {
+            if let Some(ticket) = sq.reserved.pop() {
+                return ticket;
+            }
+            if let Some(ticket) =
+                self.ticket_queue.try_pop()
+            {
+                return ticket;
+            }
+            // the batch's own operations may hold every
+            // ticket, and only give them back once they
+            // are submitted.
+            if let Ok(submitted) =
+                sq.submit_all(self.flags, self.ring_fd)
+            {
+                self.submitted
+                    .fetch_add(submitted, Release);
+            }
+        }
+        self.ticket_queue.pop()
+    }
+
+    /// Asks the running kernel which operations it
+    /// supports, so that callers can fall back to
+    /// something else instead of failing at runtime.
+    ///
+    /// # Warning
+    ///
+    /// This only becomes usable on linux kernels
+    /// 5.6 and up.
+    pub fn probe(&self) -> io::Result<Probe> {
+        Probe::new(self.ring_fd)
+    }
+
+    /// Whether the `SQPOLL` thread of this ring went
+    /// to sleep after `Config::sq_poll_idle_ms`
+    /// without new submissions, which is always
+    /// `false` without `Config::sq_poll`.
+    ///
+    /// Submitting wakes the thread up again, with
+    /// an `io_uring_enter` that is otherwise spared
+    /// in `SQPOLL` mode, so a ring that keeps
+    /// reporting `true` here pays a syscall for
+    /// most of its submissions.
+    pub fn sqpoll_needs_wakeup(&self) -> bool {
+        self.lock_sq().needs_wakeup()
+    }
+
+    /// The number of entries in the submission queue,
+    /// as the kernel set it up, which is `depth`
+    /// rounded up unless `Config::clamp` lowered it.
+    pub const fn sq_entries(&self) -> u32 {
+        self.entries.0
+    }
+
+    /// The number of entries in the completion
+    /// queue, as the kernel set it up, which may
+    /// differ from `cq_depth` with `Config::clamp`.
+    pub const fn cq_entries(&self) -> u32 {
+        self.entries.1
+    }
+
+    /// Returns the timings that are printed on drop
+    /// when `Config::print_profile_on_drop` is set,
+    /// without waiting for the drop, so they can be
+    /// exported periodically.
+    pub fn profile(&self) -> ProfileSnapshot {
+        M.snapshot()
+    }
+
+    /// Returns counts of the operations that went
+    /// through this ring so far, which are cheap to
+    /// read at any time.
+    pub fn stats(&self) -> RioStats {
+        let loaded = self.loaded.load(Acquire);
+        let completed = self.in_flight.completed();
+        RioStats {
+            submitted: self.submitted.load(Acquire),
+            completed,
+            in_flight: loaded.saturating_sub(completed),
+            submission_queue_full_events: self
+                .sq_full
+                .load(Relaxed),
+        }
+    }
+
+    /// Registers a table of file descriptors with
+    /// the kernel, which operations can then refer
+    /// to by passing `FixedFile(index)` instead of
+    /// the file itself. This spares the kernel from
+    /// taking a reference on the file for every
+    /// submission.
+    ///
+    /// Only one table may be registered at a time,
+    /// call `unregister_files` before registering
+    /// a new one.
+    ///
+    /// # Warning
+    ///
+    /// This only becomes usable on linux kernels
+    /// 5.1 and up.
+    pub fn register_files(
+        &self,
+        fds: &[RawFd],
+    ) -> io::Result<()> {
+        let mut files = self.files.lock().unwrap();
+        register(
+            self.ring_fd,
+            IORING_REGISTER_FILES,
+            fds.as_ptr() as *const libc::c_void,
+            u32::try_from(fds.len()).unwrap(),
+        )?;
+        *files = fds.to_vec();
+        Ok(())
+    }
+
+    /// Registers a table of `count` empty slots for
+    /// file descriptors, like `register_files` with
+    /// `-1` for each of them, but without building
+    /// that array first, so that a server can reserve
+    /// a slot for each of many connections up front
+    /// and fill them in with `files_update` as they
+    /// arrive.
+    ///
+    /// # Warning
+    ///
+    /// This only becomes usable on linux kernels
+    /// 5.19 and up. Older kernels fail it with an
+    /// error saying so.
+    pub fn register_files_sparse(
+        &self,
+        count: u32,
+    ) -> io::Result<()> {
+        let mut files = self.files.lock().unwrap();
+        let reg = io_uring_rsrc_register {
+            nr: count,
+            flags: IORING_RSRC_REGISTER_SPARSE,
+            ..io_uring_rsrc_register::default()
+        };
+        let reg_ptr: *const io_uring_rsrc_register = &reg;
+        let res = register(
+            self.ring_fd,
+            IORING_REGISTER_FILES2,
+            reg_ptr as *const libc::c_void,
+            u32::try_from(
+                size_of::<io_uring_rsrc_register>(),
+            )
+            .unwrap(),
+        );
+        if let Err(e) = res {
+            let (running, release) = kernel_version()?;
+            if e.raw_os_error() == Some(libc::EINVAL)
+                && running < (5, 19)
+            {
+                return Err(io::Error::new(
+                    io::ErrorKind::Other,
+                    format!(
+                        "registering sparse files needs \
+                         linux 5.19 or newer, but the \
+                         running kernel is {}.",
+                        release,
+                    ),
+                ));
+            }
+            return Err(e);
+        }
+        *files = vec![-1; usize::try_from(count).unwrap()];
+        Ok(())
+    }
+
+    /// Unregisters the table of file descriptors set
+    /// up by `register_files`.
+    pub fn unregister_files(&self) -> io::Result<()> {
+        let mut files = self.files.lock().unwrap();
+        register(
+            self.ring_fd,
+            IORING_UNREGISTER_FILES,
+            std::ptr::null(),
+            0,
+        )?;
+        files.clear();
+        Ok(())
+    }
+
+    /// Registers an eventfd, created with
+    /// `libc::eventfd`, that the kernel signals
+    /// whenever it posts a completion to this ring,
+    /// so that an existing `epoll` loop can learn
+    /// about finished operations.
+    ///
+    /// The reaper thread still takes the completions
+    /// off the ring and fills in their `Completion`s,
+    /// so once the eventfd is readable, the loop can
+    /// poll the `Completion`s it waits on, which may
+    /// not all be filled in yet. Only one eventfd may
+    /// be registered at a time.
+    ///
+    /// # Warning
+    ///
+    /// This only becomes usable on linux kernels
+    /// 5.2 and up.
+    pub fn register_eventfd(
+        &self,
+        eventfd: RawFd,
+    ) -> io::Result<()> {
+        let eventfd_ptr: *const RawFd = &eventfd;
+        register(
+            self.ring_fd,
+            IORING_REGISTER_EVENTFD,
+            eventfd_ptr as *const libc::c_void,
+            1,
+        )?;
+        Ok(())
+    }
+
+    /// Unregisters the eventfd set up by
+    /// `register_eventfd`.
+    pub fn unregister_eventfd(&self) -> io::Result<()> {
+        register(
+            self.ring_fd,
+            IORING_UNREGISTER_EVENTFD,
+            std::ptr::null(),
+            0,
+        )?;
+        Ok(())
+    }
+
+    /// Caps the number of async worker threads that
+    /// the kernel runs for this ring, for `bounded`
+    /// work, like reads and writes of regular files,
+    /// and for `unbounded` work, like operations on
+    /// sockets that may block for any time. A limit
+    /// of 0 leaves that one unchanged.
+    ///
+    /// Returns the previous `(bounded, unbounded)`
+    /// limits, so passing `(0, 0)` just reads them.
+    ///
+    /// # Warning
+    ///
+    /// This only becomes usable on linux kernels
+    /// 5.15 and up.
+    pub fn set_iowq_max_workers(
+        &self,
+        bounded: u32,
+        unbounded: u32,
+    ) -> io::Result<(u32, u32)> {
+        let mut limits = [bounded, unbounded];
+        register(
+            self.ring_fd,
+            IORING_REGISTER_IOWQ_MAX_WORKERS,
+            limits.as_mut_ptr() as *const libc::c_void,
+            2,
+        )?;
+        Ok((limits[0], limits[1]))
+    }
+
+    /// Registers buffers with the kernel, which pins
+    /// their memory once up front so that
+    /// `read_fixed` and `write_fixed` can skip
+    /// mapping it on every operation. Each buffer
+    /// is referred to by its index in `bufs`.
+    ///
+    /// The buffers must stay where they are for as
+    /// long as they are registered, which is why
+    /// they must live for `'static`, for instance
+    /// by leaking a `Box`.
+    ///
+    /// Only one set of buffers may be registered
+    /// at a time, call `unregister_buffers` before
+    /// registering a new one.
+    ///
+    /// # Warning
+    ///
+    /// This only becomes usable on linux kernels
+    /// 5.1 and up.
+    pub fn register_buffers(
+        &self,
+        bufs: &[IoSliceMut<'static>],
+    ) -> io::Result<()> {
+        let mut buffers = self.buffers.lock().unwrap();
+        register(
+            self.ring_fd,
+            IORING_REGISTER_BUFFERS,
+            bufs.as_ptr() as *const libc::c_void,
+            u32::try_from(bufs.len()).unwrap(),
+        )?;
+        *buffers = bufs
+            .iter()
+            .map(|buf| (buf.as_ptr() as u64, buf.len()))
+            .collect();
+        Ok(())
+    }
+
+    /// Unregisters the buffers set up by
+    /// `register_buffers`.
+    pub fn unregister_buffers(&self) -> io::Result<()> {
+        let mut buffers = self.buffers.lock().unwrap();
+        register(
+            self.ring_fd,
+            IORING_UNREGISTER_BUFFERS,
+            std::ptr::null(),
+            0,
+        )?;
+        buffers.clear();
+        Ok(())
+    }
+
+    /// Registers the buffer ring described by `reg`,
+    /// see `Rio::register_buf_ring`.
+    pub(crate) fn register_pbuf_ring(
+        &self,
+        reg: &io_uring_buf_reg,
+    ) -> io::Result<()> {
+        let reg_ptr: *const io_uring_buf_reg = reg;
+        register(
+            self.ring_fd,
+            IORING_REGISTER_PBUF_RING,
+            reg_ptr as *const libc::c_void,
+            1,
+        )?;
+        Ok(())
+    }
+
+    /// Unregisters the buffer ring of the group
+    /// `group_id`.
+    pub(crate) fn unregister_pbuf_ring(
+        &self,
+        group_id: u16,
+    ) -> io::Result<()> {
+        let reg = io_uring_buf_reg {
+            bgid: group_id,
+            ..io_uring_buf_reg::default()
+        };
+        let reg_ptr: *const io_uring_buf_reg = &reg;
+        register(
+            self.ring_fd,
+            IORING_UNREGISTER_PBUF_RING,
+            reg_ptr as *const libc::c_void,
+            1,
+        )?;
+        Ok(())
+    }
+
+    /// Replaces the entries of the registered file
+    /// table starting at `offset` with `fds`, where
+    /// an fd of `-1` clears its slot, so that files
+    /// can come and go without registering the
+    /// whole table again.
+    ///
+    /// Returns the number of slots that were
+    /// updated. The table returned by
+    /// `registered_files` shows those slots with
+    /// their new fds once the `Completion` is
+    /// waited on, or dropped, after the update
+    /// succeeded.
+    ///
+    /// # Warning
+    ///
+    /// This only becomes usable on linux kernels
+    /// 5.6 and up.
+    pub fn files_update<'a>(
+        &'a self,
+        offset: u32,
+        fds: &'a [RawFd],
+    ) -> Completion<'a, usize> {
+        self.with_pinned_sqe(
+            None,
+            false,
+            Pinned::FilesUpdate(
+                self.files.clone(),
+                offset,
+                fds.to_vec(),
+            ),
+            |cqe, pinned| {
+                let updated = usize::from_cqe(cqe);
+                if let Pinned::FilesUpdate(
+                    table,
+                    first,
+                    installed,
+                ) = pinned
+                {
+                    let mut files = table.lock().unwrap();
+                    let start =
+                        usize::try_from(*first).unwrap();
+                    for (slot, fd) in files
+                        .iter_mut()
+                        .skip(start)
+                        .zip(installed.iter().take(updated))
+                    {
+                        *slot = *fd;
+                    }
+                }
+                updated
+            },
+            |sqe, pinned| {
+                if let Pinned::FilesUpdate(
+                    _,
+                    _,
+                    pinned_fds,
+                ) = pinned
+                {
+                    sqe.prep_rw(
+                        IORING_OP_FILES_UPDATE,
+                        -1,
+                        pinned_fds.len(),
+                        u64::from(offset),
+                        Ordering::None,
+                    );
+                    sqe.addr = pinned_fds.as_ptr() as u64;
+                }
+            },
+        )
+    }
+
+    /// Closes the file in slot `index` of the
+    /// registered file table, freeing the slot for
+    /// reuse, with `files_update` or by operations
+    /// that fill in direct descriptors. The file
+    /// itself is closed once nothing else refers to
+    /// it, so a descriptor that was registered from
+    /// one the program still holds stays open.
+    ///
+    /// The table returned by `registered_files`
+    /// shows the slot as `-1` once the `Completion`
+    /// is waited on, or dropped, after the close
+    /// succeeded.
+    ///
+    /// Kernels from linux 5.15 on close the slot
+    /// with `IORING_OP_CLOSE`, and older ones
+    /// update it to `-1` instead, which frees it in
+    /// the same way.
+    ///
+    /// # Warning
+    ///
+    /// This only becomes usable on linux kernels
+    /// 5.6 and up.
+    pub fn close_direct<'a>(
+        &'a self,
+        index: u32,
+    ) -> Completion<'a, ()> {
+        /// The fd that the fallback updates the slot
+        /// to, which must outlive the operation.
+        static CLEARED: RawFd = -1;
+
+        let close_fixed = self.kernel >= (5, 15);
+        let cleared: *const RawFd = &CLEARED;
+
+        self.with_pinned_sqe(
+            None,
+            false,
+            Pinned::FileTable(
+                self.files.clone(),
+                Some(index),
+            ),
+            |_, pinned| {
+                if let Pinned::FileTable(
+                    table,
+                    Some(closed),
+                ) = pinned
+                {
+                    set_registered_file(table, *closed, -1);
+                }
+            },
+            |sqe, _| {
+                if close_fixed {
+                    // the kernel rejects a direct close
+                    // that also names an fd, and counts
+                    // slots from 1.
+                    sqe.prep_rw(
+                        IORING_OP_CLOSE,
+                        0,
+                        0,
+                        0,
+                        Ordering::None,
+                    );
+                    sqe.__bindgen_anon_2.splice_fd_in =
+                        i32::try_from(index + 1).unwrap();
+                } else {
+                    sqe.prep_rw(
+                        IORING_OP_FILES_UPDATE,
+                        -1,
+                        1,
+                        u64::from(index),
+                        Ordering::None,
+                    );
+                    sqe.addr = cleared as u64;
+                }
+            },
+        )
+    }
+
+    /// Returns the file descriptors currently in the
+    /// registered file table, where the position of
+    /// each one is its `FixedFile` index.
+    pub fn registered_files(&self) -> Vec<RawFd> {
+        self.files.lock().unwrap().clone()
+    }
+
+    /// Registers the ring's own file descriptor for
+    /// the calling thread, so that the submissions it
+    /// makes from then on enter the kernel with an
+    /// index that spares looking up the ring's file.
+    ///
+    /// The registration only covers the calling
+    /// thread: other threads, including the one that
+    /// reaps completions, keep entering with the fd
+    /// unless they register as well. It is undone by
+    /// `unregister_ring_fd`, by dropping the `Uring`
+    /// on the same thread, or by the thread exiting.
+    /// Until then, the kernel keeps the ring alive,
+    /// so when the `Uring` is dropped on another
+    /// thread, the next submission of this thread to
+    /// any ring undoes it.
+    ///
+    /// Registering again from the same thread does
+    /// nothing.
+    ///
+    /// # Warning
+    ///
+    /// This only becomes usable on linux kernels
+    /// 5.18 and up.
+    pub fn register_ring_fd(&self) -> io::Result<()> {
+        if registered_ring(self.ring_fd, &self.ring_id)
+            .is_some()
+        {
+            return Ok(());
+        }
+
+        // an offset of -1 lets the kernel pick a free
+        // slot, which it writes back.
+        let mut update = io_uring_rsrc_update {
+            offset: u32::MAX,
+            resv: 0,
+            data: u64::try_from(self.ring_fd).unwrap(),
+        };
+        let update_ptr: *mut io_uring_rsrc_update =
+            &mut update;
+        register(
+            self.ring_fd,
+            IORING_REGISTER_RING_FDS,
+            update_ptr as *const libc::c_void,
+            1,
+        )?;
+
+        RINGS.with(|rings| {
+            rings.borrow_mut().push((
+                Arc::downgrade(&self.ring_id),
+                update.offset,
+            ));
+        });
+        Ok(())
+    }
+
+    /// Undoes `register_ring_fd` for the calling
+    /// thread, which does nothing if it never
+    /// registered the ring.
+    pub fn unregister_ring_fd(&self) -> io::Result<()> {
+        let index = match registered_ring(
+            self.ring_fd,
+            &self.ring_id,
+        ) {
+            Some(index) => index,
+            None => return Ok(()),
+        };
+
+        let update = io_uring_rsrc_update {
+            offset: index,
+            ..io_uring_rsrc_update::default()
+        };
+        let update_ptr: *const io_uring_rsrc_update =
+            &update;
+        register(
+            self.ring_fd,
+            IORING_UNREGISTER_RING_FDS,
+            update_ptr as *const libc::c_void,
+            1,
+        )?;
+
+        RINGS.with(|rings| {
+            rings.borrow_mut().retain(|(ring, _)| {
+                !is_ring(ring, &self.ring_id)
+            });
+        });
+        Ok(())
+    }
+
+    /// Asks the kernel to cancel the operation that
+    /// was submitted with `token`, as returned by
+    /// `Completion::token`, which then fails with
+    /// `ECANCELED`.
+    ///
+    /// The returned `Completion` fails with `ENOENT`
+    /// if the operation already completed, or with
+    /// `EALREADY` if it was already running and may
+    /// or may not still be interrupted.
+    ///
+    /// # Warning
+    ///
+    /// This only becomes usable on linux kernels
+    /// 5.5 and up.
+    pub fn cancel<'a>(
+        &'a self,
+        token: u64,
+    ) -> Completion<'a, ()> {
+        self.with_sqe(None, false, |sqe| {
+            sqe.prep_rw(
+                IORING_OP_ASYNC_CANCEL,
+                -1,
+                0,
+                0,
+                Ordering::None,
+            );
+            sqe.addr = token;
+        })
+    }
+
+    /// Asks the kernel to cancel every operation that
+    /// was submitted against `fd`, such as all those
+    /// on a socket that is being closed, and resolves
+    /// to how many it canceled, each of which then
+    /// fails with `ECANCELED`. Operations on a
+    /// registered file are not matched.
+    ///
+    /// Kernels before 5.19 can't match operations by
+    /// their descriptor, so there this falls back to
+    /// canceling each operation that `rio` submitted
+    /// against `fd` by its token, with a chain of
+    /// cancellations that ends in a nop, which
+    /// completes once the last of them did.
+    ///
+    /// # Warning
+    ///
+    /// This only becomes usable on linux kernels
+    /// 5.5 and up.
+    pub fn cancel_fd<'a>(
+        &'a self,
+        fd: RawFd,
+    ) -> Completion<'a, usize> {
+        if self.kernel >= (5, 19) {
+            return self.with_sqe(None, false, |sqe| {
+                sqe.prep_rw(
+                    IORING_OP_ASYNC_CANCEL,
+                    fd,
+                    0,
+                    0,
+                    Ordering::None,
+                );
+                sqe.__bindgen_anon_1.cancel_flags =
+                    IORING_ASYNC_CANCEL_FD
+                        | IORING_ASYNC_CANCEL_ALL;
+            });
+        }
+
+        let tokens = {
+            let _sq = self.lock_sq();
+            self.in_flight.user_data_for_fd(fd)
+        };
+        if tokens.is_empty() {
+            // a nop completes with 0 as well.
+            return self.with_sqe(None, false, |sqe| {
+                sqe.prep_rw(
+                    IORING_OP_NOP,
+                    0,
+                    0,
+                    0,
+                    Ordering::None,
+                );
+            });
+        }
+
+        // tokens of operations that completed already
+        // fail with ENOENT, which must not keep the
+        // chain from going on, and are not counted.
+        let count = tokens.len() + 1;
+        let batched = self.batch_n(count, |uring| {
+            let cancels: Vec<Completion<'a, ()>> = tokens
+                .into_iter()
+                .map(|token| {
+                    uring.with_sqe(None, false, |sqe| {
+                        sqe.prep_rw(
+                            IORING_OP_ASYNC_CANCEL,
+                            -1,
+                            0,
+                            0,
+                            Ordering::HardLink,
+                        );
+                        sqe.addr = token;
+                    })
+                })
+                .collect();
+            let outcomes = Pinned::Outcomes(
+                cancels
+                    .iter()
+                    .map(Completion::state)
+                    .collect(),
+            );
+            let mut completion = uring.with_pinned_sqe(
+                None,
+                false,
+                outcomes,
+                |_, pinned| pinned.succeeded(),
+                |sqe, _| {
+                    sqe.prep_rw(
+                        IORING_OP_NOP,
+                        0,
+                        0,
+                        0,
+                        Ordering::None,
+                    );
+                },
+            );
+            completion.linked = cancels;
+            completion
+        });
+        batched.unwrap_or_else(|e| failed(self, e))
+    }
+
+    fn with_sqe<'a, F, C>(
+        &'a self,
+        iovec: Option<libc::iovec>,
+        msghdr: bool,
+        f: F,
+    ) -> Completion<'a, C>
+    where
+        F: FnOnce(&mut io_uring_sqe),
+        C: FromCqe,
+    {
+        self.with_pinned_sqe(
+            iovec,
+            msghdr,
+            Pinned::None,
+            |cqe, _| C::from_cqe(cqe),
+            |sqe, _| f(sqe),
+        )
+    }
+
+    fn with_pinned_sqe<'a, F, C>(
+        &'a self,
+        iovec: Option<libc::iovec>,
+        msghdr: bool,
+        pinned: Pinned,
+        decode: fn(io_uring_cqe, &mut Pinned) -> C,
+        f: F,
+    ) -> Completion<'a, C>
+    where
+        F: FnOnce(&mut io_uring_sqe, &mut Pinned),
+    {
+        let (mut completion, filler) =
+            pair(self, pinned, decode);
+
+        let kernel_memory = &mut completion.pinned;
+        let (sqe_id, user_data) =
+            self.push_sqe(iovec, msghdr, filler, |sqe| {
+                f(sqe, kernel_memory)
+            });
+        completion.sqe_id = sqe_id;
+        completion.user_data = user_data;
+
+        completion
+    }
+
+    fn with_stream_sqe<'a, F, C>(
+        &'a self,
+        f: F,
+    ) -> CompletionStream<'a, C>
+    where
+        F: FnOnce(&mut io_uring_sqe),
+        C: FromCqe,
+    {
+        let (mut stream, filler) = stream_pair(self);
+
+        let (sqe_id, user_data) =
+            self.push_sqe(None, false, filler, f);
+        stream.sqe_id = sqe_id;
+        stream.user_data = user_data;
+
+        stream
+    }
+
+    fn push_sqe<F>(
+        &self,
+        iovec: Option<libc::iovec>,
+        msghdr: bool,
+        filler: Filler,
+        f: F,
+    ) -> (u64, u64)
+    where
+        F: FnOnce(&mut io_uring_sqe),
+    {
+        let ticket = self.take_ticket();
+
+        let mut sq = self.lock_sq();
+        let _hold_sq_mu = Measure::new(&M.sq_mu_hold);
+
+        self.fill_sqe(
+            &mut sq, 1, ticket, iovec, msghdr, filler, f,
+        )
+    }
+
+    /// Submits the queue until it has room for
+    /// `room` sqes, and returns the error if it
+    /// can't be submitted.
+    fn make_room(
+        &self,
+        sq: &mut Sq,
+        room: u32,
+    ) -> io::Result<()> {
+        while !sq.has_room(room, self.flags) {
+            self.sq_full.fetch_add(1, Relaxed);
+            let submitted = sq
+                .submit_all(self.flags, self.ring_fd)
+                .map_err(RioError::submission)?;
+            self.submitted.fetch_add(submitted, Release);
+        }
+        Ok(())
+    }
+
+    /// Queues an sqe for `ticket`, first submitting
+    /// the queue if it has no room for `room` sqes,
+    /// so that callers can queue that many sqes
+    /// back-to-back. If that submission fails, the
+    /// `filler` gets the error, nothing is queued,
+    /// and the returned `sqe_id` and `user_data`
+    /// are 0, like those of a `failed` completion.
+    #[allow(clippy::too_many_arguments)]
+    fn fill_sqe<F>(
+        &self,
+        sq: &mut Sq,
+        room: u32,
+        ticket: usize,
+        iovec: Option<libc::iovec>,
+        msghdr: bool,
+        filler: Filler,
+        f: F,
+    ) -> (u64, u64)
+    where
+        F: FnOnce(&mut io_uring_sqe),
+    {
+        let room_made = {
+            let _get_sqe = Measure::new(&M.get_sqe);
+            self.make_room(sq, room)
+        };
+        if let Err(e) = room_made {
+            // nothing was queued, so the operation fails
+            // right away, and its ticket is free again.
+            self.ticket_queue.push_multi(vec![ticket]);
+            filler.fill(Err(e));
+            return (0, 0);
+        }
+
+        let sqe = sq.try_get_sqe(self.flags).unwrap();
+        self.load_sqe(sqe, ticket, iovec, msghdr, filler, f)
+    }
+
+    /// Fills the queued `sqe` for `ticket` in with
+    /// `f`, and records the operation as in flight,
+    /// returning its `sqe_id` and `user_data`.
+    fn load_sqe<F>(
+        &self,
+        sqe: &mut io_uring_sqe,
+        ticket: usize,
+        iovec: Option<libc::iovec>,
+        msghdr: bool,
+        filler: Filler,
+        f: F,
+    ) -> (u64, u64)
+    where
+        F: FnOnce(&mut io_uring_sqe),
+    {
+        let data_ptr = self
+            .in_flight
+            .insert(ticket, iovec, msghdr, filler);
+
+        let sqe_id = self.loaded.fetch_add(1, Release) + 1;
+
+        sqe.user_data =
+            self.in_flight.next_user_data(ticket);
+        sqe.addr = data_ptr;
+        f(sqe);
+        self.in_flight.set_opcode(ticket, sqe.opcode);
+        self.in_flight.set_fd(
+            ticket,
+            if sqe.flags & IOSQE_FIXED_FILE == 0 {
+                sqe.fd
+            } else {
+                -1
+            },
+        );
+
+        (sqe_id, sqe.user_data)
+    }
+
+    /// Remembers `file` for the `fsync` that
+    /// `Config::flush_on_drop` issues on drop.
+    fn track_write<F: AsRingFd>(&self, file: &F) {
+        if !self.config.flush_on_drop {
+            return;
+        }
+        let (fd, fixed) = file.as_ring_fd();
+        let mut written = self.written.lock().unwrap();
+        if written.iter().any(|w| (w.0, w.1) == (fd, fixed))
+        {
+            return;
+        }
+
+        let raw_fd = if fixed {
+            let files = self.files.lock().unwrap();
+            match usize::try_from(fd)
+                .ok()
+                .and_then(|slot| files.get(slot))
+            {
+                Some(raw_fd) => *raw_fd,
+                None => return,
+            }
+        } else {
+            fd
+        };
+
+        #[allow(unsafe_code)]
+        let dup = unsafe { libc::dup(raw_fd) };
+        if dup >= 0 {
+            written.push((fd, fixed, dup));
+        }
+    }
+
+    /// Issues an `fsync` for each file that was
+    /// written to, waits for them all, and closes the
+    /// duplicated descriptors.
+    fn flush_written(&mut self) {
+        let written =
+            std::mem::take(self.written.get_mut().unwrap());
+
+        let fsyncs: Vec<Completion<'_, ()>> = written
+            .iter()
+            .map(|(_, _, dup)| {
+                self.with_sqe(None, false, |sqe| {
+                    sqe.prep_rw(
+                        IORING_OP_FSYNC,
+                        *dup,
+                        0,
+                        0,
+                        Ordering::None,
+                    );
+                })
+            })
+            .collect();
+        drop(fsyncs);
+
+        for (_, _, dup) in written {
+            #[allow(unsafe_code)]
+            unsafe {
+                libc::close(dup);
+            }
+        }
+    }
+
+    /// Sets `flags` on the sqe with `user_data`, and
+    /// returns whether it was still queued to take
+    /// them.
+    pub(crate) fn add_sqe_flags(
+        &self,
+        user_data: u64,
+        flags: u8,
+    ) -> bool {
+        let mut sq = self.lock_sq();
+        match sq.queued_sqe(user_data) {
+            Some(sqe) => {
+                sqe.flags |= flags;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Sets the `ioprio` of the sqe with
+    /// `user_data`, if it is still queued and is a
+    /// read or a write, as other operations take
+    /// flags in that field instead.
+    pub(crate) fn set_sqe_ioprio(
+        &self,
+        user_data: u64,
+        ioprio: u16,
+    ) {
+        let mut sq = self.lock_sq();
+        if let Some(sqe) = sq.queued_sqe(user_data) {
+            match sqe.opcode {
+                IORING_OP_READV
+                | IORING_OP_WRITEV
+                | IORING_OP_READ_FIXED
+                | IORING_OP_WRITE_FIXED
+                | IORING_OP_READ
+                | IORING_OP_WRITE => sqe.ioprio = ioprio,
+                _ => {}
+            }
+        }
+    }
+
+    /// Bounds the operation behind `target` by a
+    /// timeout, see `Completion::with_timeout`.
+    pub(crate) fn link_timeout<'a, C>(
+        &'a self,
+        target: &mut Completion<'a, C>,
+        dur: Duration,
+    ) {
+        self.link_timespec(target, dur2timespec(dur), 0);
+    }
+
+    /// Bounds the operation behind `target` by an
+    /// absolute deadline, see
+    /// `Completion::with_deadline`.
+    pub(crate) fn link_deadline<'a, C>(
+        &'a self,
+        target: &mut Completion<'a, C>,
+        deadline: Instant,
+    ) {
+        self.link_timespec(
+            target,
+            instant2timespec(deadline),
+            IORING_TIMEOUT_ABS,
+        );
+    }
+
+    fn link_timespec<'a, C>(
+        &'a self,
+        target: &mut Completion<'a, C>,
+        expiry: __kernel_timespec,
+        timeout_flags: u32,
+    ) {
+        let timespec = Box::new(expiry);
+        let timespec_ptr: *const __kernel_timespec =
+            &*timespec;
+
+        let ticket = self.take_ticket();
+
+        {
+            let mut sq = self.lock_sq();
+            let _hold_sq_mu = Measure::new(&M.sq_mu_hold);
+
+            if let Some((sqe, chained)) = sq
+                .try_get_linked_sqe(
+                    target.user_data,
+                    self.flags,
+                )
+            {
+                let (mut timeout, filler) = pair(
+                    self,
+                    Pinned::Timespec(timespec),
+                    |_, _| (),
+                );
+                let (sqe_id, user_data) = self.load_sqe(
+                    sqe,
+                    ticket,
+                    None,
+                    false,
+                    filler,
+                    |linked| {
+                        linked.prep_rw(
+                            IORING_OP_LINK_TIMEOUT,
+                            -1,
+                            1,
+                            0,
+                            Ordering::None,
+                        );
+                        linked.flags |= chained;
+                        linked.addr = timespec_ptr as u64;
+                        linked
+                            .__bindgen_anon_1
+                            .timeout_flags = timeout_flags;
+                    },
+                );
+                timeout.sqe_id = sqe_id;
+                timeout.user_data = user_data;
+
+                // the target sits right before the
+                // timeout, and must be submitted with it.
+                target.sqe_id = timeout.sqe_id;
+                target.timer.push(timeout);
+                return;
+            }
+        }
+
+        // the target was already submitted, or there
+        // was no room left to link to it, so we arm a
+        // timeout that is hard-linked to its
+        // cancellation instead. getting a ticket for
+        // the latter may require the reaper to make
+        // progress, so we can't hold on to the sq.
+        let cancel_ticket = self.take_ticket();
+
+        let mut sq = self.lock_sq();
+        let _hold_sq_mu = Measure::new(&M.sq_mu_hold);
+        if self.make_room(&mut sq, 2).is_err() {
+            // the queue can't be submitted, which waiting
+            // on the target reports, as it can't be
+            // submitted either.
+            drop(sq);
+            self.ticket_queue
+                .push_multi(vec![ticket, cancel_ticket]);
+            return;
+        }
+
+        let (mut timeout, timeout_filler) = pair(
+            self,
+            Pinned::Timespec(timespec),
+            |_, _| (),
+        );
+        let (mut cancel, cancel_filler) =
+            pair(self, Pinned::None, |_, _| ());
+
+        let (timeout_sqe_id, timeout_user_data) = self
+            .fill_sqe(
+                &mut sq,
+                2,
+                ticket,
+                None,
+                false,
+                timeout_filler,
+                |sqe| {
+                    sqe.prep_rw(
+                        IORING_OP_TIMEOUT,
+                        -1,
+                        1,
+                        0,
+                        Ordering::None,
+                    );
+                    sqe.flags |= IOSQE_IO_HARDLINK;
+                    sqe.addr = timespec_ptr as u64;
+                    sqe.__bindgen_anon_1.timeout_flags =
+                        timeout_flags;
+                },
+            );
+        timeout.sqe_id = timeout_sqe_id;
+        timeout.user_data = timeout_user_data;
+
+        let target_user_data = target.user_data;
+        let (cancel_sqe_id, cancel_user_data) = self
+            .fill_sqe(
+                &mut sq,
+                1,
+                cancel_ticket,
+                None,
+                false,
+                cancel_filler,
+                |sqe| {
+                    sqe.prep_rw(
+                        IORING_OP_ASYNC_CANCEL,
+                        -1,
+                        0,
+                        0,
+                        Ordering::None,
+                    );
+                    sqe.addr = target_user_data;
+                },
+            );
+        cancel.sqe_id = cancel_sqe_id;
+        cancel.user_data = cancel_user_data;
+
+        // waiting on the target must submit them, or
+        // a target that never completes would wait
+        // forever.
+        target.sqe_id = cancel.sqe_id;
+        target.timer.push(timeout);
+        target.timer.push(cancel);
+    }
+}
+
+/// How much of a buffer of `len` bytes one sqe can
+/// cover, as its length field only has 32 bits.
+/// The rest is left to the caller, like any other
+/// short read or write.
+fn single_op_len(len: usize) -> usize {
+    len.min(usize::try_from(u32::MAX).unwrap())
+}
+
+/// Sets slot `index` of the registered file table
+/// `table` to `fd`, unless the table is too short,
+/// as it is after being unregistered.
+fn set_registered_file(
+    table: &Mutex<Vec<RawFd>>,
+    index: u32,
+    fd: RawFd,
+) {
+    let mut files = table.lock().unwrap();
+    if let Some(slot) =
+        files.get_mut(usize::try_from(index).unwrap())
+    {
+        *slot = fd;
+    }
+}
+
+/// How many bytes `file` has past `offset`, if it
+/// is a regular file with a known size.
+fn remaining_after<F: AsRawFd>(
+    file: &F,
+    offset: u64,
+) -> usize {
+    #[allow(unsafe_code)]
+    let mut stat: libc::stat =
+        unsafe { MaybeUninit::zeroed().assume_init() };
+    #[allow(unsafe_code)]
+    let ret =
+        unsafe { libc::fstat(file.as_raw_fd(), &mut stat) };
+    if ret < 0
+        || stat.st_mode & libc::S_IFMT != libc::S_IFREG
+    {
+        return usize::MAX;
+    }
+    let size = u64::try_from(stat.st_size).unwrap_or(0);
+    usize::try_from(size.saturating_sub(offset))
+        .unwrap_or(usize::MAX)
+}
+
+/// The offset of the part of a `write_all` or
+/// `read_exact` buffer after its first `done` bytes.
+fn offset_after(at: u64, done: usize) -> u64 {
+    at + u64::try_from(done).unwrap()
+}
+
+/// Passes on how much a read of `read_exact` read,
+/// turning the end of the file into an error.
+fn read_some(res: io::Result<usize>) -> io::Result<usize> {
+    match res {
+        Ok(0) => Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "the file ended before the buffer was filled",
+        )),
+        other => other,
+    }
+}
+
+/// Passes on how much a write of `write_all` wrote,
+/// turning a write that made no progress into an
+/// error.
+fn wrote_some(res: io::Result<usize>) -> io::Result<usize> {
+    match res {
+        Ok(0) => Err(io::Error::new(
+            io::ErrorKind::WriteZero,
+            "the kernel wrote no bytes of the buffer",
+        )),
+        other => other,
+    }
+}
+
+/// Makes room at the end of `buf` for `read_to_end`
+/// to read into, returning the length of the data
+/// that is already there.
+fn grow_for_read(buf: &mut Vec<u8>) -> usize {
+    let filled = buf.len();
+    let spare =
+        std::cmp::max(4096, buf.capacity() - filled);
+    buf.resize(filled + spare, 0);
+    filled
+}
+
+/// Drops the part of `buf` that a read of
+/// `read_to_end` did not fill, and returns its
+/// result once the end was reached or it failed.
+fn finish_read(
+    buf: &mut Vec<u8>,
+    start: usize,
+    filled: usize,
+    res: io::Result<usize>,
+) -> Option<io::Result<usize>> {
+    match res {
+        Ok(0) => {
+            buf.truncate(filled);
+            Some(Ok(filled - start))
+        }
+        Ok(read) => {
+            buf.truncate(filled + read);
+            None
+        }
+        Err(e) => {
+            buf.truncate(filled);
+            Some(Err(e))
+        }
+    }
+}
+
+/// Decodes an address that the kernel wrote into a
+/// `sockaddr_storage`, which is either an `AF_INET`
+/// or an `AF_INET6` one for TCP sockets.
+fn raw2addr(
+    storage: &libc::sockaddr_storage,
+) -> SocketAddr {
+    let storage_ptr: *const libc::sockaddr_storage =
+        storage;
+    match i32::from(storage.ss_family) {
+        libc::AF_INET => {
+            #[allow(unsafe_code)]
+            let addr = unsafe {
+                &*(storage_ptr as *const libc::sockaddr_in)
+            };
+            SocketAddr::V4(SocketAddrV4::new(
+                Ipv4Addr::from(u32::from_be(
+                    addr.sin_addr.s_addr,
+                )),
+                u16::from_be(addr.sin_port),
+            ))
+        }
+        libc::AF_INET6 => {
+            #[allow(unsafe_code)]
+            let addr = unsafe {
+                &*(storage_ptr as *const libc::sockaddr_in6)
+            };
+            SocketAddr::V6(SocketAddrV6::new(
+                Ipv6Addr::from(addr.sin6_addr.s6_addr),
+                u16::from_be(addr.sin6_port),
+                addr.sin6_flowinfo,
+                addr.sin6_scope_id,
+            ))
+        }
+        family => {
+            panic!(
+                "accepted an address of family {}",
+                family
+            )
+        }
+    }
+}
+
+/// Lowers an address into the `sockaddr_in` or
+/// `sockaddr_in6` that the kernel expects, along
+/// with its length.
+fn addr2raw(
+    address: SocketAddr,
+) -> (libc::sockaddr_storage, libc::socklen_t) {
+    #[allow(unsafe_code)]
+    let mut storage: libc::sockaddr_storage =
+        unsafe { MaybeUninit::zeroed().assume_init() };
+    let storage_ptr: *mut libc::sockaddr_storage =
+        &mut storage;
+
+    let len = match address {
+        SocketAddr::V4(ref a) => {
+            #[allow(unsafe_code)]
+            let raw = unsafe {
+                &mut *(storage_ptr
+                    as *mut libc::sockaddr_in)
+            };
+            raw.sin_family =
+                libc::sa_family_t::try_from(libc::AF_INET)
+                    .unwrap();
+            raw.sin_port = a.port().to_be();
+            raw.sin_addr.s_addr =
+                u32::from(*a.ip()).to_be();
+            size_of::<libc::sockaddr_in>()
+        }
+        SocketAddr::V6(ref a) => {
+            #[allow(unsafe_code)]
+            let raw = unsafe {
+                &mut *(storage_ptr
+                    as *mut libc::sockaddr_in6)
+            };
+            raw.sin6_family =
+                libc::sa_family_t::try_from(libc::AF_INET6)
+                    .unwrap();
+            raw.sin6_port = a.port().to_be();
+            raw.sin6_flowinfo = a.flowinfo();
+            raw.sin6_addr.s6_addr = a.ip().octets();
+            raw.sin6_scope_id = a.scope_id();
+            size_of::<libc::sockaddr_in6>()
+        }
+    };
+
+    (storage, libc::socklen_t::try_from(len).unwrap())
+}
+
+thread_local! {
+    /// The `Uring` that this thread is running a
+    /// `batch` on, and its locked submission queue.
+    static BATCH: Cell<Option<(*const Uring, *mut Sq)>> =
+        const { Cell::new(None) };
+
+    /// The rings that this thread registered with
+    /// `register_ring_fd`, by their `ring_id`, along
+    /// with the index it enters them with instead.
+    static RINGS: RefCell<Vec<(Weak<()>, u32)>> =
+        const { RefCell::new(Vec::new()) };
+}
+
+/// Whether `ring` is the registration of the ring
+/// with `ring_id`. The registration keeps the id's
+/// allocation, and so its address, from being
+/// reused by another ring.
+fn is_ring(ring: &Weak<()>, ring_id: &Arc<()>) -> bool {
+    std::ptr::eq(ring.as_ptr(), Arc::as_ptr(ring_id))
+}
+
+/// The index that this thread registered the ring
+/// with `ring_id` under, if it did.
+///
+/// The registrations of rings that were dropped on
+/// another thread are removed along the way, which
+/// also lets the kernel free those rings, since it
+/// keeps them alive for as long as they are
+/// registered.
+fn registered_ring(
+    ring_fd: i32,
+    ring_id: &Arc<()>,
+) -> Option<u32> {
+    RINGS
+        .try_with(|rings| {
+            let mut found = None;
+            rings.borrow_mut().retain(|(ring, index)| {
+                if ring.strong_count() == 0 {
+                    // registrations belong to the
+                    // thread, so any ring can undo them.
+                    let update = io_uring_rsrc_update {
+                        offset: *index,
+                        ..io_uring_rsrc_update::default()
+                    };
+                    let update_ptr: *const io_uring_rsrc_update =
+                        &update;
+                    let _ = register(
+                        ring_fd,
+                        IORING_UNREGISTER_RING_FDS,
+                        update_ptr as *const libc::c_void,
+                        1,
+                    );
+                    return false;
+                }
+                if is_ring(ring, ring_id) {
+                    found = Some(*index);
+                }
+                true
+            });
+            found
+        })
+        .ok()
+        .flatten()
+}
+
+/// The fd or registered index that this thread
+/// enters the ring behind `ring_fd` and `ring_id`
+/// with, and the `enter` flag that says which of the
+/// two it is.
+pub(crate) fn enter_target(
+    ring_fd: i32,
+    ring_id: &Arc<()>,
+) -> (i32, u32) {
+    match registered_ring(ring_fd, ring_id) {
+        Some(index) => (
+            i32::try_from(index).unwrap(),
+            IORING_ENTER_REGISTERED_RING,
+        ),
+        None => (ring_fd, 0),
+    }
+}
+
+/// Marks this thread as running a batch until
+/// dropped, even if the batch panics, and then
+/// restores the batch of another `Uring` that it
+/// may have been nested in.
+struct BatchScope {
+    outer: Option<(*const Uring, *mut Sq)>,
+}
+
+impl BatchScope {
+    fn enter(uring: &Uring, sq: *mut Sq) -> BatchScope {
+        let uring_ptr: *const Uring = uring;
+        let outer = BATCH.with(|batch| {
+            batch.replace(Some((uring_ptr, sq)))
+        });
+        BatchScope { outer }
+    }
+}
+
+impl Drop for BatchScope {
+    fn drop(&mut self) {
+        BATCH.with(|batch| batch.set(self.outer));
+    }
+}
+
+/// Access to the submission queue, either through
+/// its lock or through the `batch` holding it.
+enum SqGuard<'a> {
+    Locked(MutexGuard<'a, Sq>),
+    Batch(&'a mut Sq),
+}
+
+impl<'a> std::ops::Deref for SqGuard<'a> {
+    type Target = Sq;
+
+    fn deref(&self) -> &Sq {
+        match self {
+            SqGuard::Locked(sq) => sq,
+            SqGuard::Batch(sq) => sq,
+        }
+    }
+}
+
+impl<'a> std::ops::DerefMut for SqGuard<'a> {
+    fn deref_mut(&mut self) -> &mut Sq {
+        match self {
+            SqGuard::Locked(sq) => sq,
+            SqGuard::Batch(sq) => sq,
+        }
+    }
+}
+
+/// Durations too long for the kernel saturate to
+/// the longest one it takes.
+pub(crate) fn dur2timespec(
+    dur: Duration,
+) -> __kernel_timespec {
+    __kernel_timespec {
+        tv_sec: i64::try_from(dur.as_secs())
+            .unwrap_or(i64::MAX),
+        tv_nsec: i64::from(dur.subsec_nanos()),
+    }
+}
+
+/// `Instant` is measured against `CLOCK_MONOTONIC`
+/// on linux, but doesn't expose its raw value, so
+/// we shift the current reading of that clock by
+/// the distance to `deadline` instead.
+fn instant2timespec(
+    deadline: Instant,
+) -> __kernel_timespec {
+    let mut now = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    #[allow(unsafe_code)]
+    unsafe {
+        libc::clock_gettime(
+            libc::CLOCK_MONOTONIC,
+            &mut now,
+        );
+    }
+    let now_instant = Instant::now();
+
+    let clock_now = Duration::new(
+        u64::try_from(now.tv_sec).unwrap(),
+        u32::try_from(now.tv_nsec).unwrap(),
+    );
+    let clock_deadline = if deadline >= now_instant {
+        clock_now + (deadline - now_instant)
+    } else {
+        clock_now
+            .checked_sub(now_instant - deadline)
+            .unwrap_or_default()
+    };
+
+    dur2timespec(clock_deadline)
 }
@@ -203,12 +203,24 @@ mod metrics;
 mod io_uring;
 
 #[cfg(target_os = "linux")]
-pub use io_uring::{Config, Ordering, Rio, Uring};
+pub use io_uring::{
+    cqe_flags, opcode, AsRingFd, BufRing, BufferPool,
+    Chain, Config, ConfigBuilder, FileSlot, FileStat,
+    FixedFile, IoPriority, IoPriorityClass, Msghdr,
+    MsghdrMut, OpenHow, Ordering, PooledBuffer, Probe, Rio,
+    RioError, RioStats, ShardedRio, StatxExt, Uring,
+    CURRENT_POSITION, DIRECT_DESCRIPTOR,
+};
 
-pub use completion::Completion;
+pub use {
+    completion::{
+        Completion, CompletionStream, Flagged, WithFlags,
+    },
+    metrics::{PhaseProfile, ProfileSnapshot},
+};
 
 use {
-    completion::{pair, Filler},
+    completion::{failed, pair, stream_pair, Filler},
     histogram::Histogram,
     lazy::Lazy,
     metrics::{Measure, M},
@@ -305,6 +317,19 @@ impl FromCqe for usize {
     }
 }
 
+impl FromCqe for u32 {
+    fn from_cqe(cqe: io_uring::io_uring_cqe) -> u32 {
+        use std::convert::TryFrom;
+        u32::try_from(cqe.res).unwrap()
+    }
+}
+
+impl FromCqe for i32 {
+    fn from_cqe(cqe: io_uring::io_uring_cqe) -> i32 {
+        cqe.res
+    }
+}
+
 impl FromCqe for () {
     fn from_cqe(_: io_uring::io_uring_cqe) {}
 }
@@ -104,6 +104,50 @@ pub struct Metrics {
     pub allocated_bytes: AtomicU64,
 }
 
+/// A copy of the timings that
+/// `Config::print_profile_on_drop` prints, taken
+/// while the rings are still running. These are
+/// collected for the whole process rather than for
+/// one ring, and stay empty when `rio` is built with
+/// the `no_metrics` feature.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProfileSnapshot {
+    /// One entry for each phase that is measured,
+    /// in the same order as in the printed table.
+    pub phases: Vec<PhaseProfile>,
+}
+
+/// The timings of one phase in a `ProfileSnapshot`.
+///
+/// Durations are in the units of the clock that
+/// `rio` measures with, which are nanoseconds, or
+/// TSC cycles on `x86_64`. Percentiles are `NaN`
+/// for phases that were never measured.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PhaseProfile {
+    /// The name of the phase, as printed in the
+    /// table, such as `"enter sqe"` or `"wait"`.
+    pub name: &'static str,
+    /// How many times the phase was measured.
+    pub count: u64,
+    /// The total time spent in the phase.
+    pub sum: u64,
+    /// The shortest measurement.
+    pub min: f64,
+    /// The median measurement.
+    pub median: f64,
+    /// The 90th percentile.
+    pub p90: f64,
+    /// The 99th percentile.
+    pub p99: f64,
+    /// The 99.9th percentile.
+    pub p99_9: f64,
+    /// The 99.99th percentile.
+    pub p99_99: f64,
+    /// The longest measurement.
+    pub max: f64,
+}
+
 impl Drop for Metrics {
     fn drop(&mut self) {
         #[cfg(not(feature = "no_metrics"))]
@@ -111,8 +155,45 @@ impl Drop for Metrics {
     }
 }
 
-#[cfg(not(feature = "no_metrics"))]
 impl Metrics {
+    pub fn snapshot(&self) -> ProfileSnapshot {
+        let phase =
+            |name, histo: &Histogram| PhaseProfile {
+                name,
+                count: histo.count(),
+                sum: histo.sum(),
+                min: histo.percentile(0.),
+                median: histo.percentile(50.),
+                p90: histo.percentile(90.),
+                p99: histo.percentile(99.),
+                p99_9: histo.percentile(99.9),
+                p99_99: histo.percentile(99.99),
+                max: histo.percentile(100.),
+            };
+
+        ProfileSnapshot {
+            phases: vec![
+                phase("sq_mu_wait", &self.sq_mu_wait),
+                phase("sq_mu_hold", &self.sq_mu_hold),
+                phase("enter sqe", &self.enter_sqe),
+                phase(
+                    "ticket q pop",
+                    &self.ticket_queue_pop,
+                ),
+                phase("cq_mu_wait", &self.cq_mu_wait),
+                phase("cq_mu_hold", &self.cq_mu_hold),
+                phase("enter cqe", &self.enter_cqe),
+                phase(
+                    "ticket q push",
+                    &self.ticket_queue_push,
+                ),
+                phase("reap_ready", &self.reap_ready),
+                phase("wait", &self.wait),
+            ],
+        }
+    }
+
+    #[cfg(not(feature = "no_metrics"))]
     pub fn print_profile(&self) {
         println!(
             "rio profile:\n\
@@ -0,0 +1,197 @@
+#[test]
+fn batch_keeps_linked_chain_contiguous() {
+    let file = std::fs::OpenOptions::new()
+        .create(true)
+        .read(true)
+        .write(true)
+        .truncate(true)
+        .open("batch_keeps_linked_chain_contiguous")
+        .unwrap();
+
+    let ring = rio::new().unwrap();
+
+    // hammer the ring from another thread while
+    // the batch is being built.
+    let noise_ring = ring.clone();
+    let noise = std::thread::spawn(move || {
+        for _ in 0..1000 {
+            noise_ring.nop().wait().unwrap();
+        }
+    });
+
+    for i in 0..100_u64 {
+        let out = i.to_le_bytes();
+        let into = &mut [0_u8; 8];
+        let (write, read) = ring
+            .batch(|b| {
+                (
+                    b.write_at_ordered(
+                        &file,
+                        &out,
+                        0,
+                        rio::Ordering::Link,
+                    ),
+                    b.read_at(&file, into, 0),
+                )
+            })
+            .unwrap();
+        assert_eq!(write.wait().unwrap(), 8);
+        assert_eq!(read.wait().unwrap(), 8);
+        assert_eq!(*into, out);
+    }
+
+    noise.join().unwrap();
+
+    std::fs::remove_file(
+        "batch_keeps_linked_chain_contiguous",
+    )
+    .unwrap();
+}
+
+#[test]
+fn hard_link_survives_short_read() {
+    std::fs::write(
+        "hard_link_survives_short_read",
+        b"tiny",
+    )
+    .unwrap();
+    let file = std::fs::File::open(
+        "hard_link_survives_short_read",
+    )
+    .unwrap();
+
+    let ring = rio::new().unwrap();
+    let into = &mut [0_u8; 16];
+
+    let (read, after) = ring
+        .batch(|b| {
+            (
+                b.read_at_ordered(
+                    &file,
+                    into,
+                    0,
+                    rio::Ordering::Link,
+                ),
+                b.nop(),
+            )
+        })
+        .unwrap();
+    assert_eq!(read.wait().unwrap(), 4);
+    let err = after.wait().unwrap_err();
+    assert_eq!(err.raw_os_error(), Some(libc::ECANCELED));
+
+    let (read, after) = ring
+        .batch(|b| {
+            (
+                b.read_at_ordered(
+                    &file,
+                    into,
+                    0,
+                    rio::Ordering::HardLink,
+                ),
+                b.nop(),
+            )
+        })
+        .unwrap();
+    assert_eq!(read.wait().unwrap(), 4);
+    after.wait().unwrap();
+
+    std::fs::remove_file("hard_link_survives_short_read")
+        .unwrap();
+}
+
+#[test]
+fn chain_links_every_step_but_the_last() {
+    let file = std::fs::OpenOptions::new()
+        .create(true)
+        .read(true)
+        .write(true)
+        .truncate(true)
+        .open("chain_links_every_step_but_the_last")
+        .unwrap();
+
+    let ring = rio::new().unwrap();
+
+    let out = b"chained";
+    let into = &mut [0_u8; 7];
+    let (((_, write), fsync), read) = ring
+        .chain()
+        .write_at(&file, out, 0)
+        .fsync(&file)
+        .read_at(&file, into, 0)
+        .submit()
+        .unwrap();
+    assert_eq!(write.wait().unwrap(), 7);
+    fsync.wait().unwrap();
+    assert_eq!(read.wait().unwrap(), 7);
+    assert_eq!(into, out);
+
+    // a short read cancels the rest of the chain,
+    // but not what is queued after it.
+    let into = &mut [0_u8; 16];
+    let ((_, read), after) = ring
+        .chain()
+        .read_at(&file, into, 0)
+        .nop()
+        .submit()
+        .unwrap();
+    let unlinked = ring.nop();
+    assert_eq!(read.wait().unwrap(), 7);
+    let err = after.wait().unwrap_err();
+    assert_eq!(err.raw_os_error(), Some(libc::ECANCELED));
+    unlinked.wait().unwrap();
+
+    std::fs::remove_file(
+        "chain_links_every_step_but_the_last",
+    )
+    .unwrap();
+}
+
+#[test]
+fn batch_outgrows_the_free_tickets() {
+    let config = rio::Config {
+        depth: 4,
+        ..rio::Config::default()
+    };
+    let ring: rio::Rio =
+        rio::Rio::with_config(config).unwrap();
+    let tickets = ring.cq_entries() as usize;
+
+    // park all but two tickets on a pipe that
+    // stays empty until the end.
+    let mut fds = [0; 2];
+    assert_eq!(unsafe { libc::pipe(fds.as_mut_ptr()) }, 0);
+    let reader = unsafe {
+        <std::fs::File as std::os::unix::io::FromRawFd>::from_raw_fd(fds[0])
+    };
+    let mut writer = unsafe {
+        <std::fs::File as std::os::unix::io::FromRawFd>::from_raw_fd(fds[1])
+    };
+    let polls: Vec<_> = (0..tickets - 2)
+        .map(|_| ring.poll(&reader, libc::POLLIN as u32))
+        .collect();
+    ring.submit_all().unwrap();
+
+    // the batch queues more than the free tickets,
+    // so taking the third one has to submit the
+    // first two rather than wait under the lock.
+    let nops = ring
+        .batch(|b| (b.nop(), b.nop(), b.nop(), b.nop()))
+        .unwrap();
+    nops.0.wait().unwrap();
+    nops.1.wait().unwrap();
+    nops.2.wait().unwrap();
+    nops.3.wait().unwrap();
+
+    // reserving exactly the free tickets never has
+    // to submit early.
+    let (a, b) =
+        ring.batch_n(2, |b| (b.nop(), b.nop())).unwrap();
+    a.wait().unwrap();
+    b.wait().unwrap();
+
+    std::io::Write::write_all(&mut writer, b"x").unwrap();
+    for poll in polls {
+        poll.wait().unwrap();
+    }
+}
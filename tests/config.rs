@@ -0,0 +1,646 @@
+#[test]
+fn builder_starts_ring() {
+    let ring =
+        rio::Config::builder().depth(8).start().unwrap();
+    ring.nop().wait().unwrap();
+}
+
+#[test]
+fn with_config_starts_ring() {
+    let config = rio::Config {
+        depth: 4,
+        ..rio::Config::default()
+    };
+    let ring: rio::Rio =
+        rio::Rio::with_config(config).unwrap();
+    ring.nop().wait().unwrap();
+}
+
+#[test]
+fn builder_rejects_affinity_without_sq_poll() {
+    let err = rio::Config::builder()
+        .sq_poll_affinity(1)
+        .build()
+        .unwrap_err();
+    assert_eq!(
+        err.kind(),
+        std::io::ErrorKind::InvalidInput
+    );
+
+    let config = rio::Config::builder()
+        .sq_poll(true)
+        .sq_poll_affinity(1)
+        .build()
+        .unwrap();
+    assert!(config.sq_poll);
+    assert_eq!(config.sq_poll_affinity, Some(1));
+}
+
+#[test]
+fn sq_poll_affinity_reaches_the_kernel() {
+    let cpu = unsafe { libc::sched_getcpu() };
+    assert!(cpu >= 0);
+    let ring = rio::Config::builder()
+        .sq_poll(true)
+        .sq_poll_affinity(cpu as u32)
+        .start()
+        .unwrap();
+    ring.nop().wait().unwrap();
+
+    // the kernel only checks the CPU when it is told
+    // to pin the thread.
+    let err = rio::Config::builder()
+        .sq_poll(true)
+        .sq_poll_affinity(1 << 20)
+        .start()
+        .unwrap_err();
+    assert_eq!(err.raw_os_error(), Some(libc::EINVAL));
+}
+
+#[test]
+fn sq_poll_wakes_up_after_idle() {
+    let ring = rio::Config::builder()
+        .sq_poll(true)
+        .sq_poll_idle_ms(1)
+        .start()
+        .unwrap();
+
+    ring.nop().wait().unwrap();
+
+    // give the SQPOLL thread time to go to sleep
+    std::thread::sleep(std::time::Duration::from_millis(
+        50,
+    ));
+    assert!(ring.sqpoll_needs_wakeup());
+
+    for _ in 0..8 {
+        ring.nop().wait().unwrap();
+    }
+
+    let plain = rio::new().unwrap();
+    assert!(!plain.sqpoll_needs_wakeup());
+}
+
+#[test]
+fn builder_rejects_idle_without_sq_poll() {
+    let err = rio::Config::builder()
+        .sq_poll_idle_ms(10)
+        .build()
+        .unwrap_err();
+    assert_eq!(
+        err.kind(),
+        std::io::ErrorKind::InvalidInput
+    );
+}
+
+#[test]
+fn cq_depth_is_validated() {
+    let ring = rio::Config::builder()
+        .depth(4)
+        .cq_depth(64)
+        .start();
+    ring.unwrap().nop().wait().unwrap();
+
+    for cq_depth in &[2, 48] {
+        let err = rio::Config::builder()
+            .depth(4)
+            .cq_depth(*cq_depth)
+            .start()
+            .unwrap_err();
+        assert_eq!(
+            err.kind(),
+            std::io::ErrorKind::InvalidInput
+        );
+    }
+}
+
+#[test]
+fn probe_reports_supported_ops() {
+    use rio::opcode::{IORING_OP_NOP, IORING_OP_READV};
+
+    let ring = rio::new().unwrap();
+    let probe = ring.probe().unwrap();
+    assert!(probe.is_supported(IORING_OP_NOP));
+    assert!(probe.is_supported(IORING_OP_READV));
+    assert!(!probe.is_supported(255));
+}
+
+#[test]
+fn io_poll_rejects_unpollable_file() {
+    std::fs::write("io_poll_rejects_unpollable_file", b"x")
+        .unwrap();
+    let file = std::fs::File::open(
+        "io_poll_rejects_unpollable_file",
+    )
+    .unwrap();
+
+    let ring = rio::Config::builder()
+        .io_poll(true)
+        .start()
+        .unwrap();
+
+    let buf = &mut [0_u8; 1];
+    let err =
+        ring.read_at(&file, buf, 0).wait().unwrap_err();
+    assert!(err.to_string().contains("io_poll"), "{}", err);
+
+    std::fs::remove_file("io_poll_rejects_unpollable_file")
+        .unwrap();
+}
+
+#[test]
+fn submit_all_counts_submitted_items() {
+    let ring = rio::new().unwrap();
+
+    let nops = vec![ring.nop(), ring.nop(), ring.nop()];
+    assert_eq!(ring.submit_all().unwrap(), 3);
+    assert_eq!(ring.submit_all().unwrap(), 0);
+
+    for nop in nops {
+        nop.wait().unwrap();
+    }
+}
+
+#[test]
+fn submit_and_wait_submits_and_waits_in_one_call() {
+    let ring = rio::new().unwrap();
+
+    let nops = vec![ring.nop(), ring.nop(), ring.nop()];
+    assert_eq!(ring.submit_and_wait(3).unwrap(), 3);
+
+    for nop in nops {
+        nop.wait().unwrap();
+    }
+}
+
+#[test]
+fn coop_taskrun_single_issuer_ring_completes() {
+    let ring = rio::Config::builder()
+        .coop_taskrun(true)
+        .single_issuer(true)
+        .start()
+        .unwrap();
+
+    for _ in 0..100 {
+        ring.nop().wait().unwrap();
+    }
+}
+
+#[test]
+fn registered_ring_fd_is_per_thread() {
+    let ring = rio::new().unwrap();
+
+    ring.register_ring_fd().unwrap();
+    ring.register_ring_fd().unwrap();
+    for _ in 0..100 {
+        ring.nop().wait().unwrap();
+    }
+
+    // other threads keep entering with the fd.
+    std::thread::scope(|s| {
+        s.spawn(|| ring.nop().wait().unwrap());
+    });
+
+    ring.unregister_ring_fd().unwrap();
+    ring.nop().wait().unwrap();
+}
+
+#[test]
+fn registered_ring_fd_survives_drop_on_another_thread() {
+    for _ in 0..10 {
+        let ring = rio::new().unwrap();
+        ring.register_ring_fd().unwrap();
+        ring.nop().wait().unwrap();
+
+        // this thread's registration outlives the ring.
+        std::thread::spawn(move || drop(ring))
+            .join()
+            .unwrap();
+
+        // the new ring usually gets the same fd, which
+        // must not be entered as the old ring.
+        let ring = rio::new().unwrap();
+        for _ in 0..10 {
+            ring.nop().wait().unwrap();
+        }
+        ring.register_ring_fd().unwrap();
+        ring.nop().wait().unwrap();
+    }
+}
+
+#[test]
+fn profile_counts_waits_while_running() {
+    let ring = rio::new().unwrap();
+
+    let waits = |ring: &rio::Rio| {
+        ring.profile()
+            .phases
+            .iter()
+            .find(|phase| phase.name == "wait")
+            .unwrap()
+            .count
+    };
+
+    let before = waits(&ring);
+    for _ in 0..10 {
+        ring.nop().wait().unwrap();
+    }
+    assert!(waits(&ring) >= before + 10);
+}
+
+#[test]
+fn rio_error_tells_completion_from_unsupported() {
+    let ring = rio::new().unwrap();
+
+    let file = std::fs::File::open("Cargo.toml").unwrap();
+    let buf = &mut [0_u8; 16];
+
+    // the kernel rejects writing to a file that
+    // was opened read-only.
+    let err =
+        ring.write_at(&file, buf, 0).wait().unwrap_err();
+    assert_eq!(err.raw_os_error(), Some(libc::EBADF));
+    match rio::RioError::from(err) {
+        rio::RioError::Completion(e) => {
+            assert_eq!(e.raw_os_error(), Some(libc::EBADF))
+        }
+        other => panic!("unexpected {:?}", other),
+    }
+
+    let err =
+        std::io::Error::from(rio::RioError::Unsupported(
+            rio::opcode::IORING_OP_NOP,
+        ));
+    assert!(err.to_string().contains("IORING_OP_NOP"));
+    assert!(matches!(
+        rio::RioError::from(err),
+        rio::RioError::Unsupported(
+            rio::opcode::IORING_OP_NOP
+        )
+    ));
+
+    let err = std::io::Error::from(
+        rio::RioError::SubmissionQueueFull,
+    );
+    assert_eq!(err.kind(), std::io::ErrorKind::WouldBlock);
+    assert!(matches!(
+        rio::RioError::from(err),
+        rio::RioError::SubmissionQueueFull
+    ));
+
+    // failed syscalls keep their kind and errno.
+    let err = std::io::Error::from(rio::RioError::Syscall(
+        std::io::Error::from_raw_os_error(libc::EINTR),
+    ));
+    assert_eq!(err.kind(), std::io::ErrorKind::Interrupted);
+    match rio::RioError::from(err) {
+        rio::RioError::Syscall(e) => {
+            assert_eq!(e.raw_os_error(), Some(libc::EINTR))
+        }
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[test]
+fn wait_returns_submission_errors() {
+    let ring = rio::Config::builder()
+        .single_issuer(true)
+        .start()
+        .unwrap();
+
+    // only the thread that started the ring may
+    // submit to it, so the kernel refuses this one.
+    let other = ring.clone();
+    let waiter =
+        std::thread::spawn(move || other.nop().wait());
+
+    // the refused nop stays queued, and only completes,
+    // letting its drop return, once this thread submits.
+    while !waiter.is_finished() {
+        ring.nop().wait().unwrap();
+        std::thread::sleep(
+            std::time::Duration::from_millis(1),
+        );
+    }
+    let err = waiter.join().unwrap().unwrap_err();
+    match rio::RioError::from(err) {
+        rio::RioError::Syscall(e) => {
+            assert_eq!(e.raw_os_error(), Some(libc::EEXIST))
+        }
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[test]
+fn batch_returns_submission_errors() {
+    let ring = rio::Config::builder()
+        .single_issuer(true)
+        .start()
+        .unwrap();
+
+    let other = ring.clone();
+    let batcher = std::thread::spawn(move || {
+        other.batch(|b| b.nop()).map(drop)
+    });
+
+    // the batch waits for its refused nop on drop,
+    // which this thread's submissions complete.
+    while !batcher.is_finished() {
+        ring.nop().wait().unwrap();
+        std::thread::sleep(
+            std::time::Duration::from_millis(1),
+        );
+    }
+    let err = batcher.join().unwrap().unwrap_err();
+    match rio::RioError::from(err) {
+        rio::RioError::Syscall(e) => {
+            assert_eq!(e.raw_os_error(), Some(libc::EEXIST))
+        }
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[test]
+fn registered_eventfd_counts_completions() {
+    let ring = rio::new().unwrap();
+
+    let eventfd =
+        unsafe { libc::eventfd(0, libc::EFD_CLOEXEC) };
+    assert!(eventfd >= 0);
+
+    ring.register_eventfd(eventfd).unwrap();
+
+    ring.nop().wait().unwrap();
+    ring.nop().wait().unwrap();
+
+    let mut count = 0_u64;
+    let read = unsafe {
+        libc::read(
+            eventfd,
+            &mut count as *mut u64 as *mut libc::c_void,
+            8,
+        )
+    };
+    assert_eq!(read, 8);
+    assert_eq!(count, 2);
+
+    ring.unregister_eventfd().unwrap();
+    let err = ring.unregister_eventfd().unwrap_err();
+    assert_eq!(err.raw_os_error(), Some(libc::ENXIO));
+
+    unsafe {
+        libc::close(eventfd);
+    }
+}
+
+#[test]
+fn min_kernel_follows_requested_features() {
+    assert_eq!(rio::Config::default().min_kernel(), (5, 1));
+
+    let config = rio::Config::builder()
+        .depth(8)
+        .cq_depth(16)
+        .build()
+        .unwrap();
+    assert_eq!(config.min_kernel(), (5, 5));
+
+    let config = rio::Config::builder()
+        .coop_taskrun(true)
+        .single_issuer(true)
+        .build()
+        .unwrap();
+    assert_eq!(config.min_kernel(), (6, 0));
+}
+
+#[test]
+fn dropping_rings_joins_reapers_and_closes_fds() {
+    fn open_fds() -> usize {
+        std::fs::read_dir("/proc/self/fd").unwrap().count()
+    }
+
+    fn threads() -> usize {
+        std::fs::read_dir("/proc/self/task")
+            .unwrap()
+            .count()
+    }
+
+    // warm up anything that is set up lazily once.
+    drop(rio::new().unwrap());
+
+    let fds_before = open_fds();
+    let threads_before = threads();
+
+    for _ in 0..32 {
+        let ring = rio::new().unwrap();
+        ring.nop().wait().unwrap();
+    }
+
+    // other tests in this binary run concurrently,
+    // so only a leak per ring is detectable.
+    assert!(open_fds() < fds_before + 16);
+    assert!(threads() < threads_before + 16);
+}
+
+#[test]
+fn sharded_rio_routes_to_per_cpu_rings() {
+    let rings = rio::ShardedRio::with_shards(
+        rio::Config::default(),
+        2,
+    )
+    .unwrap();
+    assert_eq!(rings.shards().len(), 2);
+
+    // the deref submits to the current CPU's ring.
+    rings.nop().wait().unwrap();
+    for shard in rings.shards() {
+        shard.nop().wait().unwrap();
+    }
+
+    let err = rio::ShardedRio::with_shards(
+        rio::Config::default(),
+        0,
+    )
+    .unwrap_err();
+    assert_eq!(
+        err.kind(),
+        std::io::ErrorKind::InvalidInput
+    );
+}
+
+#[test]
+fn reaper_affinity_rejects_missing_cpu() {
+    let err = rio::Config::builder()
+        .reaper_affinity(1 << 20)
+        .start()
+        .unwrap_err();
+    assert_eq!(
+        err.kind(),
+        std::io::ErrorKind::InvalidInput
+    );
+
+    // the CPU that this thread runs on is allowed.
+    let cpu = unsafe { libc::sched_getcpu() };
+    rio::Config::builder()
+        .reaper_affinity(cpu as usize)
+        .start()
+        .unwrap()
+        .nop()
+        .wait()
+        .unwrap();
+}
+
+#[test]
+fn stats_count_operations() {
+    let ring =
+        rio::Config::builder().depth(2).start().unwrap();
+    assert_eq!(ring.stats(), rio::RioStats::default());
+
+    let pending: Vec<_> =
+        (0..4).map(|_| ring.nop()).collect();
+    // queuing more than the submission queue holds
+    // submits the ones before.
+    let stats = ring.stats();
+    assert_eq!(stats.in_flight + stats.completed, 4);
+    assert_eq!(stats.submitted, 2);
+    assert_eq!(stats.submission_queue_full_events, 1);
+
+    for nop in pending {
+        nop.wait().unwrap();
+    }
+
+    let stats = ring.stats();
+    assert_eq!(stats.submitted, 4);
+    assert_eq!(stats.completed, 4);
+    assert_eq!(stats.in_flight, 0);
+}
+
+#[test]
+fn iowq_max_workers_reports_previous_limits() {
+    let ring = rio::Config::builder()
+        .iowq_max_workers(3, 5)
+        .start()
+        .unwrap();
+
+    assert_eq!(
+        ring.set_iowq_max_workers(2, 0).unwrap(),
+        (3, 5)
+    );
+    assert_eq!(
+        ring.set_iowq_max_workers(0, 0).unwrap(),
+        (2, 5)
+    );
+}
+
+#[test]
+fn attach_wq_fd_shares_backend_with_existing_ring() {
+    use std::os::unix::io::AsRawFd;
+
+    let first = rio::new().unwrap();
+    let second = rio::Config::builder()
+        .attach_wq_fd(first.as_raw_fd())
+        .start()
+        .unwrap();
+    second.nop().wait().unwrap();
+
+    let not_a_ring =
+        std::fs::File::open("/dev/null").unwrap();
+    assert!(rio::Config::builder()
+        .attach_wq_fd(not_a_ring.as_raw_fd())
+        .start()
+        .is_err());
+}
+
+#[test]
+fn depth_is_validated_and_rounded_up() {
+    for depth in [0, 1 << 20] {
+        let config = rio::Config {
+            depth,
+            ..rio::Config::default()
+        };
+        let err = config.start().unwrap_err();
+        assert_eq!(
+            err.kind(),
+            std::io::ErrorKind::InvalidInput
+        );
+    }
+
+    let config = rio::Config {
+        depth: 300,
+        ..rio::Config::default()
+    };
+    let ring = config.start().unwrap();
+    // 300 becomes 512, so that many fit without
+    // having to submit early.
+    let pending: Vec<_> =
+        (0..512).map(|_| ring.nop()).collect();
+    assert_eq!(
+        ring.stats().submission_queue_full_events,
+        0
+    );
+    for nop in pending {
+        nop.wait().unwrap();
+    }
+}
+
+#[test]
+fn deepest_depth_starts() {
+    let config = rio::Config {
+        depth: 1 << 15,
+        ..rio::Config::default()
+    };
+    config.start().unwrap().nop().wait().unwrap();
+}
+
+#[test]
+fn clamp_reports_the_queue_sizes_the_kernel_picked() {
+    let ring = rio::new().unwrap();
+    assert_eq!(ring.sq_entries(), 256);
+    assert_eq!(ring.cq_entries(), 512);
+
+    let err = rio::Config {
+        depth: 1 << 16,
+        ..rio::Config::default()
+    }
+    .start()
+    .unwrap_err();
+    assert_eq!(
+        err.kind(),
+        std::io::ErrorKind::InvalidInput
+    );
+
+    let ring = rio::Config::builder()
+        .depth(1 << 16)
+        .clamp(true)
+        .start()
+        .unwrap();
+    assert_eq!(ring.sq_entries(), 1 << 15);
+    assert_eq!(ring.cq_entries(), 1 << 16);
+    ring.nop().wait().unwrap();
+}
+
+#[test]
+fn reaper_batch_waits_for_enough_completions() {
+    let max_wait = std::time::Duration::from_millis(200);
+    let ring = rio::Config::builder()
+        .reaper_batch(4, max_wait)
+        .start()
+        .unwrap();
+
+    // alone, a nop is only reaped once the reaper
+    // gives up waiting for more.
+    let before = std::time::Instant::now();
+    ring.nop().wait().unwrap();
+    assert!(before.elapsed() >= max_wait);
+
+    let before = std::time::Instant::now();
+    let nops: Vec<_> = (0..4).map(|_| ring.nop()).collect();
+    for nop in nops {
+        nop.wait().unwrap();
+    }
+    assert!(before.elapsed() < max_wait);
+
+    assert!(rio::Config::builder()
+        .reaper_batch(0, max_wait)
+        .start()
+        .is_err());
+}
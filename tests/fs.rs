@@ -0,0 +1,1517 @@
+use std::os::unix::io::{AsRawFd, FromRawFd, IntoRawFd};
+
+#[test]
+fn close_releases_fd() {
+    let file = std::fs::OpenOptions::new()
+        .create(true)
+        .write(true)
+        .truncate(true)
+        .open("close_releases_fd")
+        .unwrap();
+
+    let ring = rio::new().unwrap();
+
+    let fd = file.into_raw_fd();
+    ring.close(fd).wait().unwrap();
+
+    let ret = unsafe {
+        let mut stat = std::mem::MaybeUninit::uninit();
+        libc::fstat(fd, stat.as_mut_ptr())
+    };
+    assert_eq!(ret, -1);
+    assert_eq!(
+        std::io::Error::last_os_error().raw_os_error(),
+        Some(libc::EBADF)
+    );
+
+    std::fs::remove_file("close_releases_fd").unwrap();
+}
+
+#[test]
+fn statx_empty_path_stats_open_fd() {
+    std::fs::write(
+        "statx_empty_path_stats_open_fd",
+        b"hello",
+    )
+    .unwrap();
+    let file = std::fs::File::open(
+        "statx_empty_path_stats_open_fd",
+    )
+    .unwrap();
+
+    let ring = rio::new().unwrap();
+
+    let empty = std::ffi::CString::new("").unwrap();
+    let statx = ring
+        .statx(
+            &file,
+            &empty,
+            libc::AT_EMPTY_PATH,
+            libc::STATX_SIZE,
+        )
+        .wait()
+        .unwrap();
+    assert_eq!(statx.stx_size, 5);
+
+    std::fs::remove_file("statx_empty_path_stats_open_fd")
+        .unwrap();
+}
+
+#[test]
+fn stat_decodes_metadata_like_std() {
+    use std::os::unix::fs::PermissionsExt;
+
+    std::fs::write(
+        "stat_decodes_metadata_like_std",
+        b"hello",
+    )
+    .unwrap();
+    let expected =
+        std::fs::metadata("stat_decodes_metadata_like_std")
+            .unwrap();
+
+    let ring = rio::new().unwrap();
+
+    let path = std::ffi::CString::new(
+        "stat_decodes_metadata_like_std",
+    )
+    .unwrap();
+    let stat =
+        ring.stat(&libc::AT_FDCWD, &path).wait().unwrap();
+    assert_eq!(stat.len(), 5);
+    assert!(stat.is_file());
+    assert!(!stat.is_dir());
+    assert_eq!(
+        stat.permissions().mode() & 0o777,
+        expected.permissions().mode() & 0o777,
+    );
+    assert_eq!(
+        stat.modified(),
+        expected.modified().unwrap()
+    );
+
+    let dir = std::ffi::CString::new(".").unwrap();
+    let stat =
+        ring.stat(&libc::AT_FDCWD, &dir).wait().unwrap();
+    assert!(stat.is_dir());
+
+    let missing =
+        std::ffi::CString::new("no such file").unwrap();
+    let err = ring
+        .stat(&libc::AT_FDCWD, &missing)
+        .wait()
+        .unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+
+    std::fs::remove_file("stat_decodes_metadata_like_std")
+        .unwrap();
+}
+
+#[test]
+fn read_at_buf_select_reports_chosen_buffer() {
+    std::fs::write(
+        "read_at_buf_select_reports_chosen_buffer",
+        b"0123456789abcdef",
+    )
+    .unwrap();
+    let file = std::fs::File::open(
+        "read_at_buf_select_reports_chosen_buffer",
+    )
+    .unwrap();
+
+    let ring = rio::new().unwrap();
+
+    let pool = vec![0_u8; 4 * 4];
+    unsafe {
+        ring.provide_buffers(&pool, 4, 7, 0)
+            .wait()
+            .unwrap();
+    }
+
+    let (id, read) = ring
+        .read_at_buf_select(&file, 4, 4, 7)
+        .wait()
+        .unwrap();
+    assert_eq!(read, 4);
+    let start = usize::from(id) * 4;
+    assert_eq!(&pool[start..start + 4], b"4567");
+
+    // the chosen buffer bounds a longer read.
+    let (id, read) = ring
+        .read_at_buf_select(&file, usize::MAX, 8, 7)
+        .wait()
+        .unwrap();
+    assert_eq!(read, 4);
+    let start = usize::from(id) * 4;
+    assert_eq!(&pool[start..start + 4], b"89ab");
+
+    let removed = ring.remove_buffers(4, 7).wait().unwrap();
+    assert_eq!(removed, 2);
+
+    let err =
+        unsafe { ring.provide_buffers(&pool, 0, 7, 0) }
+            .wait()
+            .unwrap_err();
+    assert_eq!(
+        err.kind(),
+        std::io::ErrorKind::InvalidInput
+    );
+
+    std::fs::remove_file(
+        "read_at_buf_select_reports_chosen_buffer",
+    )
+    .unwrap();
+}
+
+#[test]
+fn read_at_fixed_file() {
+    std::fs::write("read_at_fixed_file", b"registered")
+        .unwrap();
+    let file =
+        std::fs::File::open("read_at_fixed_file").unwrap();
+
+    let ring = rio::new().unwrap();
+
+    ring.register_files(&[file.as_raw_fd()]).unwrap();
+    assert_eq!(
+        ring.registered_files(),
+        vec![file.as_raw_fd()]
+    );
+
+    let buf = &mut [0_u8; 10];
+    let read = ring
+        .read_at(&rio::FixedFile(0), buf, 0)
+        .wait()
+        .unwrap();
+    assert_eq!(read, 10);
+    assert_eq!(buf, b"registered");
+
+    ring.unregister_files().unwrap();
+    assert!(ring.registered_files().is_empty());
+
+    std::fs::remove_file("read_at_fixed_file").unwrap();
+}
+
+#[test]
+fn read_at_uninit_initializes_the_read_prefix() {
+    use std::mem::MaybeUninit;
+
+    std::fs::write(
+        "read_at_uninit_initializes_the_read_prefix",
+        b"uninitialized",
+    )
+    .unwrap();
+    let file = std::fs::File::open(
+        "read_at_uninit_initializes_the_read_prefix",
+    )
+    .unwrap();
+
+    let ring = rio::new().unwrap();
+
+    let mut buf = vec![MaybeUninit::<u8>::uninit(); 64];
+    let read = ring
+        .read_at_uninit(&file, &mut buf, 2)
+        .wait()
+        .unwrap();
+    assert_eq!(read, 11);
+    let prefix: Vec<u8> = buf[..read]
+        .iter()
+        .map(|byte| unsafe { byte.assume_init() })
+        .collect();
+    assert_eq!(prefix, b"initialized");
+
+    std::fs::remove_file(
+        "read_at_uninit_initializes_the_read_prefix",
+    )
+    .unwrap();
+}
+
+#[test]
+fn files_update_fills_sparse_slot() {
+    std::fs::write(
+        "files_update_fills_sparse_slot",
+        b"spliced",
+    )
+    .unwrap();
+    let file = std::fs::File::open(
+        "files_update_fills_sparse_slot",
+    )
+    .unwrap();
+
+    let ring = rio::new().unwrap();
+
+    ring.register_files(&[-1, -1]).unwrap();
+
+    let fds = [file.as_raw_fd()];
+    let updated =
+        ring.files_update(1, &fds).wait().unwrap();
+    assert_eq!(updated, 1);
+    assert_eq!(ring.registered_files(), vec![-1, fds[0]]);
+
+    let buf = &mut [0_u8; 7];
+    let read = ring
+        .read_at(&rio::FixedFile(1), buf, 0)
+        .wait()
+        .unwrap();
+    assert_eq!(read, 7);
+    assert_eq!(buf, b"spliced");
+
+    // a failed update leaves the table alone.
+    let err = ring
+        .files_update(0, &[i32::MAX])
+        .wait()
+        .unwrap_err();
+    assert_eq!(err.raw_os_error(), Some(libc::EBADF));
+    assert_eq!(ring.registered_files(), vec![-1, fds[0]]);
+
+    let cleared =
+        ring.files_update(1, &[-1]).wait().unwrap();
+    assert_eq!(cleared, 1);
+    assert_eq!(ring.registered_files(), vec![-1, -1]);
+
+    std::fs::remove_file("files_update_fills_sparse_slot")
+        .unwrap();
+}
+
+#[test]
+fn close_direct_frees_the_slot() {
+    std::fs::write(
+        "close_direct_frees_the_slot",
+        b"direct",
+    )
+    .unwrap();
+    let file =
+        std::fs::File::open("close_direct_frees_the_slot")
+            .unwrap();
+
+    let ring = rio::new().unwrap();
+
+    ring.register_files(&[file.as_raw_fd()]).unwrap();
+
+    ring.close_direct(0).wait().unwrap();
+    assert_eq!(ring.registered_files(), vec![-1]);
+
+    let buf = &mut [0_u8; 6];
+    let err = ring
+        .read_at(&rio::FixedFile(0), buf, 0)
+        .wait()
+        .unwrap_err();
+    assert_eq!(err.raw_os_error(), Some(libc::EBADF));
+
+    let fds = [file.as_raw_fd()];
+    ring.files_update(0, &fds).wait().unwrap();
+    let read = ring
+        .read_at(&rio::FixedFile(0), buf, 0)
+        .wait()
+        .unwrap();
+    assert_eq!(read, 6);
+    assert_eq!(buf, b"direct");
+
+    std::fs::remove_file("close_direct_frees_the_slot")
+        .unwrap();
+}
+
+#[test]
+fn write_fixed_then_read_fixed() {
+    let file = std::fs::OpenOptions::new()
+        .create(true)
+        .read(true)
+        .write(true)
+        .truncate(true)
+        .open("write_fixed_then_read_fixed")
+        .unwrap();
+
+    let ring = rio::new().unwrap();
+
+    let out: &'static mut [u8] =
+        Box::leak(Box::new(*b"pinned"));
+    let into: &'static mut [u8] =
+        Box::leak(Box::new([0_u8; 6]));
+    let bufs = [
+        std::io::IoSliceMut::new(out),
+        std::io::IoSliceMut::new(into),
+    ];
+    ring.register_buffers(&bufs).unwrap();
+
+    // nothing touches the buffers while they are in
+    // flight.
+    let written =
+        unsafe { ring.write_fixed(&file, 0, 0, 6, 0) }
+            .wait()
+            .unwrap();
+    assert_eq!(written, 6);
+
+    let read =
+        unsafe { ring.read_fixed(&file, 1, 2, 4, 2) }
+            .wait()
+            .unwrap();
+    assert_eq!(read, 4);
+    assert_eq!(&*bufs[1], b"\0\0nned");
+
+    ring.unregister_buffers().unwrap();
+
+    std::fs::remove_file("write_fixed_then_read_fixed")
+        .unwrap();
+}
+
+#[test]
+fn renameat_respects_noreplace() {
+    std::fs::write("renameat_respects_noreplace_a", b"a")
+        .unwrap();
+    std::fs::write("renameat_respects_noreplace_b", b"b")
+        .unwrap();
+    let dir = std::fs::File::open(".").unwrap();
+
+    let ring = rio::new().unwrap();
+
+    let a = std::ffi::CString::new(
+        "renameat_respects_noreplace_a",
+    )
+    .unwrap();
+    let b = std::ffi::CString::new(
+        "renameat_respects_noreplace_b",
+    )
+    .unwrap();
+    let c = std::ffi::CString::new(
+        "renameat_respects_noreplace_c",
+    )
+    .unwrap();
+
+    let err = ring
+        .renameat(
+            &dir,
+            &a,
+            &dir,
+            &b,
+            libc::RENAME_NOREPLACE,
+        )
+        .wait()
+        .unwrap_err();
+    assert_eq!(err.raw_os_error(), Some(libc::EEXIST));
+
+    ring.renameat(
+        &dir,
+        &a,
+        &dir,
+        &c,
+        libc::RENAME_NOREPLACE,
+    )
+    .wait()
+    .unwrap();
+    assert_eq!(
+        std::fs::read("renameat_respects_noreplace_c")
+            .unwrap(),
+        b"a"
+    );
+
+    std::fs::remove_file("renameat_respects_noreplace_b")
+        .unwrap();
+    std::fs::remove_file("renameat_respects_noreplace_c")
+        .unwrap();
+}
+
+#[test]
+fn mkdirat_linked_to_openat_then_unlinkat() {
+    let dir = std::fs::File::open(".").unwrap();
+
+    let ring = rio::new().unwrap();
+
+    let path = std::ffi::CString::new(
+        "mkdirat_linked_to_openat_then_unlinkat",
+    )
+    .unwrap();
+
+    let mkdir = ring.mkdirat_ordered(
+        &dir,
+        &path,
+        0o755,
+        rio::Ordering::Link,
+    );
+    let open = ring.openat(
+        &dir,
+        &path,
+        libc::O_RDONLY | libc::O_DIRECTORY,
+        0,
+    );
+    mkdir.wait().unwrap();
+    let fd = open.wait().unwrap();
+    ring.close(fd).wait().unwrap();
+
+    let err =
+        ring.unlinkat(&dir, &path, 0).wait().unwrap_err();
+    assert_eq!(err.raw_os_error(), Some(libc::EISDIR));
+
+    ring.unlinkat(&dir, &path, libc::AT_REMOVEDIR)
+        .wait()
+        .unwrap();
+    assert!(!std::path::Path::new(
+        "mkdirat_linked_to_openat_then_unlinkat"
+    )
+    .exists());
+}
+
+#[test]
+fn linkat_and_symlinkat_build_links() {
+    std::fs::write(
+        "linkat_and_symlinkat_build_links",
+        b"x",
+    )
+    .unwrap();
+    let dir = std::fs::File::open(".").unwrap();
+
+    let ring = rio::new().unwrap();
+
+    let target = std::ffi::CString::new(
+        "linkat_and_symlinkat_build_links",
+    )
+    .unwrap();
+    let hard = std::ffi::CString::new(
+        "linkat_and_symlinkat_build_links_hard",
+    )
+    .unwrap();
+    let soft = std::ffi::CString::new(
+        "linkat_and_symlinkat_build_links_soft",
+    )
+    .unwrap();
+
+    ring.linkat(&dir, &target, &dir, &hard, 0)
+        .wait()
+        .unwrap();
+    ring.symlinkat(&target, &dir, &soft).wait().unwrap();
+
+    let meta = std::fs::metadata(
+        "linkat_and_symlinkat_build_links",
+    )
+    .unwrap();
+    assert_eq!(
+        std::os::unix::fs::MetadataExt::nlink(&meta),
+        2
+    );
+    assert_eq!(
+        std::fs::read_link(
+            "linkat_and_symlinkat_build_links_soft"
+        )
+        .unwrap(),
+        std::path::Path::new(
+            "linkat_and_symlinkat_build_links"
+        )
+    );
+
+    let err = ring
+        .symlinkat(&target, &dir, &soft)
+        .wait()
+        .unwrap_err();
+    assert_eq!(err.raw_os_error(), Some(libc::EEXIST));
+
+    for path in &[
+        "linkat_and_symlinkat_build_links",
+        "linkat_and_symlinkat_build_links_hard",
+        "linkat_and_symlinkat_build_links_soft",
+    ] {
+        std::fs::remove_file(path).unwrap();
+    }
+}
+
+#[test]
+fn writev_then_readv_split_buffers() {
+    use std::io::{IoSlice, IoSliceMut};
+
+    let file = std::fs::OpenOptions::new()
+        .create(true)
+        .read(true)
+        .write(true)
+        .truncate(true)
+        .open("writev_then_readv_split_buffers")
+        .unwrap();
+
+    let ring = rio::new().unwrap();
+
+    let bufs =
+        [IoSlice::new(b"head"), IoSlice::new(b"body")];
+    let written =
+        ring.writev(&file, &bufs, 0).wait().unwrap();
+    assert_eq!(written, 8);
+
+    let mut header = [0_u8; 3];
+    let mut body = [0_u8; 5];
+    let mut bufs = [
+        IoSliceMut::new(&mut header),
+        IoSliceMut::new(&mut body),
+    ];
+    let read =
+        ring.readv(&file, &mut bufs, 0).wait().unwrap();
+    assert_eq!(read, 8);
+    assert_eq!(&header, b"hea");
+    assert_eq!(&body, b"dbody");
+
+    std::fs::remove_file("writev_then_readv_split_buffers")
+        .unwrap();
+}
+
+#[test]
+fn fallocate_punches_hole() {
+    let file = std::fs::OpenOptions::new()
+        .create(true)
+        .read(true)
+        .write(true)
+        .truncate(true)
+        .open("fallocate_punches_hole")
+        .unwrap();
+
+    let ring = rio::new().unwrap();
+
+    let blocks = |file: &std::fs::File| {
+        use std::os::unix::fs::MetadataExt;
+        file.metadata().unwrap().blocks()
+    };
+
+    ring.fallocate(&file, 0, 0, 1024 * 1024)
+        .wait()
+        .unwrap();
+    let allocated = blocks(&file);
+    assert!(allocated >= 2048);
+
+    ring.fallocate(
+        &file,
+        libc::FALLOC_FL_PUNCH_HOLE
+            | libc::FALLOC_FL_KEEP_SIZE,
+        0,
+        512 * 1024,
+    )
+    .wait()
+    .unwrap();
+    assert!(blocks(&file) < allocated);
+    assert_eq!(file.metadata().unwrap().len(), 1024 * 1024);
+
+    std::fs::remove_file("fallocate_punches_hole").unwrap();
+}
+
+#[test]
+fn fadvise_passes_advice_to_kernel() {
+    std::fs::write("fadvise_passes_advice_to_kernel", b"x")
+        .unwrap();
+    let file = std::fs::File::open(
+        "fadvise_passes_advice_to_kernel",
+    )
+    .unwrap();
+
+    let ring = rio::new().unwrap();
+
+    ring.fadvise(&file, 0, 0, libc::POSIX_FADV_SEQUENTIAL)
+        .wait()
+        .unwrap();
+
+    let err =
+        ring.fadvise(&file, 0, 0, -1).wait().unwrap_err();
+    assert_eq!(err.raw_os_error(), Some(libc::EINVAL));
+
+    std::fs::remove_file("fadvise_passes_advice_to_kernel")
+        .unwrap();
+}
+
+#[test]
+fn punch_hole_and_zero_range_keep_file_size() {
+    use std::io::Read;
+    use std::os::unix::fs::MetadataExt;
+
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .read(true)
+        .write(true)
+        .truncate(true)
+        .open("punch_hole_and_zero_range_keep_file_size")
+        .unwrap();
+
+    let ring = rio::new().unwrap();
+
+    let buf = vec![7_u8; 1024 * 1024];
+    ring.write_at(&file, &buf, 0).wait().unwrap();
+    ring.fsync(&file).wait().unwrap();
+    let allocated = file.metadata().unwrap().blocks();
+    assert!(allocated >= 2048);
+
+    ring.punch_hole(&file, 0, 512 * 1024).wait().unwrap();
+    let punched = file.metadata().unwrap().blocks();
+    assert!(punched < allocated);
+    assert_eq!(file.metadata().unwrap().len(), 1024 * 1024);
+
+    ring.zero_range(&file, 512 * 1024, 4096)
+        .wait()
+        .unwrap();
+    assert_eq!(file.metadata().unwrap().len(), 1024 * 1024);
+
+    let mut contents = vec![];
+    file.read_to_end(&mut contents).unwrap();
+    let zeroed = 512 * 1024 + 4096;
+    assert!(contents[..zeroed]
+        .iter()
+        .all(|byte| *byte == 0));
+    assert!(contents[zeroed..]
+        .iter()
+        .all(|byte| *byte == 7));
+
+    std::fs::remove_file(
+        "punch_hole_and_zero_range_keep_file_size",
+    )
+    .unwrap();
+}
+
+#[test]
+fn with_ioprio_reaches_the_kernel() {
+    use rio::{IoPriority, IoPriorityClass};
+
+    std::fs::write(
+        "with_ioprio_reaches_the_kernel",
+        b"prio",
+    )
+    .unwrap();
+    let file = std::fs::File::open(
+        "with_ioprio_reaches_the_kernel",
+    )
+    .unwrap();
+
+    let ring = rio::new().unwrap();
+
+    let background = IoPriority {
+        class: IoPriorityClass::Idle,
+        level: 0,
+    };
+    assert_eq!(background.to_ioprio(), 3 << 13);
+
+    let buf = &mut [0_u8; 4];
+    let read = ring
+        .read_at(&file, buf, 0)
+        .with_ioprio(IoPriority {
+            class: IoPriorityClass::BestEffort,
+            level: 7,
+        })
+        .wait()
+        .unwrap();
+    assert_eq!(read, 4);
+    assert_eq!(buf, b"prio");
+
+    // the kernel only rejects priorities that it
+    // sees.
+    let err = ring
+        .read_at(&file, buf, 0)
+        .with_ioprio(IoPriority {
+            class: IoPriorityClass::None,
+            level: 1,
+        })
+        .wait()
+        .unwrap_err();
+    assert_eq!(err.raw_os_error(), Some(libc::EINVAL));
+
+    std::fs::remove_file("with_ioprio_reaches_the_kernel")
+        .unwrap();
+}
+
+#[test]
+fn sync_file_range_waits_after_write() {
+    let file = std::fs::OpenOptions::new()
+        .create(true)
+        .read(true)
+        .write(true)
+        .truncate(true)
+        .open("sync_file_range_waits_after_write")
+        .unwrap();
+
+    let ring = rio::new().unwrap();
+
+    let buf = vec![7_u8; 4096];
+    let write = ring.write_at_ordered(
+        &file,
+        &buf,
+        0,
+        rio::Ordering::Link,
+    );
+    let sync = ring.sync_file_range(
+        &file,
+        0,
+        buf.len() as u32,
+        libc::SYNC_FILE_RANGE_WAIT_BEFORE
+            | libc::SYNC_FILE_RANGE_WRITE
+            | libc::SYNC_FILE_RANGE_WAIT_AFTER,
+    );
+    assert_eq!(write.wait().unwrap(), buf.len());
+    sync.wait().unwrap();
+
+    std::fs::remove_file(
+        "sync_file_range_waits_after_write",
+    )
+    .unwrap();
+}
+
+#[test]
+fn read_to_end_reads_whole_file() {
+    let data: Vec<u8> =
+        (0..20_000_u32).map(|i| i as u8).collect();
+    std::fs::write("read_to_end_reads_whole_file", &data)
+        .unwrap();
+    let file =
+        std::fs::File::open("read_to_end_reads_whole_file")
+            .unwrap();
+
+    let ring = rio::new().unwrap();
+
+    let mut buf = b"prefix".to_vec();
+    let read = ring.read_to_end(&file, &mut buf).unwrap();
+    assert_eq!(read, data.len());
+    assert_eq!(&buf[..6], b"prefix");
+    assert_eq!(&buf[6..], &data[..]);
+
+    // the position was left at the end of the file
+    let read = ring.read_to_end(&file, &mut buf).unwrap();
+    assert_eq!(read, 0);
+
+    let file =
+        std::fs::File::open("read_to_end_reads_whole_file")
+            .unwrap();
+    let mut buf = vec![];
+    let read = extreme::run(
+        ring.read_to_end_async(&file, &mut buf),
+    )
+    .unwrap();
+    assert_eq!(read, data.len());
+    assert_eq!(buf, data);
+
+    std::fs::remove_file("read_to_end_reads_whole_file")
+        .unwrap();
+}
+
+#[test]
+fn read_to_end_follows_short_reads() {
+    use std::{io::Write, os::unix::net::UnixStream};
+
+    let (mut writer, reader) = UnixStream::pair().unwrap();
+
+    let writing = std::thread::spawn(move || {
+        for chunk in &[&b"first "[..], b"second ", b"third"]
+        {
+            writer.write_all(chunk).unwrap();
+            std::thread::sleep(
+                std::time::Duration::from_millis(10),
+            );
+        }
+    });
+
+    let ring = rio::new().unwrap();
+
+    let mut buf = vec![];
+    ring.read_to_end(&reader, &mut buf).unwrap();
+    assert_eq!(buf, b"first second third");
+
+    writing.join().unwrap();
+}
+
+#[test]
+fn write_all_writes_whole_buffer_at_offset() {
+    let data: Vec<u8> =
+        (0..100_000_u32).map(|i| i as u8).collect();
+    let file = std::fs::OpenOptions::new()
+        .create(true)
+        .read(true)
+        .write(true)
+        .truncate(true)
+        .open("write_all_writes_whole_buffer_at_offset")
+        .unwrap();
+
+    let ring = rio::new().unwrap();
+
+    ring.write_all(&file, &data, 10).unwrap();
+    extreme::run(
+        ring.write_all_async(&file, b"tail", 100_010),
+    )
+    .unwrap();
+
+    let written = std::fs::read(
+        "write_all_writes_whole_buffer_at_offset",
+    )
+    .unwrap();
+    assert_eq!(written.len(), 100_014);
+    assert_eq!(&written[..10], &[0; 10]);
+    assert_eq!(&written[10..100_010], &data[..]);
+    assert_eq!(&written[100_010..], b"tail");
+
+    std::fs::remove_file(
+        "write_all_writes_whole_buffer_at_offset",
+    )
+    .unwrap();
+}
+
+#[test]
+fn current_position_reads_and_writes_pipe() {
+    let mut fds = [0; 2];
+    assert_eq!(unsafe { libc::pipe(fds.as_mut_ptr()) }, 0);
+    let (reader, writer) = unsafe {
+        (
+            std::fs::File::from_raw_fd(fds[0]),
+            std::fs::File::from_raw_fd(fds[1]),
+        )
+    };
+
+    let ring = rio::new().unwrap();
+
+    let written = ring
+        .write_at(
+            &writer,
+            b"through the pipe",
+            rio::CURRENT_POSITION,
+        )
+        .wait()
+        .unwrap();
+    assert_eq!(written, 16);
+
+    let buf = &mut [0_u8; 16];
+    let read = ring
+        .read_at(&reader, buf, rio::CURRENT_POSITION)
+        .wait()
+        .unwrap();
+    assert_eq!(&buf[..read], b"through the pipe");
+}
+
+#[test]
+fn splice_and_tee_take_fixed_files() {
+    use std::io::Read;
+
+    std::fs::write(
+        "splice_and_tee_take_fixed_files",
+        b"spliced",
+    )
+    .unwrap();
+    let file = std::fs::File::open(
+        "splice_and_tee_take_fixed_files",
+    )
+    .unwrap();
+    let mut fds = [0; 4];
+    assert_eq!(unsafe { libc::pipe(fds.as_mut_ptr()) }, 0);
+    assert_eq!(
+        unsafe { libc::pipe(fds[2..].as_mut_ptr()) },
+        0
+    );
+    let (
+        mut first,
+        _first_writer,
+        mut second,
+        second_writer,
+    ) = unsafe {
+        (
+            std::fs::File::from_raw_fd(fds[0]),
+            std::fs::File::from_raw_fd(fds[1]),
+            std::fs::File::from_raw_fd(fds[2]),
+            std::fs::File::from_raw_fd(fds[3]),
+        )
+    };
+
+    let ring = rio::new().unwrap();
+
+    ring.register_files(&[
+        file.as_raw_fd(),
+        fds[1],
+        fds[0],
+    ])
+    .unwrap();
+
+    let spliced = ring
+        .splice(
+            &rio::FixedFile(0),
+            0,
+            &rio::FixedFile(1),
+            -1,
+            7,
+            0,
+        )
+        .wait()
+        .unwrap();
+    assert_eq!(spliced, 7);
+
+    let teed = ring
+        .tee(&rio::FixedFile(2), &second_writer, 7, 0)
+        .wait()
+        .unwrap();
+    assert_eq!(teed, 7);
+
+    let buf = &mut [0_u8; 7];
+    first.read_exact(buf).unwrap();
+    assert_eq!(buf, b"spliced");
+    second.read_exact(buf).unwrap();
+    assert_eq!(buf, b"spliced");
+
+    std::fs::remove_file("splice_and_tee_take_fixed_files")
+        .unwrap();
+}
+
+#[test]
+fn current_position_appends_with_o_append() {
+    std::fs::write("current_position_appends", b"head ")
+        .unwrap();
+    let file = std::fs::OpenOptions::new()
+        .append(true)
+        .open("current_position_appends")
+        .unwrap();
+
+    let ring = rio::new().unwrap();
+
+    ring.write_at(&file, b"tail", rio::CURRENT_POSITION)
+        .wait()
+        .unwrap();
+
+    assert_eq!(
+        std::fs::read("current_position_appends").unwrap(),
+        b"head tail"
+    );
+    std::fs::remove_file("current_position_appends")
+        .unwrap();
+}
+
+#[test]
+fn read_exact_fills_buffer_or_fails_at_eof() {
+    let data: Vec<u8> =
+        (0..50_000_u32).map(|i| i as u8).collect();
+    std::fs::write("read_exact_fills_buffer", &data)
+        .unwrap();
+    let file =
+        std::fs::File::open("read_exact_fills_buffer")
+            .unwrap();
+
+    let ring = rio::new().unwrap();
+
+    let buf = &mut [0_u8; 40_000];
+    ring.read_exact(&file, buf, 100).unwrap();
+    assert_eq!(&buf[..], &data[100..40_100]);
+
+    let buf = &mut [0_u8; 16];
+    extreme::run(ring.read_exact_async(&file, buf, 49_984))
+        .unwrap();
+    assert_eq!(&buf[..], &data[49_984..]);
+
+    let err =
+        ring.read_exact(&file, buf, 49_990).unwrap_err();
+    assert_eq!(
+        err.kind(),
+        std::io::ErrorKind::UnexpectedEof
+    );
+
+    std::fs::remove_file("read_exact_fills_buffer")
+        .unwrap();
+}
+
+#[test]
+fn rw_flags_reach_the_kernel() {
+    let file = std::fs::OpenOptions::new()
+        .create(true)
+        .read(true)
+        .write(true)
+        .truncate(true)
+        .open("rw_flags_reach_the_kernel")
+        .unwrap();
+
+    let ring = rio::new().unwrap();
+
+    let written = ring
+        .write_at_with_flags(
+            &file,
+            b"durable",
+            0,
+            libc::RWF_DSYNC,
+            rio::Ordering::None,
+        )
+        .wait()
+        .unwrap();
+    assert_eq!(written, 7);
+
+    let buf = &mut [0_u8; 7];
+    let read = ring
+        .read_at_with_flags(
+            &file,
+            buf,
+            0,
+            libc::RWF_NOWAIT,
+            rio::Ordering::None,
+        )
+        .wait()
+        .unwrap();
+    assert_eq!(&buf[..read], b"durable");
+
+    // flags that the kernel doesn't know are refused.
+    let err = ring
+        .read_at_with_flags(
+            &file,
+            buf,
+            0,
+            1 << 30,
+            rio::Ordering::None,
+        )
+        .wait()
+        .unwrap_err();
+    assert_eq!(err.raw_os_error(), Some(libc::EOPNOTSUPP));
+
+    std::fs::remove_file("rw_flags_reach_the_kernel")
+        .unwrap();
+}
+
+#[test]
+fn rw_nowait_read_from_empty_pipe_would_block() {
+    let mut fds = [0; 2];
+    assert_eq!(unsafe { libc::pipe(fds.as_mut_ptr()) }, 0);
+    let (reader, _writer) = unsafe {
+        (
+            std::fs::File::from_raw_fd(fds[0]),
+            std::fs::File::from_raw_fd(fds[1]),
+        )
+    };
+
+    let ring = rio::new().unwrap();
+
+    let buf = &mut [0_u8; 8];
+    let err = ring
+        .read_at_with_flags(
+            &reader,
+            buf,
+            rio::CURRENT_POSITION,
+            libc::RWF_NOWAIT,
+            rio::Ordering::None,
+        )
+        .wait()
+        .unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::WouldBlock);
+}
+
+#[test]
+fn buffer_pool_recycles_registered_buffers() {
+    let file = std::fs::OpenOptions::new()
+        .create(true)
+        .read(true)
+        .write(true)
+        .truncate(true)
+        .open("buffer_pool_recycles_registered_buffers")
+        .unwrap();
+
+    let ring = rio::new().unwrap();
+    let pool =
+        rio::BufferPool::with_hugepages(&ring, 2, 100)
+            .unwrap();
+    assert_eq!(pool.buffer_len() % 4096, 0);
+
+    let mut first = pool.take().unwrap();
+    let mut second = pool.take().unwrap();
+    assert_ne!(first.index(), second.index());
+    assert!(pool.take().is_none());
+
+    first[..6].copy_from_slice(b"pooled");
+    let written =
+        first.write_fixed(&file, 0, 6, 0).wait().unwrap();
+    assert_eq!(written, 6);
+
+    let read =
+        second.read_fixed(&file, 0, 6, 0).wait().unwrap();
+    assert_eq!(&second[..read], b"pooled");
+
+    let index = second.index();
+    drop(second);
+    assert_eq!(pool.take().unwrap().index(), index);
+
+    // registering again is refused while the pool
+    // holds the registration.
+    assert!(rio::BufferPool::with_hugepages(&ring, 1, 100)
+        .is_err());
+    drop(first);
+    drop(pool);
+    rio::BufferPool::with_hugepages(&ring, 1, 100).unwrap();
+
+    std::fs::remove_file(
+        "buffer_pool_recycles_registered_buffers",
+    )
+    .unwrap();
+}
+
+#[test]
+fn flush_on_drop_syncs_files_written_through_ring() {
+    fn open_fds_to(path: &std::path::Path) -> usize {
+        std::fs::read_dir("/proc/self/fd")
+            .unwrap()
+            .filter_map(|entry| {
+                std::fs::read_link(entry.ok()?.path()).ok()
+            })
+            .filter(|target| target == path)
+            .count()
+    }
+
+    let path = std::env::current_dir().unwrap().join(
+        "flush_on_drop_syncs_files_written_through_ring",
+    );
+
+    let ring = rio::Config::builder()
+        .flush_on_drop(true)
+        .start()
+        .unwrap();
+
+    let file = std::fs::File::create(&path).unwrap();
+    ring.write_at(&file, b"durable", 0).wait().unwrap();
+    ring.write_at(&file, b"!", 7).wait().unwrap();
+    drop(file);
+
+    // the ring keeps one descriptor for the fsync.
+    assert_eq!(open_fds_to(&path), 1);
+
+    drop(ring);
+    assert_eq!(open_fds_to(&path), 0);
+    assert_eq!(std::fs::read(&path).unwrap(), b"durable!");
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn async_hint_hands_reads_to_worker_threads() {
+    // io_uring names each of its workers after the
+    // thread whose submissions it runs.
+    fn workers_of_this_thread() -> usize {
+        let worker = format!("iou-wrk-{}", unsafe {
+            libc::gettid()
+        });
+        std::fs::read_dir("/proc/self/task")
+            .unwrap()
+            .filter_map(|task| {
+                std::fs::read_to_string(
+                    task.ok()?.path().join("comm"),
+                )
+                .ok()
+            })
+            .filter(|comm| comm.trim_end() == worker)
+            .count()
+    }
+
+    std::fs::write(
+        "async_hint_hands_reads_to_worker_threads",
+        b"offloaded",
+    )
+    .unwrap();
+    let file = std::fs::File::open(
+        "async_hint_hands_reads_to_worker_threads",
+    )
+    .unwrap();
+
+    let ring = rio::new().unwrap();
+
+    let buf = &mut [0_u8; 9];
+    let read = ring.read_at(&file, buf, 0).wait().unwrap();
+    assert_eq!(read, 9);
+    assert_eq!(workers_of_this_thread(), 0);
+
+    let read = ring
+        .read_at(&file, buf, 0)
+        .async_hint()
+        .wait()
+        .unwrap();
+    assert_eq!(read, 9);
+    assert_eq!(buf, b"offloaded");
+    assert!(workers_of_this_thread() > 0);
+
+    std::fs::remove_file(
+        "async_hint_hands_reads_to_worker_threads",
+    )
+    .unwrap();
+}
+
+#[test]
+fn owned_buffers_are_handed_back() {
+    let file = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open("owned_buffers_are_handed_back")
+        .unwrap();
+    let ring = rio::new().unwrap();
+
+    let task = {
+        let ring = ring.clone();
+        async move {
+            let (res, written) = ring
+                .write(&file, b"owned".to_vec(), 0)
+                .await;
+            assert_eq!(res.unwrap(), 5);
+            assert_eq!(written, b"owned");
+
+            let (res, read) =
+                ring.read(&file, vec![0; 8], 0).await;
+            assert_eq!(res.unwrap(), 5);
+            read
+        }
+    };
+    drop(ring);
+    let read = extreme::run(task);
+    assert_eq!(&read[..5], b"owned");
+
+    std::fs::remove_file("owned_buffers_are_handed_back")
+        .unwrap();
+}
+
+#[test]
+fn ftruncate_changes_file_length() {
+    let file = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open("ftruncate_changes_file_length")
+        .unwrap();
+    let ring = rio::new().unwrap();
+    ring.write_at(&file, b"0123456789", 0).wait().unwrap();
+
+    ring.ftruncate(&file, 3).wait().unwrap();
+    assert_eq!(file.metadata().unwrap().len(), 3);
+
+    ring.ftruncate(&file, 4096).wait().unwrap();
+    assert_eq!(file.metadata().unwrap().len(), 4096);
+
+    std::fs::remove_file("ftruncate_changes_file_length")
+        .unwrap();
+}
+
+#[test]
+fn openat2_keeps_paths_beneath_root() {
+    use std::ffi::CString;
+
+    let root = "openat2_keeps_paths_beneath_root";
+    std::fs::create_dir_all(root).unwrap();
+    std::fs::write(format!("{}/inner", root), b"inner")
+        .unwrap();
+    let _ = std::fs::remove_file(format!("{}/link", root));
+    std::os::unix::fs::symlink(
+        "inner",
+        format!("{}/link", root),
+    )
+    .unwrap();
+    let dir = std::fs::File::open(root).unwrap();
+
+    let ring = rio::new().unwrap();
+    let open = |path: &str, resolve: u64| {
+        let path = CString::new(path).unwrap();
+        let how = rio::OpenHow {
+            flags: (libc::O_RDONLY | libc::O_CLOEXEC)
+                as u64,
+            resolve,
+            ..rio::OpenHow::default()
+        };
+        ring.openat2(&dir, &path, &how).wait()
+    };
+
+    let fd = open("inner", libc::RESOLVE_BENEATH).unwrap();
+    let file = unsafe { std::fs::File::from_raw_fd(fd) };
+    assert_eq!(file.metadata().unwrap().len(), 5);
+
+    let err = open("../Cargo.toml", libc::RESOLVE_BENEATH)
+        .unwrap_err();
+    assert_eq!(err.raw_os_error(), Some(libc::EXDEV));
+
+    let err = open("link", libc::RESOLVE_NO_SYMLINKS)
+        .unwrap_err();
+    assert_eq!(err.raw_os_error(), Some(libc::ELOOP));
+
+    let err = open("inner", 1 << 40).unwrap_err();
+    assert_eq!(
+        err.kind(),
+        std::io::ErrorKind::InvalidInput
+    );
+
+    std::fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn fsync_with_syncs_data_or_metadata() {
+    let file = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open("fsync_with_syncs_data_or_metadata")
+        .unwrap();
+    let ring = rio::new().unwrap();
+    ring.write_at(&file, b"durable", 0).wait().unwrap();
+
+    ring.fsync_with(&file, true).wait().unwrap();
+    ring.fsync_with(&file, false).wait().unwrap();
+    // the datasync flag used to be set as an sqe
+    // flag, which made the kernel look the file up
+    // among the registered ones instead.
+    ring.fdatasync(&file).wait().unwrap();
+    ring.fsync(&file).wait().unwrap();
+
+    std::fs::remove_file(
+        "fsync_with_syncs_data_or_metadata",
+    )
+    .unwrap();
+}
+
+#[test]
+fn register_files_sparse_reserves_empty_slots() {
+    std::fs::write(
+        "register_files_sparse_reserves_empty_slots",
+        b"sparse",
+    )
+    .unwrap();
+    let file = std::fs::File::open(
+        "register_files_sparse_reserves_empty_slots",
+    )
+    .unwrap();
+
+    let ring = rio::new().unwrap();
+
+    ring.register_files_sparse(4).unwrap();
+    assert_eq!(ring.registered_files(), vec![-1; 4]);
+
+    let fds = [file.as_raw_fd()];
+    ring.files_update(2, &fds).wait().unwrap();
+
+    let buf = &mut [0_u8; 6];
+    let read = ring
+        .read_at(&rio::FixedFile(2), buf, 0)
+        .wait()
+        .unwrap();
+    assert_eq!(read, 6);
+    assert_eq!(buf, b"sparse");
+
+    // empty slots can't be read from.
+    let err = ring
+        .read_at(&rio::FixedFile(0), buf, 0)
+        .wait()
+        .unwrap_err();
+    assert_eq!(err.raw_os_error(), Some(libc::EBADF));
+
+    ring.unregister_files().unwrap();
+    std::fs::remove_file(
+        "register_files_sparse_reserves_empty_slots",
+    )
+    .unwrap();
+}
+
+#[test]
+fn owned_file_futures_outlive_their_handles() {
+    use std::sync::Arc;
+
+    let file = Arc::new(
+        std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(
+                "owned_file_futures_outlive_their_handles",
+            )
+            .unwrap(),
+    );
+    let ring = rio::new().unwrap();
+
+    let write = ring.write_owned(
+        file.clone(),
+        b"shared".to_vec(),
+        0,
+    );
+    let read = ring.read_owned(file.clone(), vec![0; 6], 0);
+    drop(ring);
+    drop(file);
+
+    // the futures hold everything they need, so
+    // they can move to another thread.
+    let read = std::thread::spawn(move || {
+        let (res, _) = extreme::run(write);
+        assert_eq!(res.unwrap(), 6);
+        extreme::run(read)
+    })
+    .join()
+    .unwrap();
+    assert_eq!(read.0.unwrap(), 6);
+    assert_eq!(read.1, b"shared");
+
+    std::fs::remove_file(
+        "owned_file_futures_outlive_their_handles",
+    )
+    .unwrap();
+}
+
+#[test]
+fn writev_slices_writes_chunks_in_order() {
+    let file = std::fs::OpenOptions::new()
+        .create(true)
+        .read(true)
+        .write(true)
+        .truncate(true)
+        .open("writev_slices_writes_chunks_in_order")
+        .unwrap();
+
+    let ring = rio::new().unwrap();
+
+    let header = b"len=4\n".to_vec();
+    let body = b"body".to_vec();
+    let written = {
+        // the array of chunks may go away before the
+        // write is done.
+        let chunks: Vec<&[u8]> =
+            vec![&header, b"\n", &body];
+        ring.writev_slices(&file, &chunks, 0)
+    }
+    .wait()
+    .unwrap();
+    assert_eq!(written, 11);
+
+    assert_eq!(
+        std::fs::read(
+            "writev_slices_writes_chunks_in_order"
+        )
+        .unwrap(),
+        b"len=4\n\nbody"
+    );
+
+    std::fs::remove_file(
+        "writev_slices_writes_chunks_in_order",
+    )
+    .unwrap();
+}
+
+#[test]
+fn submit_raw_issues_unwrapped_opcodes() {
+    use std::os::unix::io::AsRawFd;
+
+    std::fs::write(
+        "submit_raw_issues_unwrapped_opcodes",
+        b"raw",
+    )
+    .unwrap();
+    let file = std::fs::File::open(
+        "submit_raw_issues_unwrapped_opcodes",
+    )
+    .unwrap();
+
+    let ring = rio::new().unwrap();
+
+    let buf = &mut [0_u8; 8];
+    let read = unsafe {
+        ring.submit_raw(|sqe| {
+            sqe.opcode = rio::opcode::IORING_OP_READ;
+            sqe.fd = file.as_raw_fd();
+            sqe.addr = buf.as_mut_ptr() as u64;
+            sqe.len = buf.len() as u32;
+            // rio keeps track of the operation by
+            // this, so it is put back.
+            sqe.user_data = 0;
+        })
+    };
+    assert_eq!(read.wait().unwrap(), 3);
+    assert_eq!(&buf[..3], b"raw");
+
+    let err = unsafe {
+        ring.submit_raw(|sqe| {
+            sqe.opcode = rio::opcode::IORING_OP_READ;
+            sqe.fd = -1;
+        })
+    }
+    .wait()
+    .unwrap_err();
+    assert_eq!(err.raw_os_error(), Some(libc::EBADF));
+
+    std::fs::remove_file(
+        "submit_raw_issues_unwrapped_opcodes",
+    )
+    .unwrap();
+}
@@ -0,0 +1,1013 @@
+use std::net::{TcpListener, TcpStream};
+
+#[test]
+fn accept_multi_yields_each_connection() {
+    let ring = rio::new().unwrap();
+
+    let listener =
+        TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let mut accepted = ring.accept_multi(&listener);
+
+    let _first = TcpStream::connect(addr).unwrap();
+    let _second = TcpStream::connect(addr).unwrap();
+
+    let first_fd = accepted.next().unwrap().unwrap();
+    let second_fd = accepted.next().unwrap().unwrap();
+    assert_ne!(first_fd, second_fd);
+
+    unsafe {
+        libc::close(first_fd);
+        libc::close(second_fd);
+    }
+}
+
+#[test]
+fn recv_multi_yields_each_arrival() {
+    use std::io::Write;
+
+    let ring = rio::new().unwrap();
+
+    let listener =
+        TcpListener::bind("127.0.0.1:0").unwrap();
+    let mut client =
+        TcpStream::connect(listener.local_addr().unwrap())
+            .unwrap();
+    let (server, _) = listener.accept().unwrap();
+
+    let pool = vec![0_u8; 4 * 16];
+    unsafe {
+        ring.provide_buffers(&pool, 4, 3, 0)
+            .wait()
+            .unwrap();
+    }
+
+    let mut received = ring.recv_multi(&server, 3);
+
+    client.write_all(b"ping").unwrap();
+    let (id, len) = received.next().unwrap().unwrap();
+    let start = usize::from(id) * 16;
+    assert_eq!(&pool[start..start + len], b"ping");
+
+    client.write_all(b"pong").unwrap();
+    let (id, len) = received.next().unwrap().unwrap();
+    let start = usize::from(id) * 16;
+    assert_eq!(&pool[start..start + len], b"pong");
+    assert!(received.is_armed());
+
+    drop(client);
+    let (_, len) = received.next().unwrap().unwrap();
+    assert_eq!(len, 0);
+    assert!(!received.is_armed());
+    assert!(received.next().is_none());
+}
+
+#[test]
+fn send_zc_completes_after_notification() {
+    use std::io::Read;
+
+    let ring = rio::new().unwrap();
+
+    let listener =
+        TcpListener::bind("127.0.0.1:0").unwrap();
+    let client =
+        TcpStream::connect(listener.local_addr().unwrap())
+            .unwrap();
+    let (mut server, _) = listener.accept().unwrap();
+
+    let buf = vec![7_u8; 4096];
+    let sent =
+        ring.send_zc(&client, &buf, 0).wait().unwrap();
+    assert_eq!(sent, buf.len());
+
+    let mut received = vec![0_u8; 4096];
+    server.read_exact(&mut received).unwrap();
+    assert_eq!(received, buf);
+}
+
+#[test]
+fn cancel_pending_recv() {
+    let ring = rio::new().unwrap();
+
+    let listener =
+        TcpListener::bind("127.0.0.1:0").unwrap();
+    let _client =
+        TcpStream::connect(listener.local_addr().unwrap())
+            .unwrap();
+    let (server, _) = listener.accept().unwrap();
+
+    let buf = vec![0_u8; 16];
+    let pending = ring.recv(&server, &buf);
+
+    pending.cancel().wait().unwrap();
+
+    let err = pending.wait().unwrap_err();
+    assert_eq!(err.raw_os_error(), Some(libc::ECANCELED));
+
+    let done = ring.nop();
+    let cancel = done.cancel();
+    done.wait().unwrap();
+    let err = cancel.wait().unwrap_err();
+    assert_eq!(err.raw_os_error(), Some(libc::ENOENT));
+}
+
+#[test]
+fn cancel_on_drop_releases_pending_recv() {
+    let ring = rio::new().unwrap();
+
+    let listener =
+        TcpListener::bind("127.0.0.1:0").unwrap();
+    let _client =
+        TcpStream::connect(listener.local_addr().unwrap())
+            .unwrap();
+    let (server, _) = listener.accept().unwrap();
+
+    let buf = vec![0_u8; 16];
+    let pending = ring.recv(&server, &buf).cancel_on_drop();
+
+    // without cancel_on_drop, this would block until
+    // the client sends something.
+    drop(pending);
+
+    // the buffer is free again and the ring still
+    // works.
+    drop(buf);
+    ring.nop().wait().unwrap();
+}
+
+#[test]
+fn cancel_by_token() {
+    let ring = rio::new().unwrap();
+
+    let listener =
+        TcpListener::bind("127.0.0.1:0").unwrap();
+    let _client =
+        TcpStream::connect(listener.local_addr().unwrap())
+            .unwrap();
+    let (server, _) = listener.accept().unwrap();
+
+    let buf = &mut [0_u8; 16];
+    let pending = ring.read_at(&server, buf, 0);
+    let token = pending.token();
+
+    ring.cancel(token).wait().unwrap();
+
+    let err = pending.wait().unwrap_err();
+    assert_eq!(err.raw_os_error(), Some(libc::ECANCELED));
+
+    // the token no longer matches once the
+    // operation completed.
+    let err = ring.cancel(token).wait().unwrap_err();
+    assert_eq!(err.raw_os_error(), Some(libc::ENOENT));
+}
+
+#[test]
+fn cancel_fd_cancels_every_operation_on_it() {
+    use std::os::unix::io::AsRawFd;
+
+    let ring = rio::new().unwrap();
+
+    let listener =
+        TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let _first_client = TcpStream::connect(addr).unwrap();
+    let (closing, _) = listener.accept().unwrap();
+    let _second_client = TcpStream::connect(addr).unwrap();
+    let (other, _) = listener.accept().unwrap();
+
+    let (first_buf, second_buf, other_buf) =
+        (vec![0_u8; 16], vec![0_u8; 16], vec![0_u8; 16]);
+    let first = ring.recv(&closing, &first_buf);
+    let second = ring.recv(&closing, &second_buf);
+    let untouched = ring.recv(&other, &other_buf);
+
+    let canceled =
+        ring.cancel_fd(closing.as_raw_fd()).wait().unwrap();
+    assert_eq!(canceled, 2);
+
+    for pending in [first, second] {
+        let err = pending.wait().unwrap_err();
+        assert_eq!(
+            err.raw_os_error(),
+            Some(libc::ECANCELED)
+        );
+    }
+
+    untouched.cancel().wait().unwrap();
+    let err = untouched.wait().unwrap_err();
+    assert_eq!(err.raw_os_error(), Some(libc::ECANCELED));
+
+    let canceled =
+        ring.cancel_fd(closing.as_raw_fd()).wait().unwrap();
+    assert_eq!(canceled, 0);
+}
+
+#[test]
+fn accept_direct_installs_into_registered_slots() {
+    use std::io::Write;
+    use std::os::unix::io::AsRawFd;
+
+    let ring = rio::new().unwrap();
+
+    let listener =
+        TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let mut first_client =
+        TcpStream::connect(addr).unwrap();
+    let mut second_client =
+        TcpStream::connect(addr).unwrap();
+
+    ring.register_files(&[-1, -1]).unwrap();
+
+    let given = ring
+        .accept_direct(&listener, rio::FileSlot::Index(1))
+        .wait()
+        .unwrap();
+    assert_eq!(given, 1);
+    let picked = ring
+        .accept_direct(&listener, rio::FileSlot::Alloc)
+        .wait()
+        .unwrap();
+    assert_eq!(picked, 0);
+    assert_eq!(
+        ring.registered_files(),
+        vec![
+            rio::DIRECT_DESCRIPTOR,
+            rio::DIRECT_DESCRIPTOR
+        ]
+    );
+
+    first_client.write_all(b"first").unwrap();
+    second_client.write_all(b"second").unwrap();
+    let buf = vec![0_u8; 16];
+    let read = ring
+        .recv(&rio::FixedFile(given), &buf)
+        .wait()
+        .unwrap();
+    assert_eq!(&buf[..read], b"first");
+    let read = ring
+        .recv(&rio::FixedFile(picked), &buf)
+        .wait()
+        .unwrap();
+    assert_eq!(&buf[..read], b"second");
+
+    ring.close_direct(given).wait().unwrap();
+    ring.close_direct(picked).wait().unwrap();
+    assert_eq!(ring.registered_files(), vec![-1, -1]);
+
+    // a failed accept leaves the table alone.
+    let registered = [first_client.as_raw_fd()];
+    ring.files_update(0, &registered).wait().unwrap();
+    let pending = ring
+        .accept_direct(&listener, rio::FileSlot::Index(0));
+    pending.cancel().wait().unwrap();
+    let err = pending.wait().unwrap_err();
+    assert_eq!(err.raw_os_error(), Some(libc::ECANCELED));
+    assert_eq!(
+        ring.registered_files(),
+        vec![registered[0], -1]
+    );
+}
+
+#[test]
+fn recv_buf_select_fills_provided_buffer() {
+    use std::io::Write;
+    use std::os::unix::io::AsRawFd;
+
+    let ring = rio::new().unwrap();
+
+    let listener =
+        TcpListener::bind("127.0.0.1:0").unwrap();
+    let mut client =
+        TcpStream::connect(listener.local_addr().unwrap())
+            .unwrap();
+    let (server, _) = listener.accept().unwrap();
+
+    let pool = vec![0_u8; 2 * 8];
+    unsafe {
+        ring.provide_buffers(&pool, 2, 3, 0)
+            .wait()
+            .unwrap();
+    }
+
+    client.write_all(b"selected").unwrap();
+    let (id, received) =
+        ring.recv_buf_select(&server, 8, 3).wait().unwrap();
+    assert_eq!(received, 8);
+    let start = usize::from(id) * 8;
+    assert_eq!(&pool[start..start + 8], b"selected");
+
+    // registered sockets select buffers the same way.
+    ring.register_files(&[server.as_raw_fd()]).unwrap();
+    client.write_all(b"fixed").unwrap();
+    let (other_id, received) = ring
+        .recv_buf_select(&rio::FixedFile(0), 8, 3)
+        .wait()
+        .unwrap();
+    assert_eq!(received, 5);
+    assert_ne!(other_id, id);
+    let start = usize::from(other_id) * 8;
+    assert_eq!(&pool[start..start + 5], b"fixed");
+}
+
+#[test]
+fn sendmsg_passes_fd_over_unix_socket() {
+    use std::{
+        io::{IoSlice, IoSliceMut, Read, Write},
+        os::unix::{
+            io::{AsRawFd, FromRawFd},
+            net::UnixStream,
+        },
+    };
+
+    #[repr(align(8))]
+    struct Control([u8; 64]);
+
+    let ring = rio::new().unwrap();
+
+    let (left, right) = UnixStream::pair().unwrap();
+    let (mut passed_in, passed_out) =
+        UnixStream::pair().unwrap();
+
+    let fd_len = std::mem::size_of::<i32>() as u32;
+    let space =
+        unsafe { libc::CMSG_SPACE(fd_len) } as usize;
+
+    let mut send_control = Control([0; 64]);
+    unsafe {
+        let mut header: libc::msghdr = std::mem::zeroed();
+        header.msg_control =
+            send_control.0.as_mut_ptr() as _;
+        header.msg_controllen = space;
+        let cmsg = libc::CMSG_FIRSTHDR(&header);
+        (*cmsg).cmsg_level = libc::SOL_SOCKET;
+        (*cmsg).cmsg_type = libc::SCM_RIGHTS;
+        (*cmsg).cmsg_len = libc::CMSG_LEN(fd_len) as usize;
+        *(libc::CMSG_DATA(cmsg) as *mut i32) =
+            passed_out.as_raw_fd();
+    }
+
+    let data = [IoSlice::new(b"fd")];
+    let sent = rio::Msghdr::new(&data)
+        .control(&send_control.0[..space]);
+    assert_eq!(
+        ring.sendmsg(&left, &sent).wait().unwrap(),
+        2
+    );
+
+    let mut buf = [0_u8; 2];
+    let mut recv_control = Control([0; 64]);
+    let mut data = [IoSliceMut::new(&mut buf)];
+    let mut received = rio::MsghdrMut::new(&mut data)
+        .control(&mut recv_control.0);
+    assert_eq!(
+        ring.recvmsg(&right, &mut received).wait().unwrap(),
+        2
+    );
+    assert_eq!(received.flags() & libc::MSG_CTRUNC, 0);
+    assert_eq!(received.control_len(), space);
+    assert_eq!(&buf, b"fd");
+
+    let fd = unsafe {
+        let mut header: libc::msghdr = std::mem::zeroed();
+        header.msg_control =
+            recv_control.0.as_mut_ptr() as _;
+        header.msg_controllen = space;
+        let cmsg = libc::CMSG_FIRSTHDR(&header);
+        assert_eq!((*cmsg).cmsg_type, libc::SCM_RIGHTS);
+        *(libc::CMSG_DATA(cmsg) as *const i32)
+    };
+    let mut passed = unsafe { UnixStream::from_raw_fd(fd) };
+    drop(passed_out);
+
+    passed.write_all(b"hi").unwrap();
+    let mut greeting = [0_u8; 2];
+    passed_in.read_exact(&mut greeting).unwrap();
+    assert_eq!(&greeting, b"hi");
+}
+
+#[test]
+fn shutdown_linked_after_send() {
+    use std::io::Read;
+
+    let ring = rio::new().unwrap();
+
+    let listener =
+        TcpListener::bind("127.0.0.1:0").unwrap();
+    let client =
+        TcpStream::connect(listener.local_addr().unwrap())
+            .unwrap();
+    let (mut server, _) = listener.accept().unwrap();
+
+    let send = ring.send_ordered(
+        &client,
+        b"bye",
+        rio::Ordering::Link,
+    );
+    let shutdown = ring.shutdown(&client, libc::SHUT_WR);
+    assert_eq!(send.wait().unwrap(), 3);
+    shutdown.wait().unwrap();
+
+    let err =
+        ring.shutdown(&client, -1).wait().unwrap_err();
+    assert_eq!(err.raw_os_error(), Some(libc::EINVAL));
+
+    let mut received = vec![];
+    server.read_to_end(&mut received).unwrap();
+    assert_eq!(received, b"bye");
+}
+
+#[test]
+fn socket_creates_stream_socket() {
+    let ring = rio::new().unwrap();
+
+    let fd = ring
+        .socket(
+            libc::AF_INET,
+            libc::SOCK_STREAM | libc::SOCK_CLOEXEC,
+            0,
+            0,
+        )
+        .wait()
+        .unwrap();
+
+    let mut socket_type = 0_i32;
+    let mut len =
+        std::mem::size_of::<i32>() as libc::socklen_t;
+    let ret = unsafe {
+        libc::getsockopt(
+            fd,
+            libc::SOL_SOCKET,
+            libc::SO_TYPE,
+            (&mut socket_type as *mut i32).cast(),
+            &mut len,
+        )
+    };
+    assert_eq!(ret, 0);
+    assert_eq!(socket_type, libc::SOCK_STREAM);
+
+    ring.close(fd).wait().unwrap();
+
+    let err = ring
+        .socket(libc::AF_INET, -1, 0, 0)
+        .wait()
+        .unwrap_err();
+    assert_eq!(err.raw_os_error(), Some(libc::EINVAL));
+}
+
+#[test]
+fn awaited_read_is_woken_by_reaper() {
+    use std::io::Write;
+
+    let ring = rio::new().unwrap();
+
+    let listener =
+        TcpListener::bind("127.0.0.1:0").unwrap();
+    let mut client =
+        TcpStream::connect(listener.local_addr().unwrap())
+            .unwrap();
+    let (server, _) = listener.accept().unwrap();
+
+    let sender = std::thread::spawn(move || {
+        std::thread::sleep(
+            std::time::Duration::from_millis(50),
+        );
+        client.write_all(b"late").unwrap();
+        client
+    });
+
+    let buf = &mut [0_u8; 4];
+    let received = extreme::run(async {
+        let first = ring.read_at(&server, buf, 0);
+        let nop = ring.nop();
+        nop.await.unwrap();
+        first.await.unwrap()
+    });
+    assert_eq!(received, 4);
+    assert_eq!(buf, b"late");
+
+    drop(sender.join().unwrap());
+}
+
+#[test]
+fn drop_cancels_pending_read() {
+    use std::io::Write;
+
+    let listener =
+        TcpListener::bind("127.0.0.1:0").unwrap();
+    let mut client =
+        TcpStream::connect(listener.local_addr().unwrap())
+            .unwrap();
+    let (server, _) = listener.accept().unwrap();
+
+    let buf = &mut [0_u8; 4];
+
+    let ring = rio::new().unwrap();
+    let read = ring.read_at(&server, buf, 0);
+    ring.submit_all().unwrap();
+
+    // nothing is ever going to wait on the read, so
+    // dropping the ring has to cancel it instead.
+    std::mem::forget(read);
+    drop(ring);
+
+    client.write_all(b"late").unwrap();
+    std::thread::sleep(std::time::Duration::from_millis(
+        50,
+    ));
+    assert_eq!(buf, &[0; 4]);
+}
+
+#[test]
+fn accept_returns_peer_addr() {
+    let ring = rio::new().unwrap();
+
+    for local in &["127.0.0.1:0", "[::1]:0"] {
+        let listener = match TcpListener::bind(local) {
+            Ok(listener) => listener,
+            // no IPv6 loopback here
+            Err(_) => continue,
+        };
+        let client = TcpStream::connect(
+            listener.local_addr().unwrap(),
+        )
+        .unwrap();
+
+        let (stream, peer) =
+            ring.accept(&listener).wait().unwrap();
+        assert_eq!(peer, client.local_addr().unwrap());
+        assert_eq!(
+            stream.peer_addr().unwrap(),
+            client.local_addr().unwrap()
+        );
+    }
+}
+
+#[test]
+fn accept_fd_returns_connection() {
+    use std::os::unix::io::FromRawFd;
+
+    let ring = rio::new().unwrap();
+
+    let listener =
+        TcpListener::bind("127.0.0.1:0").unwrap();
+    let client =
+        TcpStream::connect(listener.local_addr().unwrap())
+            .unwrap();
+
+    let fd = ring
+        .accept_fd(&listener, libc::SOCK_CLOEXEC)
+        .wait()
+        .unwrap();
+    let stream = unsafe { TcpStream::from_raw_fd(fd) };
+    assert_eq!(
+        stream.peer_addr().unwrap(),
+        client.local_addr().unwrap()
+    );
+}
+
+#[test]
+fn recv_with_peek_leaves_data_queued() {
+    use std::io::Write;
+
+    let ring = rio::new().unwrap();
+
+    let listener =
+        TcpListener::bind("127.0.0.1:0").unwrap();
+    let mut client =
+        TcpStream::connect(listener.local_addr().unwrap())
+            .unwrap();
+    let (server, _) = listener.accept().unwrap();
+
+    client.write_all(b"peek").unwrap();
+
+    let peeked = &mut [0_u8; 4];
+    let n = ring
+        .recv_with_flags(
+            &server,
+            peeked,
+            libc::MSG_PEEK | libc::MSG_WAITALL,
+            rio::Ordering::None,
+        )
+        .wait()
+        .unwrap();
+    assert_eq!(n, 4);
+    assert_eq!(peeked, b"peek");
+
+    let received = &mut [0_u8; 4];
+    assert_eq!(
+        ring.recv(&server, received).wait().unwrap(),
+        4
+    );
+    assert_eq!(received, b"peek");
+
+    // nothing left, so a non-blocking receive fails
+    // right away.
+    let err = ring
+        .recv_with_flags(
+            &server,
+            received,
+            libc::MSG_DONTWAIT,
+            rio::Ordering::None,
+        )
+        .wait()
+        .unwrap_err();
+    assert_eq!(err.raw_os_error(), Some(libc::EAGAIN));
+}
+
+#[test]
+fn accept_with_flags_sets_nonblock() {
+    use std::os::unix::io::AsRawFd;
+
+    let ring = rio::new().unwrap();
+
+    let listener =
+        TcpListener::bind("127.0.0.1:0").unwrap();
+    let _client =
+        TcpStream::connect(listener.local_addr().unwrap())
+            .unwrap();
+
+    let (stream, _) = ring
+        .accept_with_flags(
+            &listener,
+            libc::SOCK_NONBLOCK | libc::SOCK_CLOEXEC,
+        )
+        .wait()
+        .unwrap();
+
+    let fd = stream.as_raw_fd();
+    let status = unsafe { libc::fcntl(fd, libc::F_GETFL) };
+    assert_ne!(status & libc::O_NONBLOCK, 0);
+    let fd_flags =
+        unsafe { libc::fcntl(fd, libc::F_GETFD) };
+    assert_ne!(fd_flags & libc::FD_CLOEXEC, 0);
+}
+
+#[test]
+fn socket_then_connect_to_socket_addr() {
+    use std::os::unix::io::FromRawFd;
+
+    let ring = rio::new().unwrap();
+
+    for (local, domain) in &[
+        ("127.0.0.1:0", libc::AF_INET),
+        ("[::1]:0", libc::AF_INET6),
+    ] {
+        let listener = match TcpListener::bind(local) {
+            Ok(listener) => listener,
+            // no IPv6 loopback here
+            Err(_) => continue,
+        };
+        let addr = listener.local_addr().unwrap();
+
+        let fd = ring
+            .socket(
+                *domain,
+                libc::SOCK_STREAM | libc::SOCK_CLOEXEC,
+                0,
+                0,
+            )
+            .wait()
+            .unwrap();
+        let client = unsafe { TcpStream::from_raw_fd(fd) };
+
+        ring.connect(&client, addr).wait().unwrap();
+        assert_eq!(client.peer_addr().unwrap(), addr);
+
+        let (_, peer) = listener.accept().unwrap();
+        assert_eq!(peer, client.local_addr().unwrap());
+    }
+}
+
+#[test]
+fn connect_raw_reaches_unix_socket() {
+    use std::os::unix::{
+        io::FromRawFd,
+        net::{UnixListener, UnixStream},
+    };
+
+    let path = "connect_raw_reaches_unix_socket";
+    let _ = std::fs::remove_file(path);
+    let listener = UnixListener::bind(path).unwrap();
+
+    let ring = rio::new().unwrap();
+
+    let mut addr: libc::sockaddr_un =
+        unsafe { std::mem::zeroed() };
+    addr.sun_family = libc::AF_UNIX as libc::sa_family_t;
+    for (dst, src) in
+        addr.sun_path.iter_mut().zip(path.bytes())
+    {
+        *dst = src as libc::c_char;
+    }
+
+    let fd = ring
+        .socket(libc::AF_UNIX, libc::SOCK_STREAM, 0, 0)
+        .wait()
+        .unwrap();
+    let client = unsafe { UnixStream::from_raw_fd(fd) };
+
+    ring.connect_raw(&client, &addr, rio::Ordering::None)
+        .wait()
+        .unwrap();
+    drop(listener.accept().unwrap());
+
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn with_flags_reports_sock_nonempty() {
+    use std::io::Write;
+
+    let ring = rio::new().unwrap();
+
+    let listener =
+        TcpListener::bind("127.0.0.1:0").unwrap();
+    let mut client =
+        TcpStream::connect(listener.local_addr().unwrap())
+            .unwrap();
+    let (server, _) = listener.accept().unwrap();
+
+    client.write_all(b"more than fits").unwrap();
+
+    let received = &mut [0_u8; 4];
+    let first = ring
+        .recv_with_flags(
+            &server,
+            received,
+            libc::MSG_WAITALL,
+            rio::Ordering::None,
+        )
+        .with_flags()
+        .wait()
+        .unwrap();
+    assert_eq!(first.result, 4);
+    assert_ne!(
+        first.flags
+            & rio::cqe_flags::IORING_CQE_F_SOCK_NONEMPTY,
+        0
+    );
+
+    let rest = &mut [0_u8; 64];
+    let last =
+        extreme::run(ring.recv(&server, rest).with_flags())
+            .unwrap();
+    assert_eq!(&rest[..last.result], b" than fits");
+    assert_eq!(
+        last.flags
+            & rio::cqe_flags::IORING_CQE_F_SOCK_NONEMPTY,
+        0
+    );
+}
+
+#[test]
+fn accept_multi_outgrows_small_completion_queue() {
+    let ring =
+        rio::Config::builder().depth(1).start().unwrap();
+
+    let listener =
+        TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    // far more connections than the ring has room for
+    // completions. the kernel may stop the operation
+    // instead of holding them back, so it is rearmed
+    // until every connection was accepted.
+    let clients: Vec<TcpStream> = (0..64)
+        .map(|_| TcpStream::connect(addr).unwrap())
+        .collect();
+
+    let mut accepted = 0;
+    while accepted < clients.len() {
+        let remaining = clients.len() - accepted;
+        for fd in
+            ring.accept_multi(&listener).take(remaining)
+        {
+            unsafe {
+                libc::close(fd.unwrap());
+            }
+            accepted += 1;
+        }
+    }
+    assert_eq!(accepted, clients.len());
+}
+
+#[test]
+fn sendfile_moves_file_into_socket() {
+    use std::io::Read;
+
+    let data: Vec<u8> = (0..3_000_000_u32)
+        .map(|i| (i % 251) as u8)
+        .collect();
+    std::fs::write(
+        "sendfile_moves_file_into_socket",
+        &data,
+    )
+    .unwrap();
+    let file = std::fs::File::open(
+        "sendfile_moves_file_into_socket",
+    )
+    .unwrap();
+
+    let ring = rio::new().unwrap();
+
+    let listener =
+        TcpListener::bind("127.0.0.1:0").unwrap();
+    let mut client =
+        TcpStream::connect(listener.local_addr().unwrap())
+            .unwrap();
+    let (server, _) = listener.accept().unwrap();
+
+    let reader = std::thread::spawn(move || {
+        let mut received = vec![];
+        client.read_to_end(&mut received).unwrap();
+        received
+    });
+
+    // each call moves at most a pipe's worth, and the
+    // last one stops at the end of the file.
+    let mut sent = 0;
+    while sent < data.len() {
+        let moved = ring
+            .sendfile(
+                &file,
+                &server,
+                sent as u64,
+                data.len(),
+            )
+            .wait()
+            .unwrap();
+        assert!(moved > 0);
+        assert!(moved < data.len());
+        sent += moved;
+    }
+    assert_eq!(sent, data.len());
+
+    let moved = ring
+        .sendfile(&file, &server, sent as u64, 16)
+        .wait()
+        .unwrap();
+    assert_eq!(moved, 0);
+
+    drop(server);
+    assert_eq!(reader.join().unwrap(), data);
+
+    std::fs::remove_file("sendfile_moves_file_into_socket")
+        .unwrap();
+}
+
+#[test]
+fn buf_ring_recycles_buffers_for_recv_multi() {
+    use std::io::Write;
+
+    let ring = rio::new().unwrap();
+
+    let err = ring.register_buf_ring(5, 3).unwrap_err();
+    assert_eq!(
+        err.kind(),
+        std::io::ErrorKind::InvalidInput
+    );
+
+    let listener =
+        TcpListener::bind("127.0.0.1:0").unwrap();
+    let mut client =
+        TcpStream::connect(listener.local_addr().unwrap())
+            .unwrap();
+    let (server, _) = listener.accept().unwrap();
+
+    let bufs = ring.register_buf_ring(5, 2).unwrap();
+    assert_eq!(bufs.group_id(), 5);
+    assert_eq!(bufs.entries(), 2);
+
+    let mut storage = [[0_u8; 16]; 2];
+    for (bid, buf) in storage.iter_mut().enumerate() {
+        unsafe {
+            bufs.provide(bid as u16, buf);
+        }
+    }
+
+    let mut received = ring.recv_multi(&server, 5);
+
+    client.write_all(b"one").unwrap();
+    let (first, len) = received.next().unwrap().unwrap();
+    assert_eq!(&storage[usize::from(first)][..len], b"one");
+
+    client.write_all(b"two").unwrap();
+    let (second, len) = received.next().unwrap().unwrap();
+    assert_ne!(first, second);
+    assert_eq!(
+        &storage[usize::from(second)][..len],
+        b"two"
+    );
+
+    // with the ring empty, only the buffer that was
+    // handed back can take the next arrival.
+    unsafe {
+        bufs.provide(
+            first,
+            &mut storage[usize::from(first)],
+        );
+    }
+    client.write_all(b"three").unwrap();
+    let (third, len) = received.next().unwrap().unwrap();
+    assert_eq!(third, first);
+    assert_eq!(
+        &storage[usize::from(third)][..len],
+        b"three"
+    );
+
+    // even the end of the stream needs a buffer to
+    // be selected.
+    unsafe {
+        bufs.provide(
+            third,
+            &mut storage[usize::from(third)],
+        );
+    }
+    drop(client);
+    let (_, len) = received.next().unwrap().unwrap();
+    assert_eq!(len, 0);
+    assert!(received.next().is_none());
+}
+
+#[test]
+fn reserve_parks_until_completions_make_room() {
+    use std::{
+        io::Write,
+        time::{Duration, Instant},
+    };
+
+    let ring = rio::new().unwrap();
+    extreme::run(ring.reserve(1)).unwrap();
+
+    let listener =
+        TcpListener::bind("127.0.0.1:0").unwrap();
+    let mut client =
+        TcpStream::connect(listener.local_addr().unwrap())
+            .unwrap();
+    let (server, _) = listener.accept().unwrap();
+
+    let buf = vec![0_u8; 16];
+    let pending = ring.recv(&server, &buf);
+
+    let sender = std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_millis(50));
+        client.write_all(b"room").unwrap();
+        client
+    });
+
+    // asking for more than the whole ring waits for
+    // all of it, so this needs the recv to complete.
+    let start = Instant::now();
+    extreme::run(ring.reserve(usize::MAX)).unwrap();
+    assert!(start.elapsed() >= Duration::from_millis(50));
+    assert_eq!(pending.wait().unwrap(), 4);
+
+    drop(sender.join().unwrap());
+}
+
+#[test]
+fn msg_ring_hands_accepted_fd_to_other_ring() {
+    use std::os::unix::io::{FromRawFd, IntoRawFd};
+
+    let acceptor = rio::new().unwrap();
+    let worker = rio::new().unwrap();
+
+    let listener =
+        TcpListener::bind("127.0.0.1:0").unwrap();
+    let client =
+        TcpStream::connect(listener.local_addr().unwrap())
+            .unwrap();
+
+    let (server, _) =
+        acceptor.accept(&listener).wait().unwrap();
+    let fd = server.into_raw_fd();
+
+    let receiver = {
+        let worker = worker.clone();
+        std::thread::spawn(move || worker.next_message())
+    };
+    acceptor
+        .msg_ring(&worker, fd as u64, 7)
+        .wait()
+        .unwrap();
+    let (data, len) = receiver.join().unwrap();
+    assert_eq!((data, len), (fd as u64, 7));
+
+    let server =
+        unsafe { TcpStream::from_raw_fd(data as i32) };
+    assert_eq!(
+        server.peer_addr().unwrap(),
+        client.local_addr().unwrap()
+    );
+
+    // the worker keeps serving its own operations
+    // alongside messages.
+    acceptor.msg_ring(&worker, 42, 0).wait().unwrap();
+    worker.nop().wait().unwrap();
+    assert_eq!(
+        extreme::run(worker.next_message_async()),
+        (42, 0)
+    );
+}
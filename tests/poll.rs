@@ -0,0 +1,88 @@
+use std::os::unix::io::FromRawFd;
+
+fn eventfd() -> std::fs::File {
+    let fd =
+        unsafe { libc::eventfd(0, libc::EFD_NONBLOCK) };
+    assert!(fd >= 0);
+    unsafe { std::fs::File::from_raw_fd(fd) }
+}
+
+fn signal(eventfd: &std::fs::File) {
+    use std::io::Write;
+    (&*eventfd).write_all(&1_u64.to_ne_bytes()).unwrap();
+}
+
+#[test]
+fn poll_reports_readiness() {
+    let ring = rio::new().unwrap();
+    let event = eventfd();
+
+    let readable = ring.poll(&event, libc::POLLIN as u32);
+    signal(&event);
+
+    let ready = readable.wait().unwrap();
+    assert_ne!(ready & libc::POLLIN as u32, 0);
+}
+
+#[test]
+fn poll_remove_cancels_pending_poll() {
+    let ring = rio::new().unwrap();
+    let event = eventfd();
+
+    let readable = ring.poll(&event, libc::POLLIN as u32);
+    ring.poll_remove(&readable).wait().unwrap();
+
+    let err = readable.wait().unwrap_err();
+    assert_eq!(err.raw_os_error(), Some(libc::ECANCELED));
+}
+
+#[test]
+fn poll_multi_yields_each_readiness() {
+    use std::io::Read;
+
+    let ring = rio::new().unwrap();
+    let event = eventfd();
+
+    let mut readiness =
+        ring.poll_multi(&event, libc::POLLIN as u32);
+
+    for _ in 0..2 {
+        signal(&event);
+        let ready = readiness.next().unwrap().unwrap();
+        assert_ne!(ready & libc::POLLIN as u32, 0);
+
+        let mut counter = [0_u8; 8];
+        (&event).read_exact(&mut counter).unwrap();
+    }
+}
+
+#[test]
+fn poll_multi_stream_is_woken_from_async() {
+    use std::{io::Read, pin::Pin};
+
+    let ring = rio::new().unwrap();
+    let event = eventfd();
+    let signaller = event.try_clone().unwrap();
+
+    let mut readiness =
+        ring.poll_multi(&event, libc::POLLIN as u32);
+
+    let sender = std::thread::spawn(move || {
+        std::thread::sleep(
+            std::time::Duration::from_millis(20),
+        );
+        signal(&signaller);
+    });
+
+    let ready = extreme::run(std::future::poll_fn(|cx| {
+        Pin::new(&mut readiness).poll_next(cx)
+    }));
+    assert_ne!(
+        ready.unwrap().unwrap() & libc::POLLIN as u32,
+        0
+    );
+
+    let mut counter = [0_u8; 8];
+    (&event).read_exact(&mut counter).unwrap();
+    sender.join().unwrap();
+}
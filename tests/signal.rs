@@ -0,0 +1,44 @@
+extern "C" fn ignore(_: libc::c_int) {}
+
+#[test]
+fn signals_do_not_interrupt_submission() {
+    unsafe {
+        // without SA_RESTART, so that blocking
+        // syscalls fail with EINTR
+        let mut action: libc::sigaction =
+            std::mem::zeroed();
+        let handler: extern "C" fn(libc::c_int) = ignore;
+        action.sa_sigaction = handler as usize;
+        assert_eq!(
+            libc::sigaction(
+                libc::SIGURG,
+                &action,
+                std::ptr::null_mut()
+            ),
+            0
+        );
+    }
+
+    let ring = rio::new().unwrap();
+
+    let done = std::sync::Arc::new(
+        std::sync::atomic::AtomicBool::new(false),
+    );
+    let signaller_done = done.clone();
+    let signaller = std::thread::spawn(move || {
+        while !signaller_done
+            .load(std::sync::atomic::Ordering::Relaxed)
+        {
+            unsafe {
+                libc::kill(libc::getpid(), libc::SIGURG);
+            }
+        }
+    });
+
+    for _ in 0..10_000 {
+        ring.nop().wait().unwrap();
+    }
+
+    done.store(true, std::sync::atomic::Ordering::Relaxed);
+    signaller.join().unwrap();
+}
@@ -0,0 +1,250 @@
+use std::time::{Duration, Instant};
+
+#[test]
+fn timeout_waits_for_duration() {
+    let ring = rio::new().unwrap();
+
+    let start = Instant::now();
+    ring.timeout(Duration::from_millis(20)).wait().unwrap();
+    assert!(start.elapsed() >= Duration::from_millis(20));
+
+    let deadline =
+        Instant::now() + Duration::from_millis(20);
+    ring.timeout_at(deadline).wait().unwrap();
+    assert!(Instant::now() >= deadline);
+}
+
+#[test]
+fn timeout_remove_cancels_pending_timeout() {
+    let ring = rio::new().unwrap();
+
+    let timeout = ring.timeout(Duration::from_secs(60));
+    ring.timeout_remove(&timeout).wait().unwrap();
+
+    let err = timeout.wait().unwrap_err();
+    assert_eq!(err.raw_os_error(), Some(libc::ECANCELED));
+}
+
+#[test]
+fn with_timeout_bounds_pending_recv() {
+    use std::net::{TcpListener, TcpStream};
+
+    let ring = rio::new().unwrap();
+
+    let listener =
+        TcpListener::bind("127.0.0.1:0").unwrap();
+    let _client =
+        TcpStream::connect(listener.local_addr().unwrap())
+            .unwrap();
+    let (server, _) = listener.accept().unwrap();
+
+    let buf = vec![0_u8; 16];
+
+    let start = Instant::now();
+    let err = ring
+        .recv(&server, &buf)
+        .with_timeout(Duration::from_millis(20))
+        .wait()
+        .unwrap_err();
+    assert_eq!(err.raw_os_error(), Some(libc::ETIMEDOUT));
+    assert!(start.elapsed() >= Duration::from_millis(20));
+
+    // once submitted, the timeout can't be linked
+    // to the recv anymore.
+    let recv = ring.recv(&server, &buf);
+    ring.submit_all().unwrap();
+    let err = recv
+        .with_timeout(Duration::from_millis(20))
+        .wait()
+        .unwrap_err();
+    assert_eq!(err.raw_os_error(), Some(libc::ETIMEDOUT));
+
+    let done = ring
+        .nop()
+        .with_timeout(Duration::from_secs(60))
+        .wait();
+    assert!(done.is_ok());
+
+    // a timeout longer than the kernel can hold
+    // never fires.
+    let done = ring
+        .nop()
+        .with_timeout(Duration::from_secs(u64::MAX))
+        .wait();
+    assert!(done.is_ok());
+}
+
+#[test]
+fn with_timeout_keeps_earlier_links() {
+    std::fs::write("with_timeout_keeps_earlier_links", b"")
+        .unwrap();
+    // writing to a file opened for reading fails, which
+    // cancels whatever is linked to the write.
+    let file = std::fs::File::open(
+        "with_timeout_keeps_earlier_links",
+    )
+    .unwrap();
+
+    let ring = rio::new().unwrap();
+
+    let buf = vec![0_u8; 16];
+    let (write, fsync, nop) = ring
+        .batch(|ring| {
+            let write = ring.write_at_ordered(
+                &file,
+                &buf,
+                0,
+                rio::Ordering::Link,
+            );
+            let fsync = ring.fsync(&file);
+            let nop = ring.nop();
+            (
+                write,
+                fsync.with_timeout(Duration::from_secs(60)),
+                nop,
+            )
+        })
+        .unwrap();
+
+    let start = Instant::now();
+    let err = write.wait().unwrap_err();
+    assert_eq!(err.raw_os_error(), Some(libc::EBADF));
+    let err = fsync.wait().unwrap_err();
+    assert_eq!(err.raw_os_error(), Some(libc::ECANCELED));
+    nop.wait().unwrap();
+    assert!(start.elapsed() < Duration::from_secs(60));
+
+    // the last operation queued still gets a linked
+    // timeout, which passes its link on.
+    let (write, fsync) = ring
+        .batch(|ring| {
+            let write = ring
+                .write_at_ordered(
+                    &file,
+                    &buf,
+                    0,
+                    rio::Ordering::Link,
+                )
+                .with_timeout(Duration::from_secs(60));
+            (write, ring.fsync(&file))
+        })
+        .unwrap();
+    let err = write.wait().unwrap_err();
+    assert_eq!(err.raw_os_error(), Some(libc::EBADF));
+    let err = fsync.wait().unwrap_err();
+    assert_eq!(err.raw_os_error(), Some(libc::ECANCELED));
+
+    std::fs::remove_file(
+        "with_timeout_keeps_earlier_links",
+    )
+    .unwrap();
+}
+
+#[test]
+fn drained_nop_waits_for_earlier_timeout() {
+    let ring = rio::new().unwrap();
+
+    let start = Instant::now();
+    let timeout = ring.timeout(Duration::from_millis(20));
+    ring.nop_ordered(rio::Ordering::Drain).wait().unwrap();
+    assert!(start.elapsed() >= Duration::from_millis(20));
+
+    timeout.wait().unwrap();
+}
+
+#[test]
+fn wait_timeout_cancels_stuck_read() {
+    use std::net::{TcpListener, TcpStream};
+
+    let ring = rio::new().unwrap();
+
+    let listener =
+        TcpListener::bind("127.0.0.1:0").unwrap();
+    let _client =
+        TcpStream::connect(listener.local_addr().unwrap())
+            .unwrap();
+    let (server, _) = listener.accept().unwrap();
+
+    let buf = &mut [0_u8; 16];
+    let start = Instant::now();
+    let read = ring
+        .recv(&server, buf)
+        .wait_timeout(Duration::from_millis(20))
+        .unwrap();
+    assert_eq!(read, None);
+    assert!(start.elapsed() >= Duration::from_millis(20));
+
+    let nop = ring
+        .nop()
+        .wait_timeout(Duration::from_secs(60))
+        .unwrap();
+    assert_eq!(nop, Some(()));
+
+    // a timeout past what an `Instant` can hold
+    // just waits.
+    let nop = ring
+        .nop()
+        .wait_timeout(Duration::from_secs(u64::MAX))
+        .unwrap();
+    assert_eq!(nop, Some(()));
+}
+
+#[test]
+fn with_deadline_bounds_pending_recv() {
+    use std::net::{TcpListener, TcpStream};
+
+    let ring = rio::new().unwrap();
+
+    let listener =
+        TcpListener::bind("127.0.0.1:0").unwrap();
+    let _client =
+        TcpStream::connect(listener.local_addr().unwrap())
+            .unwrap();
+    let (server, _) = listener.accept().unwrap();
+
+    let buf = vec![0_u8; 16];
+
+    let deadline =
+        Instant::now() + Duration::from_millis(20);
+    let err = ring
+        .recv(&server, &buf)
+        .with_deadline(deadline)
+        .wait()
+        .unwrap_err();
+    assert_eq!(err.raw_os_error(), Some(libc::ETIMEDOUT));
+    assert!(Instant::now() >= deadline);
+
+    // a deadline that already passed expires right
+    // away, also once the recv was submitted.
+    let recv = ring.recv(&server, &buf);
+    ring.submit_all().unwrap();
+    let start = Instant::now();
+    let err =
+        recv.with_deadline(deadline).wait().unwrap_err();
+    assert_eq!(err.raw_os_error(), Some(libc::ETIMEDOUT));
+    assert!(start.elapsed() < Duration::from_secs(1));
+}
+
+#[test]
+fn timeout_after_completions_fires_on_count_or_duration() {
+    let ring = rio::new().unwrap();
+
+    let start = Instant::now();
+    let timeout = ring.timeout_after_completions(
+        2,
+        Duration::from_secs(60),
+    );
+    ring.nop().wait().unwrap();
+    ring.nop().wait().unwrap();
+    timeout.wait().unwrap();
+    assert!(start.elapsed() < Duration::from_secs(60));
+
+    let start = Instant::now();
+    let timeout = ring.timeout_after_completions(
+        100,
+        Duration::from_millis(20),
+    );
+    ring.nop().wait().unwrap();
+    timeout.wait().unwrap();
+    assert!(start.elapsed() >= Duration::from_millis(20));
+}
@@ -13,9 +13,15 @@ fn test_vec_value() {
 
     let buffer: Vec<u8> = b"hello world!".to_vec();
     ring.write_at(&file, &buffer, 0).wait().unwrap();
-    ring.sync_file_range(&file, 0, buffer.len())
-        .wait()
-        .unwrap();
+    ring.sync_file_range(
+        &file,
+        0,
+        buffer.len() as u32,
+        libc::SYNC_FILE_RANGE_WRITE
+            | libc::SYNC_FILE_RANGE_WAIT_AFTER,
+    )
+    .wait()
+    .unwrap();
 
     let mut contents = vec![];
     file.read_to_end(&mut contents).unwrap();